@@ -3,24 +3,25 @@ mod codegen_state;
 mod error;
 mod function;
 mod instruction;
+mod register_allocator;
 mod slot;
 
-use std::collections::HashMap;
+use std::rc::Rc;
 
 use iced_x86::{
-    code_asm::{r10, r11, r8, r9, rax, rcx, rdi, rdx, rsi, AsmRegister64, CodeAssembler},
+    code_asm::{r11, AsmRegister64, CodeAssembler, CodeLabel},
     BlockEncoderOptions, DecoderOptions, Register,
 };
 
 use crate::{
-    codegen::x86_64::codegen_state::CodegenState,
-    ir::{self, Slot},
+    codegen::x86_64::codegen_state::CodegenState, compiler::CompilerError, ir, parser::Identifier,
     value::EncodedValue,
 };
 
 use self::{
-    abi::{emit_function_epilogue, emit_function_prelude},
+    abi::{emit_function_epilogue, emit_function_prelude, emit_tail_call_exit},
     instruction::codegen_instruction,
+    register_allocator::{allocate_registers, Allocation},
 };
 pub use self::{error::CodegenError, function::Function};
 
@@ -28,15 +29,30 @@ use super::CodegenResult;
 
 pub type FuncPointer = unsafe extern "C" fn() -> EncodedValue;
 
-pub fn codegen(block: ir::Block) -> CodegenResult<Function> {
+// Never handed out by the register allocator, so instructions can use it for
+// intermediate results without disturbing any slot.
+const SCRATCH_REGISTER: AsmRegister64 = r11;
+
+// Errors are reported along with the location of the instruction being
+// generated, if it has one.
+pub fn codegen(block: ir::Block) -> Result<Function, CompilerError> {
     let mut state = CodegenState::new();
-    let mut assembler = CodeAssembler::new(64)?;
+    // Passed to the runtime by the generated code if the stack overflows.
+    let name = block.function_name().map(|name| Rc::new(name.clone()));
+    let mut assembler = CodeAssembler::new(64).map_err(CodegenError::IcedError)?;
     let mut start_label = assembler.create_label();
 
-    assembler.set_label(&mut start_label)?;
-    codegen_block(&mut state, &mut assembler, block)?;
+    place_label(&mut assembler, &mut start_label)?;
+    let name_pointer = name.as_ref().map(Rc::as_ptr);
+    codegen_block(&mut state, &mut assembler, block, start_label, name_pointer)?;
 
-    let code_length = 4096; // TODO calculate this
+    // The code's length only becomes known once it's assembled, and it has to
+    // be assembled at the address it will run from, so it's assembled once to
+    // measure it and again into the memory mapped for it.
+    let code_length = assembler
+        .assemble(0)
+        .map_err(CodegenError::IcedError)?
+        .len();
 
     let mut memory_map = memmap2::MmapOptions::new()
         .len(code_length)
@@ -50,23 +66,39 @@ pub fn codegen(block: ir::Block) -> CodegenResult<Function> {
         )
         .map_err(CodegenError::IcedError)?;
 
-    let func_addr = result.label_ip(&start_label)?;
+    let func_addr = result
+        .label_ip(&start_label)
+        .map_err(CodegenError::IcedError)?;
 
-    let mut generated_code = result.inner.code_buffer;
-    // assert!(generated_code.len() == code_length);
+    let generated_code = result.inner.code_buffer;
+    if generated_code.len() != memory_map.len() {
+        return Err(CodegenError::InternalError(format!(
+            "generated code is {} bytes long, but {} bytes were mapped for it",
+            generated_code.len(),
+            memory_map.len()
+        ))
+        .into());
+    }
 
     print_generated_code(&generated_code, memory_map.as_ptr() as u64);
 
-    generated_code.resize(memory_map.len(), 0xcc);
     memory_map.copy_from_slice(&generated_code);
     let memory_map = memory_map.make_exec().map_err(CodegenError::MmapError)?;
 
     let function_pointer = unsafe { std::mem::transmute::<u64, FuncPointer>(func_addr) };
-    let function = Function::new(memory_map, function_pointer);
+    let function = Function::new(memory_map, function_pointer, state.into_locations(), name);
     Ok(function)
 }
 
-fn print_generated_code(generated_code: &Vec<u8>, ip: u64) {
+// Sets a label on the current position. A zero-length instruction is emitted
+// for it, because the assembler only allows one label per instruction.
+fn place_label(assembler: &mut CodeAssembler, label: &mut CodeLabel) -> CodegenResult<()> {
+    assembler.set_label(label)?;
+    assembler.zero_bytes()?;
+    Ok(())
+}
+
+fn print_generated_code(generated_code: &[u8], ip: u64) {
     let decoder = iced_x86::Decoder::with_ip(64, generated_code, ip, DecoderOptions::NONE);
 
     println!("Generated assembly:");
@@ -75,163 +107,58 @@ fn print_generated_code(generated_code: &Vec<u8>, ip: u64) {
     }
 }
 
+// Places in the function being generated that instructions can jump to.
+struct FunctionLabels {
+    // Before the prelude, where the function calls itself.
+    entry: CodeLabel,
+    // After the prelude, where a tail call to the function itself goes.
+    body: CodeLabel,
+    epilogue: CodeLabel,
+    // Where tail calls to other functions leave from.
+    tail_call: CodeLabel,
+}
+
 fn codegen_block(
     state: &mut CodegenState,
     assembler: &mut CodeAssembler,
-    block: ir::Block,
-) -> CodegenResult<()> {
-    let register_map = allocate_registers(&block);
-
-    let mut epilogue_label = assembler.create_label();
-
-    emit_function_prelude(assembler, &block)?;
+    mut block: ir::Block,
+    entry_label: CodeLabel,
+    name: Option<*const Identifier>,
+) -> Result<(), CompilerError> {
+    let register_map = loop {
+        match allocate_registers(&block)? {
+            Allocation::Registers(register_map) => break register_map,
+            Allocation::Spill(slot) => block.spill(slot),
+        }
+    };
 
-    for instruction in block.instructions() {
-        codegen_instruction(
-            state,
-            &register_map,
-            assembler,
-            instruction,
-            &epilogue_label,
-        )?;
+    println!("Register map:");
+    for (slot, register) in &register_map {
+        println!("  {}: {:?}", slot, Register::from(*register));
     }
+    println!();
 
-    assembler.set_label(&mut epilogue_label)?;
-    emit_function_epilogue(assembler, block)?;
-
-    Ok(())
-}
-
-fn allocate_registers(block: &ir::Block) -> HashMap<Slot, AsmRegister64> {
-    let mut free_registers = vec![r8, r9, r10, r11];
-
-    let mut register_map = HashMap::new();
+    let mut labels = FunctionLabels {
+        entry: entry_label,
+        body: assembler.create_label(),
+        epilogue: assembler.create_label(),
+        tail_call: assembler.create_label(),
+    };
 
-    fn assign_register(
-        register_map: &mut HashMap<Slot, AsmRegister64>,
-        slot: &Slot,
-        register: AsmRegister64,
-    ) {
-        if !register_map.contains_key(slot) {
-            register_map.insert(slot.clone(), register);
-        }
-    }
+    emit_function_prelude(assembler, &block, name)?;
+    place_label(assembler, &mut labels.body)?;
 
-    fn choose_register(
-        register_map: &mut HashMap<Slot, AsmRegister64>,
-        free_registers: &mut Vec<AsmRegister64>,
-        slot: &Slot,
-    ) -> AsmRegister64 {
-        if let Some(register) = register_map.get(slot) {
-            *register
-        } else {
-            let register = free_registers.pop().unwrap();
-            assign_register(register_map, slot, register);
-            register
-        }
-    }
-
-    fn release_register(
-        register_map: &mut HashMap<Slot, AsmRegister64>,
-        free_registers: &mut Vec<AsmRegister64>,
-        slot: &Slot,
-    ) {
-        if let Some(register) = register_map.get(slot) {
-            if !free_registers.contains(register) {
-                free_registers.push(*register);
-            }
-        }
+    for instruction in block.instructions() {
+        state.set_location(instruction.location());
+        codegen_instruction(state, &register_map, assembler, instruction, &labels)
+            .map_err(|error| CompilerError::CodegenError(error, instruction.location().cloned()))?;
     }
 
-    for instruction in block.instructions().iter().rev() {
-        match instruction {
-            ir::Instruction::Label(_) => {}
-            ir::Instruction::Assign(_, slot) => {
-                choose_register(&mut register_map, &mut free_registers, slot);
-            }
-            ir::Instruction::Opcode {
-                destination,
-                opcode,
-            } => match opcode {
-                ir::Opcode::Return => {}
-                ir::Opcode::Jump(condition, _) => match condition {
-                    ir::JumpCondition::Unconditional => {}
-                    ir::JumpCondition::Equal(lhs, rhs)
-                    | ir::JumpCondition::NotEqual(lhs, rhs)
-                    | ir::JumpCondition::Less(lhs, rhs)
-                    | ir::JumpCondition::Greater(lhs, rhs)
-                    | ir::JumpCondition::LessOrEqual(lhs, rhs)
-                    | ir::JumpCondition::GreaterOrEqual(lhs, rhs) => {
-                        choose_register(&mut register_map, &mut free_registers, lhs);
-                        choose_register(&mut register_map, &mut free_registers, rhs);
-                    }
-                    ir::JumpCondition::Zero(slot) | ir::JumpCondition::NotZero(slot) => {
-                        choose_register(&mut register_map, &mut free_registers, slot);
-                    }
-                },
-
-                ir::Opcode::SetReturnValue(slot) => {
-                    assign_register(&mut register_map, slot, rax);
-                }
-
-                ir::Opcode::Literal(_)
-                | ir::Opcode::FunctionArgument(_)
-                | ir::Opcode::StackVariable(_) => {
-                    release_register(&mut register_map, &mut free_registers, destination);
-                }
-
-                ir::Opcode::CallFunction(_, parameters) => {
-                    for (index, parameter) in parameters.iter().enumerate() {
-                        let register = match index {
-                            0 => rdi,
-                            1 => rsi,
-                            2 => rdx,
-                            3 => rcx,
-                            4 => r8,
-                            5 => r9,
-                            _ => panic!("too many arguments"),
-                        };
-                        assign_register(&mut register_map, parameter, register);
-                    }
-                }
-                ir::Opcode::BinaryOperator(lhs, _, rhs) => {
-                    let register = register_map
-                        .get(destination)
-                        .expect(&format!(
-                            "destination register not yet set for slot {destination}"
-                        ))
-                        .clone();
-
-                    assign_register(&mut register_map, destination, register);
-                    assign_register(&mut register_map, lhs, register);
-                    choose_register(&mut register_map, &mut free_registers, rhs);
-                }
-                ir::Opcode::PhiStart(slot) => {
-                    let register = register_map
-                        .get(destination)
-                        .expect(&format!(
-                            "destination register not yet set for slot {destination}"
-                        ))
-                        .clone();
-
-                    assign_register(&mut register_map, slot, register);
-                }
-                ir::Opcode::PhiEnd(slots) => {
-                    let register =
-                        choose_register(&mut register_map, &mut free_registers, destination);
-                    for slot in slots {
-                        assign_register(&mut register_map, slot, register);
-                    }
-                }
-            },
-        };
-    }
+    place_label(assembler, &mut labels.epilogue)?;
+    emit_function_epilogue(assembler, &block)?;
 
-    println!("Register map:");
-    for (slot, register) in &register_map {
-        println!("  {}: {:?}", slot, Register::from(*register));
-    }
-    println!();
+    place_label(assembler, &mut labels.tail_call)?;
+    emit_tail_call_exit(assembler, &block)?;
 
-    register_map
+    Ok(())
 }
@@ -1,10 +1,29 @@
 use iced_x86::code_asm::{
-    dword_ptr, r8, r9, rbp, rcx, rdi, rdx, rsi, rsp, AsmMemoryOperand, AsmRegister64, CodeAssembler,
+    qword_ptr, r10, r11, r8, r9, rax, rbp, rbx, rcx, rdi, rdx, rsi, rsp, AsmMemoryOperand,
+    AsmRegister64, CodeAssembler, CodeLabel,
 };
 
-use crate::{codegen::CodegenResult, ir};
+use crate::{
+    codegen::CodegenResult,
+    ir,
+    parser::{Identifier, Location},
+    runtime,
+    value::EncodedValue,
+};
+
+use super::{place_label, CodegenError, SCRATCH_REGISTER};
 
-use super::CodegenError;
+// Registers that the callee is allowed to clobber. These are saved on the
+// stack around every call made from generated code, so that values held in
+// them survive the call.
+const CALLER_SAVED_REGISTERS: [AsmRegister64; 9] = [rax, rcx, rdx, rsi, rdi, r8, r9, r10, r11];
+
+pub enum CallArgument {
+    Register(AsmRegister64),
+    Immediate(u64),
+    // A stack variable, read from the stack when the call is made.
+    StackVariable(usize),
+}
 
 pub fn parameter_register(index: usize) -> Result<AsmRegister64, CodegenError> {
     match index {
@@ -21,13 +40,223 @@ pub fn parameter_register(index: usize) -> Result<AsmRegister64, CodegenError> {
 }
 
 pub fn stack_variable_ref(offset: usize) -> AsmMemoryOperand {
-    dword_ptr(rbp - (8 + offset))
+    qword_ptr(rbp - 8 * (offset + 1))
+}
+
+// Where `register` was saved by `emit_call`, relative to `base`, which points
+// just past the saved registers.
+fn saved_register_ref(base: AsmRegister64, register: AsmRegister64) -> AsmMemoryOperand {
+    let index = CALLER_SAVED_REGISTERS
+        .iter()
+        .position(|saved| *saved == register)
+        .expect("register is not caller-saved");
+
+    qword_ptr(base + 8 * (CALLER_SAVED_REGISTERS.len() - 1 - index))
+}
+
+// Calls `target` using the System V calling convention, leaving the result in
+// `destination`. Every caller-saved register other than `destination` keeps
+// its value across the call, and arguments may be taken from any register
+// without worrying about the order they're moved in.
+pub fn emit_call(
+    assembler: &mut CodeAssembler,
+    target: u64,
+    arguments: &[CallArgument],
+    destination: AsmRegister64,
+) -> CodegenResult<()> {
+    emit_call_with(assembler, arguments, destination, |assembler| {
+        assembler.mov(rax, target)?;
+        assembler.call(rax)?;
+        Ok(())
+    })
+}
+
+// Like `emit_call`, but calls a label in the code being generated, which is
+// how a function calls itself.
+pub fn emit_label_call(
+    assembler: &mut CodeAssembler,
+    target: CodeLabel,
+    arguments: &[CallArgument],
+    destination: AsmRegister64,
+) -> CodegenResult<()> {
+    emit_call_with(assembler, arguments, destination, |assembler| {
+        assembler.call(target)?;
+        Ok(())
+    })
+}
+
+fn emit_call_with(
+    assembler: &mut CodeAssembler,
+    arguments: &[CallArgument],
+    destination: AsmRegister64,
+    emit_call_instruction: impl FnOnce(&mut CodeAssembler) -> CodegenResult<()>,
+) -> CodegenResult<()> {
+    emit_saving_registers(assembler, destination, |assembler| {
+        for (index, argument) in arguments.iter().enumerate() {
+            let parameter = parameter_register(index)?;
+            match argument {
+                CallArgument::Register(register) => {
+                    assembler.mov(parameter, saved_register_ref(rbx, *register))?
+                }
+                CallArgument::Immediate(value) => assembler.mov(parameter, *value)?,
+                CallArgument::StackVariable(offset) => {
+                    assembler.mov(parameter, stack_variable_ref(*offset))?
+                }
+            }
+        }
+
+        emit_call_instruction(assembler)
+    })
+}
+
+// Moves the arguments of a tail call into the parameter registers. They're
+// all pushed before any is popped into place, so an argument can come from a
+// register that another is moved into.
+pub fn emit_tail_call_arguments(
+    assembler: &mut CodeAssembler,
+    arguments: &[CallArgument],
+) -> CodegenResult<()> {
+    for argument in arguments {
+        match argument {
+            CallArgument::Register(register) => assembler.push(*register)?,
+            CallArgument::Immediate(value) => {
+                assembler.mov(SCRATCH_REGISTER, *value)?;
+                assembler.push(SCRATCH_REGISTER)?;
+            }
+            CallArgument::StackVariable(offset) => assembler.push(stack_variable_ref(*offset))?,
+        }
+    }
+
+    for index in (0..arguments.len()).rev() {
+        assembler.pop(parameter_register(index)?)?;
+    }
+
+    Ok(())
+}
+
+// Like `emit_call`, but the arguments are stored in an array on the stack, and
+// `target` is passed a pointer to the array along with the number of
+// arguments.
+pub fn emit_variadic_call(
+    assembler: &mut CodeAssembler,
+    target: u64,
+    arguments: &[CallArgument],
+    destination: AsmRegister64,
+) -> CodegenResult<()> {
+    emit_saving_registers(assembler, destination, |assembler| {
+        // Rounded up to keep the stack aligned.
+        let array_size = (arguments.len() + arguments.len() % 2) * 8;
+        assembler.sub(rsp, array_size as i32)?;
+
+        for (index, argument) in arguments.iter().enumerate() {
+            match argument {
+                CallArgument::Register(register) => {
+                    assembler.mov(rax, saved_register_ref(rbx, *register))?
+                }
+                CallArgument::Immediate(value) => assembler.mov(rax, *value)?,
+                CallArgument::StackVariable(offset) => {
+                    assembler.mov(rax, stack_variable_ref(*offset))?
+                }
+            }
+            assembler.mov(qword_ptr(rsp + 8 * index), rax)?;
+        }
+
+        assembler.mov(rdi, rsp)?;
+        assembler.mov(rsi, arguments.len() as u64)?;
+        assembler.mov(rax, target)?;
+        assembler.call(rax)?;
+
+        Ok(())
+    })
 }
 
+// Saves every caller-saved register and aligns the stack for a call made by
+// `emit_call_instructions`, then restores them with the result of the call
+// in `destination`. While the call is being set up, rbx points just past the
+// saved registers.
+fn emit_saving_registers(
+    assembler: &mut CodeAssembler,
+    destination: AsmRegister64,
+    emit_call_instructions: impl FnOnce(&mut CodeAssembler) -> CodegenResult<()>,
+) -> CodegenResult<()> {
+    for register in CALLER_SAVED_REGISTERS {
+        assembler.push(register)?;
+    }
+
+    // rbx is callee-saved, so it holds on to the unaligned stack pointer
+    // across the call.
+    assembler.push(rbx)?;
+    assembler.lea(rbx, qword_ptr(rsp + 8))?;
+    assembler.and(rsp, -16)?;
+
+    emit_call_instructions(assembler)?;
+
+    assembler.lea(rsp, qword_ptr(rbx - 8))?;
+    assembler.pop(rbx)?;
+
+    // Replace the saved copy of the destination register, so restoring the
+    // saved registers puts the result in place.
+    assembler.mov(saved_register_ref(rsp, destination), rax)?;
+    for register in CALLER_SAVED_REGISTERS.iter().rev() {
+        assembler.pop(*register)?;
+    }
+
+    Ok(())
+}
+
+// Returns early from the current function if `register` holds a runtime
+// error, passing the error along to our caller. The error is given
+// `location` first, unless it already has one from further down.
+pub fn emit_runtime_error_check(
+    assembler: &mut CodeAssembler,
+    register: AsmRegister64,
+    epilogue_label: &CodeLabel,
+    location: Option<*const Location>,
+) -> CodegenResult<()> {
+    let mut no_error_label = assembler.create_label();
+
+    assembler.cmp(register, EncodedValue::RUNTIME_ERROR as i32)?;
+    assembler.jne(no_error_label)?;
+    match location {
+        Some(location) => emit_call(
+            assembler,
+            runtime::risp_locate_error as *const () as u64,
+            &[CallArgument::Immediate(location as u64)],
+            rax,
+        )?,
+        None if register != rax => assembler.mov(rax, register)?,
+        None => {}
+    }
+    assembler.jmp(*epilogue_label)?;
+    place_label(assembler, &mut no_error_label)?;
+
+    Ok(())
+}
+
+// Functions check the stack pointer against the limit before anything else,
+// and return an error naming the function if it's been reached. Only code at
+// the top level, which can't be called recursively, goes without a name.
 pub fn emit_function_prelude(
     assembler: &mut CodeAssembler,
     block: &ir::Block,
+    name: Option<*const Identifier>,
 ) -> CodegenResult<()> {
+    if let Some(name) = name {
+        let mut within_limit_label = assembler.create_label();
+
+        assembler.mov(SCRATCH_REGISTER, runtime::stack_limit_address() as u64)?;
+        assembler.cmp(rsp, qword_ptr(SCRATCH_REGISTER))?;
+        assembler.jae(within_limit_label)?;
+        emit_call(
+            assembler,
+            runtime::risp_stack_overflow as *const () as u64,
+            &[CallArgument::Immediate(name as u64)],
+            rax,
+        )?;
+        assembler.ret()?;
+        place_label(assembler, &mut within_limit_label)?;
+    }
+
     if block.requires_stack_frame() {
         assembler.push(rbp)?;
         assembler.mov(rbp, rsp)?;
@@ -41,14 +270,29 @@ pub fn emit_function_prelude(
 
 pub fn emit_function_epilogue(
     assembler: &mut CodeAssembler,
-    block: ir::Block,
+    block: &ir::Block,
 ) -> CodegenResult<()> {
+    emit_leave_frame(assembler, block)?;
+    assembler.ret()?;
+
+    Ok(())
+}
+
+// Where tail calls to other functions leave from. The frame is given up, and
+// the function whose address is in the scratch register returns to our
+// caller in our place.
+pub fn emit_tail_call_exit(assembler: &mut CodeAssembler, block: &ir::Block) -> CodegenResult<()> {
+    emit_leave_frame(assembler, block)?;
+    assembler.jmp(SCRATCH_REGISTER)?;
+
+    Ok(())
+}
+
+fn emit_leave_frame(assembler: &mut CodeAssembler, block: &ir::Block) -> CodegenResult<()> {
     if block.requires_stack_frame() {
         assembler.mov(rsp, rbp)?;
         assembler.pop(rbp)?;
     }
 
-    assembler.ret()?;
-
     Ok(())
 }
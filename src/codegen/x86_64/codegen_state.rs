@@ -1,14 +1,18 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, rc::Rc};
 
 use iced_x86::code_asm::CodeAssembler;
 
-use crate::ir;
+use crate::{ir, parser::Location};
 
 use super::slot::SlotValue;
 
 pub struct CodegenState {
     pub slot_values: HashMap<ir::Slot, SlotValue>,
     labels: HashMap<ir::Label, iced_x86::code_asm::CodeLabel>,
+    // Generated code passes the location of anything that raises a runtime
+    // error to the runtime, so they're kept for as long as the code is.
+    locations: Vec<Rc<Location>>,
+    location: Option<*const Location>,
 }
 
 impl CodegenState {
@@ -16,9 +20,30 @@ impl CodegenState {
         Self {
             slot_values: HashMap::new(),
             labels: HashMap::new(),
+            locations: Vec::new(),
+            location: None,
         }
     }
 
+    // Sets the location of the instruction being generated. Consecutive
+    // instructions often come from the same code, so they share a copy.
+    pub fn set_location(&mut self, location: Option<&Location>) {
+        self.location = location.map(|location| {
+            if self.locations.last().map(Rc::as_ref) != Some(location) {
+                self.locations.push(Rc::new(location.clone()));
+            }
+            Rc::as_ptr(self.locations.last().unwrap())
+        });
+    }
+
+    pub fn location(&self) -> Option<*const Location> {
+        self.location
+    }
+
+    pub fn into_locations(self) -> Vec<Rc<Location>> {
+        self.locations
+    }
+
     pub fn label(
         &mut self,
         assembler: &mut CodeAssembler,
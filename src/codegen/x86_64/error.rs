@@ -1,12 +1,9 @@
-use crate::value::ValueEncodeError;
-
 #[derive(Debug)]
 pub enum CodegenError {
     IcedError(iced_x86::IcedError),
     MmapError(std::io::Error),
     NotImplemented(String),
     InternalError(String),
-    ValueEncodeError(ValueEncodeError),
     // ValueDecodeError(ValueDecodeError),
 }
 
@@ -1,7 +1,12 @@
+use std::rc::Rc;
+
 use memmap2::Mmap;
 
 use crate::{
     codegen::{self, FuncPointer},
+    heap,
+    parser::{Identifier, Location},
+    runtime::{self, RuntimeError},
     value::Value,
 };
 
@@ -10,6 +15,11 @@ pub struct Function {
     #[allow(dead_code)]
     memory_map: Mmap,
     ptr: codegen::FuncPointer,
+    // Pointed to by the generated code.
+    #[allow(dead_code)]
+    locations: Vec<Rc<Location>>,
+    #[allow(dead_code)]
+    name: Option<Rc<Identifier>>,
 }
 
 impl PartialEq for Function {
@@ -27,10 +37,15 @@ impl core::hash::Hash for Function {
 }
 
 impl Function {
-    pub fn call(&self) -> Value {
-        let result = unsafe { (self.ptr)() };
+    pub fn call(&self) -> Result<Value, (RuntimeError, Option<Location>)> {
+        let result = heap::with_stack_roots(|| unsafe { (self.ptr)() });
+
+        if result.is_runtime_error() {
+            return Err(runtime::take_error().expect("runtime error was not raised"));
+        }
+
         match result.try_into() {
-            Ok(value) => value,
+            Ok(value) => Ok(value),
             Err(err) => panic!("failed to decode value: {:?}", err),
         }
     }
@@ -39,8 +54,18 @@ impl Function {
         self.ptr as usize
     }
 
-    pub fn new(memory_map: Mmap, ptr: FuncPointer) -> Self {
-        Self { memory_map, ptr }
+    pub fn new(
+        memory_map: Mmap,
+        ptr: FuncPointer,
+        locations: Vec<Rc<Location>>,
+        name: Option<Rc<Identifier>>,
+    ) -> Self {
+        Self {
+            memory_map,
+            ptr,
+            locations,
+            name,
+        }
     }
 }
 
@@ -1,17 +1,24 @@
 use std::collections::HashMap;
 
-use iced_x86::code_asm::{rax, AsmRegister64, CodeAssembler, CodeLabel};
+use iced_x86::code_asm::{rax, rcx, rdx, rsi, AsmRegister64, CodeAssembler, CodeLabel};
 
 use crate::{
     codegen::CodegenResult,
     ir::{self, AssignmentTarget, Slot},
-    parser::{ArithmeticOperator, BinaryOperator},
+    parser::{ArithmeticOperator, BinaryOperator, Location},
+    runtime,
+    value::{EncodedValue, Value},
 };
 
 use super::{
-    abi::{parameter_register, stack_variable_ref},
+    abi::{
+        emit_call, emit_label_call, emit_runtime_error_check, emit_tail_call_arguments,
+        emit_variadic_call, parameter_register, stack_variable_ref, CallArgument,
+    },
     codegen_state::CodegenState,
-    slot::{slot_to_register, SlotValue},
+    place_label,
+    slot::{slot_to_call_argument, slot_to_register, SlotValue},
+    CodegenError, FunctionLabels, SCRATCH_REGISTER,
 };
 
 pub fn codegen_instruction(
@@ -19,16 +26,19 @@ pub fn codegen_instruction(
     register_map: &HashMap<Slot, AsmRegister64>,
     assembler: &mut CodeAssembler,
     instruction: &ir::Instruction,
-    epilogue_label: &CodeLabel,
+    labels: &FunctionLabels,
 ) -> CodegenResult<()> {
+    let epilogue_label = &labels.epilogue;
+
     match instruction {
         ir::Instruction::Label(label) => {
             let label = state.label(assembler, label);
-            assembler.set_label(label)?;
+            place_label(assembler, label)?;
         }
         ir::Instruction::Opcode {
             destination,
             opcode,
+            ..
         } => {
             match opcode {
                 ir::Opcode::Literal(literal) => {
@@ -37,40 +47,119 @@ pub fn codegen_instruction(
                         .insert(*destination, SlotValue::Literal(literal.clone()));
                 }
                 ir::Opcode::BinaryOperator(lhs, BinaryOperator::ArithmeticOperator(op), rhs) => {
+                    let constant_rhs = match state.slot_values.get(rhs) {
+                        Some(SlotValue::Literal(Value::Integer(value))) => Some(*value),
+                        _ => None,
+                    };
+
                     let lhs = slot_to_register(state, register_map, assembler, lhs)?;
                     let rhs = slot_to_register(state, register_map, assembler, rhs)?;
+                    let result = destination_register(register_map, destination);
 
-                    match op {
-                        ArithmeticOperator::Add => {
-                            assembler.add::<AsmRegister64, AsmRegister64>(lhs, rhs)?;
-                        }
-                        ArithmeticOperator::Multiply => {
-                            assembler.imul_2::<AsmRegister64, AsmRegister64>(lhs, rhs)?;
+                    let runtime_function = match op {
+                        ArithmeticOperator::Add => runtime::risp_add as *const (),
+                        ArithmeticOperator::Subtract => runtime::risp_subtract as *const (),
+                        ArithmeticOperator::Multiply => runtime::risp_multiply as *const (),
+                        ArithmeticOperator::Divide => runtime::risp_divide as *const (),
+                        ArithmeticOperator::Remainder => runtime::risp_remainder as *const (),
+                        ArithmeticOperator::Power => runtime::risp_power as *const (),
+                        ArithmeticOperator::BitwiseAnd => runtime::risp_bitwise_and as *const (),
+                        ArithmeticOperator::BitwiseOr => runtime::risp_bitwise_or as *const (),
+                        ArithmeticOperator::BitwiseXor => runtime::risp_bitwise_xor as *const (),
+                        ArithmeticOperator::ShiftLeft => runtime::risp_shift_left as *const (),
+                        ArithmeticOperator::ShiftRight => runtime::risp_shift_right as *const (),
+                        ArithmeticOperator::LogicalShiftRight => {
+                            runtime::risp_logical_shift_right as *const ()
                         }
-                        ArithmeticOperator::Subtract => {
-                            assembler.sub::<AsmRegister64, AsmRegister64>(lhs, rhs)?;
-                        }
-                        ArithmeticOperator::Divide => {
-                            todo!("division operator");
+                    };
+
+                    let mut done_label = assembler.create_label();
+
+                    if has_inline_fast_path(*op) {
+                        let mut slow_path_label = assembler.create_label();
+                        if *op == ArithmeticOperator::Power {
+                            emit_inline_power(assembler, lhs, rhs, constant_rhs, slow_path_label)?;
+                        } else {
+                            emit_inline_arithmetic(assembler, *op, lhs, rhs, slow_path_label)?;
                         }
+                        assembler.mov(result, SCRATCH_REGISTER)?;
+                        assembler.jmp(done_label)?;
+                        place_label(assembler, &mut slow_path_label)?;
                     }
 
+                    emit_call(
+                        assembler,
+                        runtime_function as u64,
+                        &[CallArgument::Register(lhs), CallArgument::Register(rhs)],
+                        result,
+                    )?;
+                    emit_runtime_error_check(assembler, result, epilogue_label, state.location())?;
+                    place_label(assembler, &mut done_label)?;
+
                     state
                         .slot_values
-                        .insert(*destination, SlotValue::Register(lhs));
+                        .insert(*destination, SlotValue::Register(result));
                 }
-                ir::Opcode::BinaryOperator(_lhs, BinaryOperator::ComparisonOperator(_op), _rhs) => {
-                    unimplemented!("comparison operators in expressions")
+                ir::Opcode::BinaryOperator(
+                    _,
+                    op @ (BinaryOperator::ComparisonOperator(_)
+                    | BinaryOperator::LogicalOperator(_)),
+                    _,
+                ) => {
+                    return Err(CodegenError::InternalError(format!(
+                        "{op} should have been compiled as a predicate"
+                    )))
                 }
-                ir::Opcode::CallFunction(func, args) => {
+                ir::Opcode::CallFunction(_, args) | ir::Opcode::CallBuiltin(_, args) => {
+                    let mut arguments = Vec::with_capacity(args.len());
                     for arg in args {
-                        slot_to_register(state, register_map, assembler, arg)?;
+                        arguments.push(slot_to_call_argument(state, register_map, assembler, arg)?);
+                    }
+
+                    let result = destination_register(register_map, destination);
+                    match opcode {
+                        ir::Opcode::CallFunction(ir::Callee::Function(func), _) => {
+                            emit_call(assembler, func.address() as u64, &arguments, result)?
+                        }
+                        ir::Opcode::CallFunction(ir::Callee::Current, _) => {
+                            emit_label_call(assembler, labels.entry, &arguments, result)?
+                        }
+                        ir::Opcode::CallBuiltin(builtin, _) if builtin.variadic => {
+                            emit_variadic_call(assembler, builtin.address(), &arguments, result)?
+                        }
+                        ir::Opcode::CallBuiltin(builtin, _) => {
+                            emit_call(assembler, builtin.address(), &arguments, result)?
+                        }
+                        _ => unreachable!(),
                     }
+                    emit_runtime_error_check(assembler, result, epilogue_label, state.location())?;
 
-                    assembler.call(func.address() as u64)?;
                     state
                         .slot_values
-                        .insert(*destination, SlotValue::Register(rax));
+                        .insert(*destination, SlotValue::Register(result));
+                }
+                ir::Opcode::TailCall(callee, args) => {
+                    let mut arguments = Vec::with_capacity(args.len());
+                    for arg in args {
+                        arguments.push(slot_to_call_argument(state, register_map, assembler, arg)?);
+                    }
+
+                    emit_tail_call_arguments(assembler, &arguments)?;
+                    match callee {
+                        ir::Callee::Current => assembler.jmp(labels.body)?,
+                        ir::Callee::Function(func) => {
+                            assembler.mov(SCRATCH_REGISTER, func.address() as u64)?;
+                            assembler.jmp(labels.tail_call)?;
+                        }
+                    }
+
+                    // Nothing after a tail call runs, but its result can still
+                    // be referred to, such as by the end of a condition it's
+                    // in a branch of.
+                    state.slot_values.insert(
+                        *destination,
+                        SlotValue::Register(destination_register(register_map, destination)),
+                    );
                 }
                 ir::Opcode::FunctionArgument(index) => {
                     state
@@ -95,70 +184,56 @@ pub fn codegen_instruction(
                         ir::JumpCondition::Unconditional => {
                             assembler.jmp(label)?;
                         }
-                        ir::JumpCondition::Zero(identifier) => {
-                            let register =
-                                slot_to_register(state, register_map, assembler, identifier)?;
-                            assembler.test(register, register)?;
-                            assembler.jz(label)?;
+                        ir::JumpCondition::Truthy(slot) => {
+                            let register = slot_to_register(state, register_map, assembler, slot)?;
+                            emit_truthy_jump(assembler, register, label)?;
                         }
-                        ir::JumpCondition::NotZero(identifier) => {
-                            let register =
-                                slot_to_register(state, register_map, assembler, identifier)?;
-                            assembler.test(register, register)?;
-                            assembler.jnz(label)?;
+                        ir::JumpCondition::StrictlyTrue(slot) => {
+                            let register = slot_to_register(state, register_map, assembler, slot)?;
+                            emit_strictly_true_jump(
+                                assembler,
+                                register,
+                                label,
+                                epilogue_label,
+                                state.location(),
+                            )?;
                         }
-                        ir::JumpCondition::Equal(lhs, rhs) => {
+                        ir::JumpCondition::Equal(lhs, rhs)
+                        | ir::JumpCondition::NotEqual(lhs, rhs)
+                        | ir::JumpCondition::Greater(lhs, rhs)
+                        | ir::JumpCondition::GreaterOrEqual(lhs, rhs)
+                        | ir::JumpCondition::Less(lhs, rhs)
+                        | ir::JumpCondition::LessOrEqual(lhs, rhs) => {
                             let lhs_register =
                                 slot_to_register(state, register_map, assembler, lhs)?;
                             let rhs_register =
                                 slot_to_register(state, register_map, assembler, rhs)?;
-                            assembler.cmp(lhs_register, rhs_register)?;
-                            assembler.je(label)?;
-                        }
-                        ir::JumpCondition::NotEqual(lhs, rhs) => {
-                            let lhs_register =
-                                slot_to_register(state, register_map, assembler, lhs)?;
-                            let rhs_register =
-                                slot_to_register(state, register_map, assembler, rhs)?;
-                            assembler.cmp(lhs_register, rhs_register)?;
-                            assembler.jne(label)?;
-                        }
-                        ir::JumpCondition::Greater(lhs, rhs) => {
-                            let lhs_register =
-                                slot_to_register(state, register_map, assembler, lhs)?;
-                            let rhs_register =
-                                slot_to_register(state, register_map, assembler, rhs)?;
-                            assembler.cmp(lhs_register, rhs_register)?;
-                            assembler.jg(label)?;
-                        }
-                        ir::JumpCondition::GreaterOrEqual(lhs, rhs) => {
-                            let lhs_register =
-                                slot_to_register(state, register_map, assembler, lhs)?;
-                            let rhs_register =
-                                slot_to_register(state, register_map, assembler, rhs)?;
-                            assembler.cmp(lhs_register, rhs_register)?;
-                            assembler.jge(label)?;
-                        }
-                        ir::JumpCondition::Less(lhs, rhs) => {
-                            let lhs_register =
-                                slot_to_register(state, register_map, assembler, lhs)?;
-                            let rhs_register =
-                                slot_to_register(state, register_map, assembler, rhs)?;
-                            assembler.cmp(lhs_register, rhs_register)?;
-                            assembler.jl(label)?;
-                        }
-                        ir::JumpCondition::LessOrEqual(lhs, rhs) => {
-                            let lhs_register =
-                                slot_to_register(state, register_map, assembler, lhs)?;
-                            let rhs_register =
-                                slot_to_register(state, register_map, assembler, rhs)?;
-                            assembler.cmp(lhs_register, rhs_register)?;
-                            assembler.jle(label)?;
+                            emit_comparison(
+                                assembler,
+                                lhs_register,
+                                rhs_register,
+                                epilogue_label,
+                                state.location(),
+                            )?;
+
+                            match condition {
+                                ir::JumpCondition::Equal(_, _) => assembler.je(label)?,
+                                ir::JumpCondition::NotEqual(_, _) => assembler.jne(label)?,
+                                ir::JumpCondition::Greater(_, _) => assembler.jg(label)?,
+                                ir::JumpCondition::GreaterOrEqual(_, _) => assembler.jge(label)?,
+                                ir::JumpCondition::Less(_, _) => assembler.jl(label)?,
+                                ir::JumpCondition::LessOrEqual(_, _) => assembler.jle(label)?,
+                                _ => unreachable!(),
+                            }
                         }
                     };
                 }
                 ir::Opcode::PhiStart(slot) => {
-                    let _ = slot_to_register(state, register_map, assembler, slot)?;
+                    let value = slot_to_register(state, register_map, assembler, slot)?;
+                    let result = destination_register(register_map, destination);
+                    if value != result {
+                        assembler.mov(result, value)?;
+                    }
                 }
                 ir::Opcode::PhiEnd(_slots) => {
                     state.slot_values.insert(*destination, SlotValue::Phi);
@@ -181,3 +256,333 @@ pub fn codegen_instruction(
 
     Ok(())
 }
+
+// The register the allocator chose for an instruction's result. Results that
+// are never used don't get one, so they go in the scratch register instead.
+fn destination_register(
+    register_map: &HashMap<Slot, AsmRegister64>,
+    destination: &Slot,
+) -> AsmRegister64 {
+    register_map
+        .get(destination)
+        .copied()
+        .unwrap_or(SCRATCH_REGISTER)
+}
+
+// Compares two values, leaving the flags set as if they were compared with a
+// signed `cmp`. Inline integers are compared directly, and anything else is
+// compared by the runtime, which raises an error if the types don't match.
+fn emit_comparison(
+    assembler: &mut CodeAssembler,
+    lhs: AsmRegister64,
+    rhs: AsmRegister64,
+    epilogue_label: &CodeLabel,
+    error_location: Option<*const Location>,
+) -> CodegenResult<()> {
+    let scratch = SCRATCH_REGISTER;
+    let mut slow_path_label = assembler.create_label();
+    let mut done_label = assembler.create_label();
+
+    assembler.mov(scratch, lhs)?;
+    assembler.or(scratch, rhs)?;
+    assembler.test(scratch, EncodedValue::TAG_MASK as i32)?;
+    assembler.jnz(slow_path_label)?;
+    assembler.cmp(lhs, rhs)?;
+    assembler.jmp(done_label)?;
+
+    // The runtime returns the ordering of the operands as an integer, which
+    // is compared against zero to set the flags.
+    place_label(assembler, &mut slow_path_label)?;
+    emit_call(
+        assembler,
+        runtime::risp_compare as *const () as u64,
+        &[CallArgument::Register(lhs), CallArgument::Register(rhs)],
+        scratch,
+    )?;
+    emit_runtime_error_check(assembler, scratch, epilogue_label, error_location)?;
+    assembler.cmp(scratch, 0)?;
+    place_label(assembler, &mut done_label)?;
+
+    Ok(())
+}
+
+// Jumps to `label` if the value in `register` counts as true. Booleans and
+// inline integers are checked inline, and anything else is left to the
+// runtime.
+fn emit_truthy_jump(
+    assembler: &mut CodeAssembler,
+    register: AsmRegister64,
+    label: CodeLabel,
+) -> CodegenResult<()> {
+    let scratch = SCRATCH_REGISTER;
+    let mut slow_path_label = assembler.create_label();
+    let mut not_taken_label = assembler.create_label();
+
+    assembler.cmp(register, EncodedValue::TRUE as i32)?;
+    assembler.je(label)?;
+    assembler.cmp(register, EncodedValue::FALSE as i32)?;
+    assembler.je(not_taken_label)?;
+
+    // An inline integer is true unless it's zero, which is encoded as zero.
+    assembler.test(register, EncodedValue::TAG_MASK as i32)?;
+    assembler.jnz(slow_path_label)?;
+    assembler.test(register, register)?;
+    assembler.jnz(label)?;
+    assembler.jmp(not_taken_label)?;
+
+    place_label(assembler, &mut slow_path_label)?;
+    emit_call(
+        assembler,
+        runtime::risp_truthy as *const () as u64,
+        &[CallArgument::Register(register)],
+        scratch,
+    )?;
+    assembler.cmp(scratch, EncodedValue::TRUE as i32)?;
+    assembler.je(label)?;
+    place_label(assembler, &mut not_taken_label)?;
+
+    Ok(())
+}
+
+// Jumps to `label` if `register` holds `true`. Anything other than a boolean
+// raises a runtime error.
+fn emit_strictly_true_jump(
+    assembler: &mut CodeAssembler,
+    register: AsmRegister64,
+    label: CodeLabel,
+    epilogue_label: &CodeLabel,
+    error_location: Option<*const Location>,
+) -> CodegenResult<()> {
+    let scratch = SCRATCH_REGISTER;
+    let mut not_taken_label = assembler.create_label();
+
+    assembler.cmp(register, EncodedValue::TRUE as i32)?;
+    assembler.je(label)?;
+    assembler.cmp(register, EncodedValue::FALSE as i32)?;
+    assembler.je(not_taken_label)?;
+
+    emit_call(
+        assembler,
+        runtime::risp_require_boolean as *const () as u64,
+        &[CallArgument::Register(register)],
+        scratch,
+    )?;
+    emit_runtime_error_check(assembler, scratch, epilogue_label, error_location)?;
+    place_label(assembler, &mut not_taken_label)?;
+
+    Ok(())
+}
+
+// Computes the result of an arithmetic operation on two inline integers into
+// the scratch register. Jumps to `slow_path_label` instead if either operand
+// isn't an inline integer or the result doesn't fit in one.
+fn emit_inline_arithmetic(
+    assembler: &mut CodeAssembler,
+    op: ArithmeticOperator,
+    lhs: AsmRegister64,
+    rhs: AsmRegister64,
+    slow_path_label: CodeLabel,
+) -> CodegenResult<()> {
+    let scratch = SCRATCH_REGISTER;
+
+    emit_inline_integer_check(assembler, lhs, rhs, slow_path_label)?;
+
+    // The result is built up in the scratch register, so the operands are
+    // left alone if the operation overflows.
+    match op {
+        ArithmeticOperator::Add => {
+            assembler.mov(scratch, lhs)?;
+            assembler.add::<AsmRegister64, AsmRegister64>(scratch, rhs)?;
+        }
+        ArithmeticOperator::Subtract => {
+            assembler.mov(scratch, lhs)?;
+            assembler.sub::<AsmRegister64, AsmRegister64>(scratch, rhs)?;
+        }
+        ArithmeticOperator::Multiply => {
+            // Only one side keeps its tag shift, so the product is shifted
+            // just once.
+            assembler.mov(scratch, rhs)?;
+            assembler.sar(scratch, EncodedValue::TAG_BITS as u32)?;
+            assembler.imul_2::<AsmRegister64, AsmRegister64>(scratch, lhs)?;
+        }
+        // Inline integers keep their value in the high bits and zeros in the
+        // tag bits, so these give an encoded integer with a zero tag too.
+        ArithmeticOperator::BitwiseAnd => {
+            assembler.mov(scratch, lhs)?;
+            assembler.and::<AsmRegister64, AsmRegister64>(scratch, rhs)?;
+        }
+        ArithmeticOperator::BitwiseOr => {
+            assembler.mov(scratch, lhs)?;
+            assembler.or::<AsmRegister64, AsmRegister64>(scratch, rhs)?;
+        }
+        ArithmeticOperator::BitwiseXor => {
+            assembler.mov(scratch, lhs)?;
+            assembler.xor::<AsmRegister64, AsmRegister64>(scratch, rhs)?;
+        }
+        ArithmeticOperator::Divide
+        | ArithmeticOperator::Remainder
+        | ArithmeticOperator::Power
+        | ArithmeticOperator::ShiftLeft
+        | ArithmeticOperator::ShiftRight
+        | ArithmeticOperator::LogicalShiftRight => {
+            return Err(CodegenError::InternalError(format!(
+                "{op} can't be done inline"
+            )))
+        }
+    }
+
+    assembler.jo(slow_path_label)?;
+
+    Ok(())
+}
+
+// Inline integers have a zero tag, so if either operand has any tag bits set
+// it has to go to the runtime.
+fn emit_inline_integer_check(
+    assembler: &mut CodeAssembler,
+    lhs: AsmRegister64,
+    rhs: AsmRegister64,
+    slow_path_label: CodeLabel,
+) -> CodegenResult<()> {
+    assembler.mov(SCRATCH_REGISTER, lhs)?;
+    assembler.or(SCRATCH_REGISTER, rhs)?;
+    assembler.test(SCRATCH_REGISTER, EncodedValue::TAG_MASK as i32)?;
+    assembler.jnz(slow_path_label)?;
+    Ok(())
+}
+
+// Raises an inline integer to the power of another by squaring and
+// multiplying, leaving the result in the scratch register. Like the other
+// operators, it jumps to `slow_path_label` if an operand isn't an inline
+// integer or the result doesn't fit in one, and also for a negative
+// exponent, which the runtime reports.
+//
+// The result is kept encoded and multiplied by successive squares of the
+// base, which are kept decoded. A square that overflows would make any
+// result it's needed for overflow too, so it's only computed if there are
+// more bits of the exponent to go.
+fn emit_inline_power(
+    assembler: &mut CodeAssembler,
+    base: AsmRegister64,
+    exponent: AsmRegister64,
+    constant_exponent: Option<i64>,
+    slow_path_label: CodeLabel,
+) -> CodegenResult<()> {
+    let result = SCRATCH_REGISTER;
+    let one = 1 << EncodedValue::TAG_BITS;
+
+    // The squares, and the bits of the exponent still to go, are kept in
+    // registers that aren't holding either operand. Those may be holding
+    // something else, so they're saved while they're borrowed and put back
+    // on the way out, before going to the runtime too.
+    let mut free = [rax, rcx, rdx, rsi]
+        .into_iter()
+        .filter(|&register| register != base && register != exponent);
+    let (square, counter) = match (free.next(), free.next()) {
+        (Some(square), Some(counter)) => (square, counter),
+        _ => unreachable!("only two registers can hold operands"),
+    };
+
+    let mut overflow_label = assembler.create_label();
+    let mut done_label = assembler.create_label();
+
+    // A small constant exponent is unrolled into just the multiplications
+    // its bits call for.
+    if let Some(constant_exponent @ 0..=63) = constant_exponent {
+        assembler.test(base, EncodedValue::TAG_MASK as i32)?;
+        assembler.jnz(slow_path_label)?;
+
+        if constant_exponent == 0 {
+            assembler.mov(result, one as u64)?;
+            return Ok(());
+        }
+
+        assembler.push(square)?;
+        assembler.mov(square, base)?;
+        assembler.sar(square, EncodedValue::TAG_BITS as u32)?;
+
+        let mut remaining = constant_exponent;
+        let mut started = false;
+        while remaining != 0 {
+            if remaining & 1 != 0 {
+                if started {
+                    assembler.imul_2::<AsmRegister64, AsmRegister64>(result, square)?;
+                } else {
+                    assembler.imul_3::<AsmRegister64, AsmRegister64, i32>(result, square, one)?;
+                    started = true;
+                }
+                assembler.jo(overflow_label)?;
+            }
+
+            remaining >>= 1;
+            if remaining != 0 {
+                assembler.imul_2::<AsmRegister64, AsmRegister64>(square, square)?;
+                assembler.jo(overflow_label)?;
+            }
+        }
+        assembler.jmp(done_label)?;
+
+        place_label(assembler, &mut overflow_label)?;
+        assembler.pop(square)?;
+        assembler.jmp(slow_path_label)?;
+
+        place_label(assembler, &mut done_label)?;
+        assembler.pop(square)?;
+
+        return Ok(());
+    }
+
+    emit_inline_integer_check(assembler, base, exponent, slow_path_label)?;
+    assembler.test(exponent, exponent)?;
+    assembler.js(slow_path_label)?;
+
+    let mut loop_label = assembler.create_label();
+    let mut square_label = assembler.create_label();
+
+    assembler.push(square)?;
+    assembler.push(counter)?;
+    assembler.mov(square, base)?;
+    assembler.sar(square, EncodedValue::TAG_BITS as u32)?;
+    assembler.mov(counter, exponent)?;
+    assembler.sar(counter, EncodedValue::TAG_BITS as u32)?;
+    assembler.mov(result, one as u64)?;
+
+    place_label(assembler, &mut loop_label)?;
+    assembler.test(counter, 1)?;
+    assembler.jz(square_label)?;
+    assembler.imul_2::<AsmRegister64, AsmRegister64>(result, square)?;
+    assembler.jo(overflow_label)?;
+
+    place_label(assembler, &mut square_label)?;
+    assembler.shr(counter, 1)?;
+    assembler.jz(done_label)?;
+    assembler.imul_2::<AsmRegister64, AsmRegister64>(square, square)?;
+    assembler.jo(overflow_label)?;
+    assembler.jmp(loop_label)?;
+
+    place_label(assembler, &mut overflow_label)?;
+    assembler.pop(counter)?;
+    assembler.pop(square)?;
+    assembler.jmp(slow_path_label)?;
+
+    place_label(assembler, &mut done_label)?;
+    assembler.pop(counter)?;
+    assembler.pop(square)?;
+
+    Ok(())
+}
+
+// Division and remainder always go through the runtime, which takes care of
+// division by zero. So do shifts, since their amount needs checking and x86
+// only takes a variable amount in cl, which may be holding a function
+// argument.
+fn has_inline_fast_path(op: ArithmeticOperator) -> bool {
+    !matches!(
+        op,
+        ArithmeticOperator::Divide
+            | ArithmeticOperator::Remainder
+            | ArithmeticOperator::ShiftLeft
+            | ArithmeticOperator::ShiftRight
+            | ArithmeticOperator::LogicalShiftRight
+    )
+}
@@ -0,0 +1,251 @@
+use std::collections::{HashMap, HashSet};
+
+use iced_x86::code_asm::{r10, r8, r9, rax, rcx, rdi, rdx, rsi, AsmRegister64};
+
+use crate::{
+    codegen::CodegenResult,
+    ir::{self, AssignmentTarget, Slot},
+};
+
+use super::{abi::parameter_register, CodegenError};
+
+// Registers that can hold values, in the reverse of the order they're handed
+// out in. Parameter registers are only used if the function doesn't refer to
+// the argument passed in them.
+const REGISTERS: [AsmRegister64; 8] = [rax, rdi, rsi, rdx, rcx, r8, r9, r10];
+
+pub enum Allocation {
+    Registers(HashMap<Slot, AsmRegister64>),
+    // There weren't enough registers to go round, and keeping this slot on the
+    // stack would free one up.
+    Spill(Slot),
+}
+
+struct OutOfRegisters;
+
+// Walks backwards through the instructions, so a slot takes a register at its
+// last use and gives it back at the instruction that defines it. Literals and
+// stack variables are only put in a register by the instruction using them,
+// so they hold one for no longer than that if they're used once.
+pub fn allocate_registers(block: &ir::Block) -> CodegenResult<Allocation> {
+    let mut allocator = RegisterAllocator::new(block);
+
+    for instruction in block.instructions().iter().rev() {
+        if allocator.allocate(instruction).is_err() {
+            return match allocator.spill_candidate(&instruction.operands()) {
+                Some(slot) => Ok(Allocation::Spill(slot)),
+                None => Err(CodegenError::InternalError(
+                    "ran out of registers with nothing to spill".to_owned(),
+                )),
+            };
+        }
+    }
+
+    Ok(Allocation::Registers(allocator.register_map))
+}
+
+struct RegisterAllocator<'a> {
+    definitions: HashMap<Slot, &'a ir::Opcode>,
+    use_counts: HashMap<Slot, usize>,
+    free_registers: Vec<AsmRegister64>,
+    // The registers that aren't free, and the slots holding them.
+    held_registers: Vec<(Slot, AsmRegister64)>,
+    register_map: HashMap<Slot, AsmRegister64>,
+}
+
+impl<'a> RegisterAllocator<'a> {
+    fn new(block: &'a ir::Block) -> Self {
+        let mut definitions = HashMap::new();
+        let mut use_counts = HashMap::new();
+        let mut arguments = HashSet::new();
+
+        for instruction in block.instructions() {
+            for operand in instruction.operands() {
+                *use_counts.entry(operand).or_insert(0) += 1;
+            }
+
+            match instruction {
+                ir::Instruction::Opcode {
+                    destination,
+                    opcode,
+                    ..
+                } => {
+                    if let ir::Opcode::FunctionArgument(index) = opcode {
+                        arguments.insert(*index);
+                    }
+                    definitions.insert(*destination, opcode);
+                }
+                ir::Instruction::Assign(AssignmentTarget::FunctionArgument(index), _) => {
+                    arguments.insert(*index);
+                }
+                ir::Instruction::Assign(AssignmentTarget::StackVariable(_), _)
+                | ir::Instruction::Label(_) => {}
+            }
+        }
+
+        let argument_registers = arguments
+            .into_iter()
+            .filter_map(|index| parameter_register(index).ok())
+            .collect::<Vec<_>>();
+
+        Self {
+            definitions,
+            use_counts,
+            free_registers: REGISTERS
+                .into_iter()
+                .filter(|register| !argument_registers.contains(register))
+                .collect(),
+            held_registers: Vec::new(),
+            register_map: HashMap::new(),
+        }
+    }
+
+    fn allocate(&mut self, instruction: &ir::Instruction) -> Result<(), OutOfRegisters> {
+        let ir::Instruction::Opcode {
+            destination,
+            opcode,
+            ..
+        } = instruction
+        else {
+            if let ir::Instruction::Assign(_, slot) = instruction {
+                self.use_slot(*slot, None)?;
+                self.release_rematerialized(&[*slot]);
+            }
+            return Ok(());
+        };
+
+        // Nothing before the instruction needs the value it defines. Its
+        // operands are all read before it's written, so they can be given the
+        // same register.
+        self.release(*destination);
+        let destination_register = self.register_map.get(destination).copied();
+
+        match opcode {
+            ir::Opcode::Literal(_)
+            | ir::Opcode::FunctionArgument(_)
+            | ir::Opcode::StackVariable(_)
+            | ir::Opcode::Return => {}
+            ir::Opcode::SetReturnValue(slot) => self.use_slot(*slot, Some(rax))?,
+            ir::Opcode::Jump(..) => {
+                for operand in instruction.operands() {
+                    self.use_slot(operand, None)?;
+                }
+            }
+            ir::Opcode::BinaryOperator(lhs, _, rhs) => {
+                self.use_slot(*lhs, destination_register)?;
+                self.use_slot(*rhs, None)?;
+            }
+            // Literals are passed to calls as immediates, and stack variables
+            // straight from the stack, so they don't need a register.
+            ir::Opcode::CallFunction(_, arguments)
+            | ir::Opcode::TailCall(_, arguments)
+            | ir::Opcode::CallBuiltin(_, arguments) => {
+                for argument in arguments {
+                    if !self.is_rematerializable(argument) {
+                        self.use_slot(*argument, None)?;
+                    }
+                }
+            }
+            // Each branch moves its value into the register the result of the
+            // condition is in. It's held from the end of the first branch, as
+            // the later ones come after it.
+            ir::Opcode::PhiStart(slot) => self.use_slot(*slot, destination_register)?,
+            ir::Opcode::PhiEnd(slots) => {
+                if let (Some(register), Some(first)) = (destination_register, slots.first()) {
+                    for slot in slots {
+                        self.register_map.insert(*slot, register);
+                    }
+                    self.use_slot(*first, Some(register))?;
+                }
+            }
+        }
+
+        self.release_rematerialized(&instruction.operands());
+        Ok(())
+    }
+
+    // Makes sure `slot` has a register, which it holds until the instruction
+    // that defines it, taking `preferred` if it's free.
+    fn use_slot(
+        &mut self,
+        slot: Slot,
+        preferred: Option<AsmRegister64>,
+    ) -> Result<(), OutOfRegisters> {
+        // Arguments stay in the registers they were passed in.
+        if matches!(
+            self.definitions.get(&slot),
+            None | Some(ir::Opcode::FunctionArgument(_))
+        ) {
+            return Ok(());
+        }
+
+        let register = match self.register_map.get(&slot) {
+            Some(register) => *register,
+            None => preferred
+                .filter(|register| self.free_registers.contains(register))
+                .or_else(|| self.free_registers.last().copied())
+                .ok_or(OutOfRegisters)?,
+        };
+
+        if !self.held_registers.contains(&(slot, register)) {
+            let Some(index) = self
+                .free_registers
+                .iter()
+                .position(|free| *free == register)
+            else {
+                return Err(OutOfRegisters);
+            };
+
+            self.free_registers.remove(index);
+            self.held_registers.push((slot, register));
+        }
+
+        self.register_map.insert(slot, register);
+        Ok(())
+    }
+
+    fn release(&mut self, slot: Slot) {
+        if let Some(index) = self
+            .held_registers
+            .iter()
+            .position(|(held_by, _)| *held_by == slot)
+        {
+            let (_, register) = self.held_registers.remove(index);
+            self.free_registers.push(register);
+        }
+    }
+
+    // Literals and stack variables used just once are put in their register
+    // by the instruction using them, so nothing before it needs the register.
+    fn release_rematerialized(&mut self, operands: &[Slot]) {
+        for operand in operands {
+            if self.is_rematerializable(operand) && self.use_counts.get(operand) == Some(&1) {
+                self.release(*operand);
+            }
+        }
+    }
+
+    fn is_rematerializable(&self, slot: &Slot) -> bool {
+        matches!(
+            self.definitions.get(slot),
+            Some(ir::Opcode::Literal(_) | ir::Opcode::StackVariable(_))
+        )
+    }
+
+    // A slot holding a register that keeping on the stack would free up,
+    // preferring one the instruction being allocated doesn't use.
+    fn spill_candidate(&self, operands: &[Slot]) -> Option<Slot> {
+        let candidates = self
+            .held_registers
+            .iter()
+            .map(|(slot, _)| *slot)
+            .filter(|slot| {
+                !self.is_rematerializable(slot) || self.use_counts.get(slot) != Some(&1)
+            });
+
+        candidates
+            .clone()
+            .find(|slot| !operands.contains(slot))
+            .or_else(|| candidates.clone().next())
+    }
+}
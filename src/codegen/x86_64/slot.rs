@@ -9,7 +9,7 @@ use crate::{
 };
 
 use super::{
-    abi::{parameter_register, stack_variable_ref},
+    abi::{parameter_register, stack_variable_ref, CallArgument},
     codegen_state::CodegenState,
     CodegenError,
 };
@@ -31,20 +31,18 @@ pub fn slot_to_register(
 ) -> CodegenResult<AsmRegister64> {
     let slot_value = state.slot_values.get(slot);
     match slot_value {
-        Some(SlotValue::Register(register)) => Ok(register.clone()),
+        Some(SlotValue::Register(register)) => Ok(*register),
         Some(SlotValue::Literal(literal)) => {
-            let value: EncodedValue = literal.try_into().map_err(CodegenError::ValueEncodeError)?;
             let reg = register_map
                 .get(slot)
-                .expect(&format!("no register mapped for slot {slot}"));
+                .unwrap_or_else(|| panic!("no register mapped for slot {slot}"));
 
-            let value = unsafe { value.encoded_value() };
-            assembler.mov(*reg, value)?;
+            assembler.mov(*reg, encode_literal(literal))?;
 
             Ok(*reg)
         }
 
-        Some(SlotValue::FunctionArgument(index)) => Ok(parameter_register(*index)?.into()),
+        Some(SlotValue::FunctionArgument(index)) => parameter_register(*index),
 
         Some(SlotValue::StackOffset(offset)) => {
             let offset = *offset;
@@ -68,3 +66,28 @@ pub fn slot_to_register(
         ))),
     }
 }
+
+// Like `slot_to_register`, but literals are passed as immediates and stack
+// variables straight from the stack, rather than taking up a register.
+pub fn slot_to_call_argument(
+    state: &mut CodegenState,
+    register_map: &HashMap<Slot, AsmRegister64>,
+    assembler: &mut CodeAssembler,
+    slot: &ir::Slot,
+) -> CodegenResult<CallArgument> {
+    match state.slot_values.get(slot) {
+        Some(SlotValue::Literal(literal)) => Ok(CallArgument::Immediate(encode_literal(literal))),
+        Some(SlotValue::StackOffset(offset)) => Ok(CallArgument::StackVariable(*offset)),
+        _ => Ok(CallArgument::Register(slot_to_register(
+            state,
+            register_map,
+            assembler,
+            slot,
+        )?)),
+    }
+}
+
+fn encode_literal(literal: &Value) -> u64 {
+    let value = EncodedValue::constant(literal);
+    unsafe { value.encoded_value() }
+}
@@ -1,30 +1,48 @@
+mod analysis;
 mod error;
+mod lint;
 pub mod stack_frame;
 
 use crate::{
     codegen::{self, Function},
     ir::{self, AssignmentTarget, Instruction, Opcode, Slot},
     parser::{
-        Assignment, BinaryOperator, Block, ComparisonOperator, Condition, Expression, Identifier,
-        Literal, Loop, LoopPredicatePosition, Statement, VariableDeclaration,
+        ArithmeticOperator, Assignment, BinaryOperator, Block, ComparisonOperator, Condition,
+        Expression, Identifier, Literal, Located, Location, LogicalOperator, Loop,
+        LoopPredicatePosition, Statement, StringPart, UnaryOperator, VariableDeclaration,
     },
+    runtime,
     value::Value,
 };
 
 pub use self::{
+    analysis::{analyze_program, check_arguments},
     error::{CompileError, CompilerError},
+    lint::{lint, Lint, Lints, Warning},
     stack_frame::{StackFrame, Symbol},
 };
 
-pub type CompileResult<T = ir::Slot> = Result<T, CompileError>;
+pub type CompileResult<T = ir::Slot> = Result<T, CompilerError>;
 
-pub fn compile<'a>(
-    stack_frame: &'a mut StackFrame<'_>,
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CompileOptions {
+    // Only accept booleans in conditions, rather than deciding whether any
+    // value counts as true. Anything else is a runtime error.
+    pub strict_conditions: bool,
+    // Which warnings to report about code before it's compiled.
+    pub lints: Lints,
+}
+
+pub fn compile(
+    stack_frame: &mut StackFrame<'_>,
     block: &Block,
+    options: CompileOptions,
 ) -> Result<Function, CompilerError> {
     println!("AST:\n{:#?}\n", block);
 
-    let mut ir_block = ir::Block::new(stack_frame);
+    analysis::analyze(stack_frame, block)?;
+
+    let mut ir_block = ir::Block::new(stack_frame, options.strict_conditions);
     compile_function_body(&mut ir_block, block)?;
 
     println!("IR:\n{}", ir_block);
@@ -33,18 +51,39 @@ pub fn compile<'a>(
     Ok(function)
 }
 
-fn compile_statement(block: &mut ir::Block, statement: &Statement) -> CompileResult {
-    match statement {
+// Compiles code with `compile`, attributing the instructions it emits, and any
+// errors, to `location`.
+fn at_location<T>(
+    block: &mut ir::Block,
+    location: &Location,
+    compile: impl FnOnce(&mut ir::Block) -> CompileResult<T>,
+) -> CompileResult<T> {
+    let outer_location = block.set_location(Some(location.clone()));
+    let result = compile(block);
+    block.set_location(outer_location);
+    result
+}
+
+fn error<T>(block: &ir::Block, error: CompileError) -> CompileResult<T> {
+    Err(CompilerError::CompileError(
+        error,
+        block.location().cloned(),
+    ))
+}
+
+fn compile_statement(block: &mut ir::Block, statement: &Located<Statement>) -> CompileResult {
+    at_location(block, &statement.location, |block| match &statement.value {
         Statement::Expression(expression) => compile_expression(block, expression),
         Statement::FunctionDefinition(_definition) => compile_literal(block, &Literal::Integer(0)),
         Statement::VariableDeclaration(declaration) => {
             compile_variable_declaration(block, declaration)
         }
-        Statement::Condition(condition) => compile_condition_statement(block, condition),
+        Statement::Condition(condition) => compile_condition_statement(block, condition, false),
         Statement::Return(result) => compile_return_statement(block, result),
-        Statement::Assignment(assignment) => compile_assignment_statement(block, assignment),
+        Statement::Assignment(assignment) => compile_assignment(block, assignment),
         Statement::Loop(loop_statement) => compile_loop_statement(block, loop_statement),
-    }
+        Statement::Error(_) => unreachable!("statements with syntax errors are never compiled"),
+    })
 }
 
 fn compile_loop_statement(block: &mut ir::Block, loop_statement: &Loop) -> CompileResult {
@@ -60,7 +99,7 @@ fn compile_loop_statement(block: &mut ir::Block, loop_statement: &Loop) -> Compi
     }
 
     block.set_label(start_label.clone());
-    compile_block(block, &loop_statement.block)?;
+    compile_block(block, &loop_statement.block, false)?;
 
     block.set_label(test_label);
     compile_predicate(
@@ -75,34 +114,29 @@ fn compile_loop_statement(block: &mut ir::Block, loop_statement: &Loop) -> Compi
     Ok(Slot::new())
 }
 
+// Jumps to `true_target` if the predicate holds, and otherwise to
+// `false_target`, or falls through if there isn't one.
 fn compile_predicate(
     block: &mut ir::Block<'_, '_>,
-    predicate: &Expression,
+    predicate: &Located<Expression>,
     true_target: ir::Label,
     false_target: Option<ir::Label>,
 ) -> CompileResult {
-    match predicate {
-        Expression::Identifier(identifier) => {
-            let identifier = compile_identifier(block, identifier)?;
-            block.push_op(Opcode::Jump(
-                ir::JumpCondition::NotZero(identifier),
-                true_target,
-            ));
-            if let Some(false_target) = false_target {
-                block.push_op(Opcode::Jump(ir::JumpCondition::Unconditional, false_target));
-            }
-            Ok(Slot::new())
-        }
-        Expression::FunctionCall(_identifier, _expressions) => todo!("function call in predicate"),
-        Expression::Literal(_literal) => todo!("literal in predicate"),
-        Expression::BinaryExpression(_lhs, BinaryOperator::ArithmeticOperator(op), _rhs) => {
-            match op {
-                op => unimplemented!("predicate arithmetic operator {op:?}"),
-            }
-        }
+    at_location(block, &predicate.location, |block| {
+        compile_predicate_at(block, predicate, true_target, false_target)
+    })
+}
+
+fn compile_predicate_at(
+    block: &mut ir::Block<'_, '_>,
+    predicate: &Located<Expression>,
+    true_target: ir::Label,
+    false_target: Option<ir::Label>,
+) -> CompileResult {
+    match &predicate.value {
         Expression::BinaryExpression(lhs, BinaryOperator::ComparisonOperator(op), rhs) => {
             let lhs = compile_expression(block, lhs)?;
-            let rhs = compile_expression(block, rhs)?;
+            let (lhs, rhs) = compile_after(block, lhs, rhs)?;
 
             let condition = match op {
                 ComparisonOperator::GreaterThan => ir::JumpCondition::Greater(lhs, rhs),
@@ -118,37 +152,140 @@ fn compile_predicate(
             if let Some(false_target) = false_target {
                 block.push_op(Opcode::Jump(ir::JumpCondition::Unconditional, false_target));
             }
+        }
+        Expression::BinaryExpression(lhs, BinaryOperator::LogicalOperator(op), rhs) => {
+            let rhs_label = ir::Label::new("logical rhs");
 
-            Ok(Slot::new())
+            match op {
+                LogicalOperator::And => {
+                    let after_label = ir::Label::new("logical after");
+                    let lhs_false_target = false_target.clone().unwrap_or(after_label.clone());
+
+                    compile_predicate(block, lhs, rhs_label.clone(), Some(lhs_false_target))?;
+                    block.set_label(rhs_label);
+                    compile_predicate(block, rhs, true_target, false_target)?;
+                    block.set_label(after_label);
+                }
+                LogicalOperator::Or => {
+                    compile_predicate(block, lhs, true_target.clone(), Some(rhs_label.clone()))?;
+                    block.set_label(rhs_label);
+                    compile_predicate(block, rhs, true_target, false_target)?;
+                }
+            }
+        }
+        Expression::UnaryExpression(UnaryOperator::Not, operand) => {
+            let after_label = ir::Label::new("not after");
+            let operand_true_target = false_target.unwrap_or(after_label.clone());
+
+            compile_predicate(block, operand, operand_true_target, Some(true_target))?;
+            block.set_label(after_label);
+        }
+        _ => {
+            let value = compile_expression(block, predicate)?;
+            let condition = if block.strict_conditions() {
+                ir::JumpCondition::StrictlyTrue(value)
+            } else {
+                ir::JumpCondition::Truthy(value)
+            };
+
+            block.push_op(Opcode::Jump(condition, true_target));
+
+            if let Some(false_target) = false_target {
+                block.push_op(Opcode::Jump(ir::JumpCondition::Unconditional, false_target));
+            }
         }
     }
+
+    Ok(Slot::new())
+}
+
+// Evaluates a predicate to `true` or `false`, for comparisons and logical
+// operators used as values.
+fn compile_boolean_expression(
+    block: &mut ir::Block,
+    expression: &Located<Expression>,
+) -> CompileResult {
+    let true_label = ir::Label::new("boolean true");
+    let false_label = ir::Label::new("boolean false");
+    let end_label = ir::Label::new("boolean end");
+
+    compile_predicate(
+        block,
+        expression,
+        true_label.clone(),
+        Some(false_label.clone()),
+    )?;
+
+    block.set_label(true_label);
+    let true_value = compile_literal(block, &Literal::Boolean(true))?;
+    let true_phi = block.push_op(ir::Opcode::PhiStart(true_value));
+    block.push_op(ir::Opcode::Jump(
+        ir::JumpCondition::Unconditional,
+        end_label.clone(),
+    ));
+
+    block.set_label(false_label);
+    let false_value = compile_literal(block, &Literal::Boolean(false))?;
+    let false_phi = block.push_op(ir::Opcode::PhiStart(false_value));
+
+    block.set_label(end_label);
+    let result = block.push_op(ir::Opcode::PhiEnd(vec![true_phi, false_phi]));
+    Ok(result)
 }
 
-fn compile_assignment_statement(block: &mut ir::Block, assignment: &Assignment) -> CompileResult {
-    let rhs = compile_expression(block, &assignment.rhs)?;
-    let lhs = assignment.lhs.clone();
-    let lhs = block
-        .resolve(&lhs)
-        .ok_or(CompileError::UnresolvedSymbol(lhs))?;
+// A compound assignment such as `x += 1` reads the target once, combines it
+// with the right hand side and writes the result back to the same target.
+fn compile_assignment(block: &mut ir::Block, assignment: &Assignment) -> CompileResult {
+    let lhs = assignment.lhs.value.clone();
+    let Some(symbol) = block.resolve(&lhs) else {
+        return error(block, CompileError::UnresolvedSymbol(lhs));
+    };
 
-    let target = match lhs {
+    let target = match symbol {
         Symbol::Argument(index) => AssignmentTarget::FunctionArgument(index),
         Symbol::StackVariable(offset) => AssignmentTarget::StackVariable(offset),
-        Symbol::Function(_func, _arity) => todo!(),
+        Symbol::Function(..) | Symbol::CurrentFunction(_) => {
+            return error(block, CompileError::AssignmentToFunction(lhs))
+        }
     };
 
-    block.push(Instruction::Assign(target, rhs));
-    Ok(rhs)
+    let value = match assignment.operator {
+        None => compile_expression(block, &assignment.rhs)?,
+        Some(operator) => {
+            let Some(current) = block.resolve_to_slot(&lhs) else {
+                return error(block, CompileError::UnresolvedSymbol(lhs));
+            };
+            let (current, rhs) = compile_after(block, current, &assignment.rhs)?;
+            block.push_op(Opcode::BinaryOperator(
+                current,
+                BinaryOperator::ArithmeticOperator(operator),
+                rhs,
+            ))
+        }
+    };
+
+    block.push(Instruction::Assign(target, value));
+    Ok(value)
 }
 
-fn compile_return_statement(block: &mut ir::Block, result: &Expression) -> CompileResult {
+fn compile_return_statement(block: &mut ir::Block, result: &Located<Expression>) -> CompileResult {
+    if let Some(result) = compile_tail_call(block, result) {
+        return result;
+    }
+
     let result = compile_expression(block, result)?;
     block.push_op(ir::Opcode::SetReturnValue(result));
     block.push_op(ir::Opcode::Return);
     Ok(result)
 }
 
-fn compile_condition_statement(block: &mut ir::Block, condition: &Condition) -> CompileResult {
+// A condition whose value is returned from the function has the ends of its
+// branches in tail position.
+fn compile_condition_statement(
+    block: &mut ir::Block,
+    condition: &Condition,
+    tail_position: bool,
+) -> CompileResult {
     let next_branch = ir::Label::new("condition next");
     let end_label = ir::Label::new("condition end");
 
@@ -174,7 +311,7 @@ fn compile_condition_statement(block: &mut ir::Block, condition: &Condition) ->
             block.set_label(block_label.clone());
         }
 
-        let result = compile_block(block, branch_block)?;
+        let result = compile_block(block, branch_block, tail_position)?;
         let phi = block.push_op(ir::Opcode::PhiStart(result));
         branch_results.push(phi);
 
@@ -193,14 +330,21 @@ fn compile_condition_statement(block: &mut ir::Block, condition: &Condition) ->
     Ok(result)
 }
 
-fn compile_block(ir_block: &mut ir::Block, block: &Block) -> CompileResult {
+fn compile_block(ir_block: &mut ir::Block, block: &Block, tail_position: bool) -> CompileResult {
     let mut result = None;
 
-    for statement in &block.0 {
-        result = Some(compile_statement(ir_block, statement)?);
+    for (index, statement) in block.0.iter().enumerate() {
+        result = Some(if tail_position && index == block.0.len() - 1 {
+            compile_tail_statement(ir_block, statement)?
+        } else {
+            compile_statement(ir_block, statement)?
+        });
     }
 
-    Ok(result.unwrap())
+    match result {
+        Some(result) => Ok(result),
+        None => error(ir_block, CompileError::EmptyBlock),
+    }
 }
 
 fn compile_function_body(ir_block: &mut ir::Block, block: &Block) -> CompileResult {
@@ -208,10 +352,29 @@ fn compile_function_body(ir_block: &mut ir::Block, block: &Block) -> CompileResu
 
     let mut returned = false;
 
-    for statement in &block.0 {
-        result = Some(compile_statement(ir_block, statement)?);
+    for (index, statement) in block.0.iter().enumerate() {
+        // The value of the last statement is returned, so a call there is in
+        // tail position.
+        let tail_call = match &statement.value {
+            Statement::Expression(expression) if index == block.0.len() - 1 => {
+                compile_tail_call(ir_block, expression)
+            }
+            _ => None,
+        };
+
+        if let Some(tail_call) = tail_call {
+            result = Some(tail_call?);
+            returned = true;
+            break;
+        }
+
+        result = Some(if index == block.0.len() - 1 {
+            compile_tail_statement(ir_block, statement)?
+        } else {
+            compile_statement(ir_block, statement)?
+        });
 
-        if let Statement::Return(_) = statement {
+        if let Statement::Return(_) = statement.value {
             returned = true;
             break;
         }
@@ -231,90 +394,268 @@ fn compile_function_body(ir_block: &mut ir::Block, block: &Block) -> CompileResu
     Ok(result)
 }
 
+// Compiles a statement whose value is returned from the function, so that a
+// call that gives its value is a tail call.
+fn compile_tail_statement(
+    ir_block: &mut ir::Block,
+    statement: &Located<Statement>,
+) -> CompileResult {
+    match &statement.value {
+        Statement::Expression(expression) => match compile_tail_call(ir_block, expression) {
+            Some(tail_call) => tail_call,
+            None => compile_statement(ir_block, statement),
+        },
+        Statement::Condition(condition) => at_location(ir_block, &statement.location, |ir_block| {
+            compile_condition_statement(ir_block, condition, true)
+        }),
+        _ => compile_statement(ir_block, statement),
+    }
+}
+
 fn compile_variable_declaration(
     block: &mut ir::Block,
     declaration: &VariableDeclaration,
 ) -> CompileResult {
     let initial_value = compile_expression(block, &declaration.value)?;
-    let variable = block.insert_stack_variable(&declaration.name, initial_value);
+    let variable = block.insert_stack_variable(&declaration.name.value, initial_value);
     Ok(variable)
 }
 
-fn compile_expression(block: &mut ir::Block, expression: &Expression) -> CompileResult {
-    match expression {
+fn compile_expression(block: &mut ir::Block, expression: &Located<Expression>) -> CompileResult {
+    at_location(block, &expression.location, |block| {
+        compile_expression_at(block, expression)
+    })
+}
+
+fn compile_expression_at(block: &mut ir::Block, expression: &Located<Expression>) -> CompileResult {
+    match &expression.value {
         Expression::Identifier(identifier) => compile_identifier(block, identifier),
         Expression::FunctionCall(identifier, args) => {
             compile_function_call(block, identifier, args)
         }
         Expression::Literal(literal) => compile_literal(block, literal),
+        Expression::BinaryExpression(
+            _,
+            BinaryOperator::ComparisonOperator(_) | BinaryOperator::LogicalOperator(_),
+            _,
+        )
+        | Expression::UnaryExpression(UnaryOperator::Not, _) => {
+            compile_boolean_expression(block, expression)
+        }
         Expression::BinaryExpression(lhs, operator, rhs) => {
             compile_binary_operator_expression(block, lhs, operator, rhs)
         }
+        // Flipping every bit is the same as xor with -1, which keeps the tag
+        // of an inline integer intact.
+        Expression::UnaryExpression(UnaryOperator::BitwiseNot, operand) => {
+            compile_binary_operator_expression(
+                block,
+                operand,
+                &BinaryOperator::ArithmeticOperator(ArithmeticOperator::BitwiseXor),
+                &Located::new(
+                    expression.location.clone(),
+                    Expression::Literal(Literal::Integer(-1)),
+                ),
+            )
+        }
+        // Subtracting from zero promotes to a big integer when negating
+        // would overflow, as any other subtraction does.
+        Expression::UnaryExpression(UnaryOperator::Negate, operand) => {
+            compile_binary_operator_expression(
+                block,
+                &Located::new(
+                    expression.location.clone(),
+                    Expression::Literal(Literal::Integer(0)),
+                ),
+                &BinaryOperator::ArithmeticOperator(ArithmeticOperator::Subtract),
+                operand,
+            )
+        }
+        Expression::Index(value, index) => compile_index_expression(block, value, index),
+        Expression::Assignment(assignment) => compile_assignment(block, assignment),
     }
 }
 
-fn compile_identifier(
-    block: &mut ir::Block,
-    identifier: &Identifier,
-) -> Result<ir::Slot, CompileError> {
+fn compile_identifier(block: &mut ir::Block, identifier: &Identifier) -> CompileResult {
     match block.resolve_to_slot(identifier) {
         Some(slot) => Ok(slot),
-        None => unimplemented!("undefined symbol"),
+        None if block.resolve(identifier).is_some()
+            || runtime::builtin(&identifier.0).is_some() =>
+        {
+            error(block, CompileError::NotAValue(identifier.clone()))
+        }
+        None => error(block, CompileError::UnresolvedSymbol(identifier.clone())),
     }
 }
 
 pub fn compile_binary_operator_expression(
     block: &mut ir::Block,
-    lhs: &Expression,
+    lhs: &Located<Expression>,
     operator: &BinaryOperator,
-    rhs: &Expression,
+    rhs: &Located<Expression>,
 ) -> CompileResult {
     let lhs_slot = compile_expression(block, lhs)?;
-    let rhs_slot = compile_expression(block, rhs)?;
+    let (lhs_slot, rhs_slot) = compile_after(block, lhs_slot, rhs)?;
 
     let slot = block.push_op(ir::Opcode::BinaryOperator(lhs_slot, *operator, rhs_slot));
     Ok(slot)
 }
 
-fn compile_function_call(
+fn compile_arguments(
     block: &mut ir::Block,
-    identifier: &Identifier,
-    args: &Vec<Expression>,
-) -> CompileResult {
+    args: &[Located<Expression>],
+) -> CompileResult<Vec<Slot>> {
     let mut argument_slots = Vec::with_capacity(args.len());
+    let mut starts = Vec::with_capacity(args.len());
     for arg in args.iter() {
+        starts.push(block.instructions().len());
         let argument_slot = compile_expression(block, arg)?;
         argument_slots.push(argument_slot);
     }
 
+    // An argument keeps its value even if a later one assigns to the
+    // variable it reads.
+    for (index, slot) in argument_slots.iter_mut().enumerate() {
+        if let Some(start) = starts.get(index + 1) {
+            *slot = block.keep_value(*slot, *start);
+        }
+    }
+    Ok(argument_slots)
+}
+
+// Compiles `rhs` after the already compiled `lhs`, which keeps its value if
+// `rhs` assigns to the variable it reads.
+fn compile_after(
+    block: &mut ir::Block,
+    lhs: Slot,
+    rhs: &Located<Expression>,
+) -> CompileResult<(Slot, Slot)> {
+    let start = block.instructions().len();
+    let rhs = compile_expression(block, rhs)?;
+    Ok((block.keep_value(lhs, start), rhs))
+}
+
+fn compile_function_call(
+    block: &mut ir::Block,
+    identifier: &Identifier,
+    args: &[Located<Expression>],
+) -> CompileResult {
+    let argument_slots = compile_arguments(block, args)?;
+
     let Some(identifier_symbol) = block.resolve(identifier) else {
-        return Err(CompileError::UnresolvedSymbol(identifier.clone()));
+        let Some(builtin) = runtime::builtin(&identifier.0) else {
+            return error(block, CompileError::UnresolvedSymbol(identifier.clone()));
+        };
+
+        if !builtin.accepts_argument_count(argument_slots.len()) {
+            return error(
+                block,
+                CompileError::IncorrectArity(
+                    identifier.clone(),
+                    builtin.arity,
+                    argument_slots.len(),
+                ),
+            );
+        }
+
+        return Ok(block.push_op(ir::Opcode::CallBuiltin(builtin, argument_slots)));
     };
 
-    let Symbol::Function(function, arity) = identifier_symbol else {
-        return Err(CompileError::NotImplemented(format!(
-            "calling symbol {:?}",
-            identifier_symbol
-        )));
+    let (callee, arity) = match identifier_symbol {
+        Symbol::Function(function, arity) => (ir::Callee::Function(function), arity),
+        Symbol::CurrentFunction(arity) => (ir::Callee::Current, arity),
+        Symbol::Argument(_) | Symbol::StackVariable(_) => {
+            return error(block, CompileError::NotAFunction(identifier.clone()))
+        }
     };
 
     if argument_slots.len() != arity {
-        return Err(CompileError::IncorrectArity(
-            identifier.clone(),
-            argument_slots.len(),
-            arity,
-        ));
+        return error(
+            block,
+            CompileError::IncorrectArity(identifier.clone(), arity, argument_slots.len()),
+        );
     }
 
-    let return_value_slot = block.push_op(ir::Opcode::CallFunction(function, argument_slots));
+    let return_value_slot = block.push_op(ir::Opcode::CallFunction(callee, argument_slots));
     Ok(return_value_slot)
 }
 
+// Compiles a call whose result is returned as a jump to the function called,
+// which takes over the current frame and returns straight to our caller. A
+// function that calls itself this way runs in constant stack space. Anything
+// that isn't a call to a function defined in risp, with the right number of
+// arguments, gives `None` and is compiled as usual.
+fn compile_tail_call(
+    block: &mut ir::Block,
+    expression: &Located<Expression>,
+) -> Option<CompileResult> {
+    let Expression::FunctionCall(identifier, args) = &expression.value else {
+        return None;
+    };
+
+    let callee = match block.resolve(identifier)? {
+        Symbol::Function(function, arity) if arity == args.len() => ir::Callee::Function(function),
+        Symbol::CurrentFunction(arity) if arity == args.len() => ir::Callee::Current,
+        _ => return None,
+    };
+
+    Some(at_location(block, &expression.location, |block| {
+        let argument_slots = compile_arguments(block, args)?;
+        Ok(block.push_op(ir::Opcode::TailCall(callee, argument_slots)))
+    }))
+}
+
+// Indexing a string gives the character at that index, as `char_at` does.
+fn compile_index_expression(
+    block: &mut ir::Block,
+    value: &Located<Expression>,
+    index: &Located<Expression>,
+) -> CompileResult {
+    let char_at = runtime::builtin("char_at").expect("char_at builtin is missing");
+    let value = compile_expression(block, value)?;
+    let (value, index) = compile_after(block, value, index)?;
+    Ok(block.push_op(ir::Opcode::CallBuiltin(char_at, vec![value, index])))
+}
+
 fn compile_literal(block: &mut ir::Block, literal: &Literal) -> CompileResult {
     match literal {
         Literal::Integer(int) => Ok(block.push_op(ir::Opcode::Literal(Value::Integer(*int)))),
         Literal::String(string) => {
             Ok(block.push_op(ir::Opcode::Literal(Value::String(string.to_string()))))
         }
+        Literal::InterpolatedString(parts) => compile_interpolated_string(block, parts),
+        Literal::Boolean(boolean) => {
+            Ok(block.push_op(ir::Opcode::Literal(Value::Boolean(*boolean))))
+        }
     }
 }
+
+// Interpolated strings are converted to a chain of concatenations, with each
+// expression converted to a string first.
+fn compile_interpolated_string(block: &mut ir::Block, parts: &[StringPart]) -> CompileResult {
+    let to_string = runtime::builtin("str").expect("str builtin is missing");
+    let mut result = None;
+
+    for part in parts {
+        let part = match part {
+            StringPart::Text(text) => compile_literal(block, &Literal::String(text.clone()))?,
+            StringPart::Expression(expression) => {
+                let value = compile_expression(block, expression)?;
+                block.push_op(ir::Opcode::CallBuiltin(to_string, vec![value]))
+            }
+        };
+
+        result = Some(match result {
+            Some(result) => block.push_op(ir::Opcode::BinaryOperator(
+                result,
+                BinaryOperator::ArithmeticOperator(ArithmeticOperator::Add),
+                part,
+            )),
+            None => part,
+        });
+    }
+
+    // The parser only makes an interpolated string out of a string with an
+    // interpolation in it, so there's always at least one part.
+    Ok(result.expect("interpolated string has no parts"))
+}
@@ -0,0 +1,267 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    parser::{
+        Assignment, Block, Condition, Expression, Identifier, Literal, Located, Location, Loop,
+        Statement, StringPart,
+    },
+    runtime,
+};
+
+use super::{CompileError, CompilerError, StackFrame, Symbol};
+
+// Checks that every name in a function body refers to something it can be
+// used as, and that calls pass the right number of arguments, before any code
+// is generated for it.
+pub fn analyze(stack_frame: &StackFrame, block: &Block) -> Result<(), CompilerError> {
+    let mut analyzer = Analyzer::new(stack_frame, HashMap::new());
+
+    for statement in &block.0 {
+        analyzer.statement(statement)?;
+    }
+
+    Ok(())
+}
+
+// Checks a whole program without compiling it, carrying on after an error to
+// find the rest. As in the evaluator, each function body can call the
+// functions defined before it, and everything else can call all of them.
+pub fn analyze_program(block: &Block) -> Vec<CompilerError> {
+    let stack_frame = StackFrame::default();
+    let mut functions = HashMap::new();
+    let mut errors = Vec::new();
+
+    for statement in &block.0 {
+        let Statement::FunctionDefinition(definition) = &statement.value else {
+            continue;
+        };
+
+        let name = &definition.name.value;
+        let arity = definition.args.len();
+        errors.extend(check_arguments(&definition.args).err());
+
+        let mut function_frame = stack_frame.push_function(name, arity);
+        for (index, arg) in definition.args.iter().enumerate() {
+            function_frame.insert(&arg.value, Symbol::Argument(index));
+        }
+        Analyzer::new(&function_frame, functions.clone()).statements(&definition.body, &mut errors);
+
+        functions.insert(name.clone(), arity);
+    }
+
+    let top_level_frame = stack_frame.push();
+    Analyzer::new(&top_level_frame, functions).statements(block, &mut errors);
+
+    errors.sort_by_key(|error| match error {
+        CompilerError::CompileError(_, location) | CompilerError::CodegenError(_, location) => {
+            location.as_ref().map(|location| location.offset)
+        }
+    });
+    errors
+}
+
+// Checks that no two of a function's arguments have the same name, reporting
+// the second of them if they do.
+pub fn check_arguments(args: &[Located<Identifier>]) -> Result<(), CompilerError> {
+    let mut names = HashSet::new();
+
+    for arg in args {
+        if !names.insert(&arg.value) {
+            return error(
+                CompileError::DuplicateArgument(arg.value.clone()),
+                &arg.location,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// What a name refers to at the point it's used.
+enum Name {
+    Value,
+    Function(usize),
+    Builtin(runtime::Builtin),
+}
+
+struct Analyzer<'a, 'b> {
+    stack_frame: &'a StackFrame<'b>,
+    // Variables declared so far in the body. Like the compiler, the analyzer
+    // gives the whole body one scope, so a variable declared in a nested
+    // block can be used after it.
+    variables: HashSet<Identifier>,
+    // Functions that aren't in the stack frame, as they haven't been
+    // compiled, along with their arities.
+    functions: HashMap<Identifier, usize>,
+}
+
+fn error(error: CompileError, location: &Location) -> Result<(), CompilerError> {
+    Err(CompilerError::CompileError(error, Some(location.clone())))
+}
+
+impl<'a, 'b> Analyzer<'a, 'b> {
+    fn new(stack_frame: &'a StackFrame<'b>, functions: HashMap<Identifier, usize>) -> Self {
+        Self {
+            stack_frame,
+            variables: HashSet::new(),
+            functions,
+        }
+    }
+
+    // Checks each statement in `block`, collecting the first error in each.
+    fn statements(&mut self, block: &Block, errors: &mut Vec<CompilerError>) {
+        for statement in &block.0 {
+            errors.extend(self.statement(statement).err());
+        }
+    }
+
+    fn resolve(&self, name: &Identifier) -> Option<Name> {
+        if self.variables.contains(name) {
+            return Some(Name::Value);
+        }
+
+        match self.stack_frame.resolve(name) {
+            Some(Symbol::Argument(_) | Symbol::StackVariable(_)) => Some(Name::Value),
+            Some(Symbol::Function(_, arity) | Symbol::CurrentFunction(arity)) => {
+                Some(Name::Function(arity))
+            }
+            None => match self.functions.get(name) {
+                Some(arity) => Some(Name::Function(*arity)),
+                None => runtime::builtin(&name.0).map(Name::Builtin),
+            },
+        }
+    }
+
+    fn statement(&mut self, statement: &Located<Statement>) -> Result<(), CompilerError> {
+        let location = &statement.location;
+
+        match &statement.value {
+            Statement::Expression(expression) | Statement::Return(expression) => {
+                self.expression(expression)
+            }
+            // Function bodies are analyzed when they're compiled, in a stack
+            // frame of their own.
+            Statement::FunctionDefinition(_) => Ok(()),
+            Statement::VariableDeclaration(declaration) => {
+                self.expression(&declaration.value)?;
+                self.variables.insert(declaration.name.value.clone());
+                Ok(())
+            }
+            Statement::Condition(condition) => self.condition(condition, location),
+            Statement::Assignment(assignment) => self.assignment(assignment),
+            Statement::Loop(loop_statement) => self.loop_statement(loop_statement, location),
+            Statement::Error(_) => Ok(()),
+        }
+    }
+
+    fn block(&mut self, block: &Block, location: &Location) -> Result<(), CompilerError> {
+        if block.0.is_empty() {
+            return error(CompileError::EmptyBlock, location);
+        }
+
+        for statement in &block.0 {
+            self.statement(statement)?;
+        }
+
+        Ok(())
+    }
+
+    fn condition(
+        &mut self,
+        condition: &Condition,
+        location: &Location,
+    ) -> Result<(), CompilerError> {
+        for (predicate, block) in &condition.branches {
+            if let Some(predicate) = predicate {
+                self.expression(predicate)?;
+            }
+            self.block(block, location)?;
+        }
+
+        Ok(())
+    }
+
+    fn assignment(&mut self, assignment: &Assignment) -> Result<(), CompilerError> {
+        let lhs = &assignment.lhs;
+
+        match self.resolve(&lhs.value) {
+            Some(Name::Value) => {}
+            Some(Name::Function(_) | Name::Builtin(_)) => {
+                return error(
+                    CompileError::AssignmentToFunction(lhs.value.clone()),
+                    &lhs.location,
+                )
+            }
+            None => {
+                return error(
+                    CompileError::UnresolvedSymbol(lhs.value.clone()),
+                    &lhs.location,
+                )
+            }
+        }
+
+        self.expression(&assignment.rhs)
+    }
+
+    fn loop_statement(
+        &mut self,
+        loop_statement: &Loop,
+        location: &Location,
+    ) -> Result<(), CompilerError> {
+        self.expression(&loop_statement.predicate)?;
+        self.block(&loop_statement.block, location)
+    }
+
+    fn expression(&mut self, expression: &Located<Expression>) -> Result<(), CompilerError> {
+        let location = &expression.location;
+
+        match &expression.value {
+            Expression::Identifier(identifier) => match self.resolve(identifier) {
+                Some(Name::Value) => Ok(()),
+                Some(Name::Function(_) | Name::Builtin(_)) => {
+                    error(CompileError::NotAValue(identifier.clone()), location)
+                }
+                None => error(CompileError::UnresolvedSymbol(identifier.clone()), location),
+            },
+            Expression::FunctionCall(identifier, args) => {
+                for arg in args {
+                    self.expression(arg)?;
+                }
+
+                let arity = match self.resolve(identifier) {
+                    Some(Name::Function(arity)) if args.len() != arity => arity,
+                    Some(Name::Builtin(builtin)) if !builtin.accepts_argument_count(args.len()) => {
+                        builtin.arity
+                    }
+                    Some(Name::Function(_) | Name::Builtin(_)) => return Ok(()),
+                    Some(Name::Value) => {
+                        return error(CompileError::NotAFunction(identifier.clone()), location)
+                    }
+                    None => {
+                        return error(CompileError::UnresolvedSymbol(identifier.clone()), location)
+                    }
+                };
+
+                error(
+                    CompileError::IncorrectArity(identifier.clone(), arity, args.len()),
+                    location,
+                )
+            }
+            Expression::Literal(Literal::InterpolatedString(parts)) => {
+                for part in parts {
+                    if let StringPart::Expression(expression) = part {
+                        self.expression(expression)?;
+                    }
+                }
+                Ok(())
+            }
+            Expression::Literal(_) => Ok(()),
+            Expression::BinaryExpression(lhs, _, rhs) | Expression::Index(lhs, rhs) => {
+                self.expression(lhs)?;
+                self.expression(rhs)
+            }
+            Expression::UnaryExpression(_, operand) => self.expression(operand),
+            Expression::Assignment(assignment) => self.assignment(assignment),
+        }
+    }
+}
@@ -1,26 +1,30 @@
-use crate::{codegen, parser::Identifier};
+use crate::{
+    codegen,
+    parser::{Identifier, Location},
+};
 
 #[derive(Debug)]
 pub enum CompileError {
+    AssignmentToFunction(Identifier),
+    DuplicateArgument(Identifier),
+    EmptyBlock,
+    // The function, the number of arguments it expects and the number given.
     IncorrectArity(Identifier, usize, usize),
-    NotImplemented(String),
+    NotAFunction(Identifier),
+    NotAValue(Identifier),
     UnresolvedSymbol(Identifier),
 }
 
+// Each error comes with the location of the code being compiled when it
+// happened, if there was any.
 #[derive(Debug)]
 pub enum CompilerError {
-    CompileError(CompileError),
-    CodegenError(codegen::CodegenError),
-}
-
-impl From<CompileError> for CompilerError {
-    fn from(err: CompileError) -> Self {
-        CompilerError::CompileError(err)
-    }
+    CompileError(CompileError, Option<Location>),
+    CodegenError(codegen::CodegenError, Option<Location>),
 }
 
 impl From<codegen::CodegenError> for CompilerError {
     fn from(err: codegen::CodegenError) -> Self {
-        CompilerError::CodegenError(err)
+        CompilerError::CodegenError(err, None)
     }
 }
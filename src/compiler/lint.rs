@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+
+use crate::parser::{
+    Assignment, Block, Condition, Expression, Identifier, Literal, Located, Location, Loop,
+    Statement, StringPart,
+};
+
+// The rules that can produce warnings, each of which can be turned off on its
+// own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lint {
+    UnusedVariable,
+    UnusedArgument,
+    UnreachableCode,
+    SelfAssignment,
+    ConstantCondition,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 5] = [
+        Lint::UnusedVariable,
+        Lint::UnusedArgument,
+        Lint::UnreachableCode,
+        Lint::SelfAssignment,
+        Lint::ConstantCondition,
+    ];
+
+    // The name used to turn the rule off, as in `--allow=unused-variable`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::UnusedVariable => "unused-variable",
+            Lint::UnusedArgument => "unused-argument",
+            Lint::UnreachableCode => "unreachable-code",
+            Lint::SelfAssignment => "self-assignment",
+            Lint::ConstantCondition => "constant-condition",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Lint> {
+        Lint::ALL.into_iter().find(|lint| lint.name() == name)
+    }
+}
+
+// Which lints are enabled. All of them are by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Lints {
+    disabled: u8,
+}
+
+impl Lints {
+    pub fn allow(&mut self, lint: Lint) {
+        self.disabled |= 1 << lint as u8;
+    }
+
+    pub fn is_enabled(&self, lint: Lint) -> bool {
+        self.disabled & (1 << lint as u8) == 0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Warning {
+    UnusedVariable(Identifier),
+    UnusedArgument(Identifier),
+    UnreachableCode,
+    SelfAssignment(Identifier),
+    ConstantCondition,
+}
+
+impl Warning {
+    pub fn lint(&self) -> Lint {
+        match self {
+            Warning::UnusedVariable(_) => Lint::UnusedVariable,
+            Warning::UnusedArgument(_) => Lint::UnusedArgument,
+            Warning::UnreachableCode => Lint::UnreachableCode,
+            Warning::SelfAssignment(_) => Lint::SelfAssignment,
+            Warning::ConstantCondition => Lint::ConstantCondition,
+        }
+    }
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Warning::UnusedVariable(name) => write!(f, "variable '{}' is never used", name),
+            Warning::UnusedArgument(name) => write!(f, "argument '{}' is never used", name),
+            Warning::UnreachableCode => write!(f, "unreachable code after return"),
+            Warning::SelfAssignment(name) => write!(f, "'{}' is assigned to itself", name),
+            Warning::ConstantCondition => write!(f, "condition is always the same"),
+        }
+    }
+}
+
+// Looks for code that's allowed but probably a mistake, in the order it
+// appears in the source. Names starting with `_` are never reported as
+// unused.
+pub fn lint(block: &Block, lints: Lints) -> Vec<Located<Warning>> {
+    let mut linter = Linter {
+        lints,
+        scope: Scope::default(),
+        warnings: Vec::new(),
+    };
+
+    linter.block(block);
+    linter.end_scope();
+
+    linter
+        .warnings
+        .sort_by_key(|warning| warning.location.offset);
+    linter.warnings
+}
+
+struct Declaration {
+    name: Identifier,
+    location: Location,
+    argument: bool,
+    used: bool,
+}
+
+// The names declared in a function body. As in the compiler, the whole body
+// is one scope, and a later `let` of the same name replaces the earlier one.
+#[derive(Default)]
+struct Scope {
+    declarations: Vec<Declaration>,
+    current: HashMap<Identifier, usize>,
+}
+
+struct Linter {
+    lints: Lints,
+    scope: Scope,
+    warnings: Vec<Located<Warning>>,
+}
+
+impl Linter {
+    fn warn(&mut self, warning: Warning, location: &Location) {
+        if self.lints.is_enabled(warning.lint()) {
+            self.warnings.push(Located::new(location.clone(), warning));
+        }
+    }
+
+    fn declare(&mut self, name: &Identifier, location: &Location, argument: bool) {
+        self.scope
+            .current
+            .insert(name.clone(), self.scope.declarations.len());
+        self.scope.declarations.push(Declaration {
+            name: name.clone(),
+            location: location.clone(),
+            argument,
+            used: false,
+        });
+    }
+
+    fn use_name(&mut self, name: &Identifier) {
+        if let Some(&index) = self.scope.current.get(name) {
+            self.scope.declarations[index].used = true;
+        }
+    }
+
+    fn end_scope(&mut self) {
+        let scope = std::mem::take(&mut self.scope);
+
+        for declaration in scope.declarations {
+            if declaration.used || declaration.name.0.starts_with('_') {
+                continue;
+            }
+
+            let warning = if declaration.argument {
+                Warning::UnusedArgument(declaration.name)
+            } else {
+                Warning::UnusedVariable(declaration.name)
+            };
+            self.warn(warning, &declaration.location);
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        let mut returned = false;
+
+        for statement in &block.0 {
+            if returned {
+                self.warn(Warning::UnreachableCode, &statement.location);
+                returned = false;
+            }
+
+            self.statement(statement);
+
+            if let Statement::Return(_) = statement.value {
+                returned = true;
+            }
+        }
+    }
+
+    fn statement(&mut self, statement: &Located<Statement>) {
+        match &statement.value {
+            Statement::Expression(expression) | Statement::Return(expression) => {
+                self.expression(expression)
+            }
+            Statement::FunctionDefinition(definition) => {
+                let outer_scope = std::mem::take(&mut self.scope);
+
+                for arg in &definition.args {
+                    // A repeated argument is rejected when the function is
+                    // compiled, so it's only declared once here.
+                    if !self.scope.current.contains_key(&arg.value) {
+                        self.declare(&arg.value, &arg.location, true);
+                    }
+                }
+                self.block(&definition.body);
+                self.end_scope();
+
+                self.scope = outer_scope;
+            }
+            Statement::VariableDeclaration(declaration) => {
+                self.expression(&declaration.value);
+                self.declare(&declaration.name.value, &declaration.name.location, false);
+            }
+            Statement::Condition(condition) => self.condition(condition),
+            Statement::Assignment(assignment) => self.assignment(assignment, &statement.location),
+            Statement::Loop(loop_statement) => self.loop_statement(loop_statement),
+            Statement::Error(_) => {}
+        }
+    }
+
+    fn condition(&mut self, condition: &Condition) {
+        for (predicate, block) in &condition.branches {
+            if let Some(predicate) = predicate {
+                self.predicate(predicate);
+            }
+            self.block(block);
+        }
+    }
+
+    fn assignment(&mut self, assignment: &Assignment, location: &Location) {
+        match (&assignment.operator, &assignment.rhs.value) {
+            (None, Expression::Identifier(rhs)) if *rhs == assignment.lhs.value => self.warn(
+                Warning::SelfAssignment(assignment.lhs.value.clone()),
+                location,
+            ),
+            // A compound assignment reads the variable as well as writing it.
+            (Some(_), _) => self.use_name(&assignment.lhs.value),
+            _ => {}
+        }
+
+        self.expression(&assignment.rhs);
+    }
+
+    fn loop_statement(&mut self, loop_statement: &Loop) {
+        self.predicate(&loop_statement.predicate);
+        self.block(&loop_statement.block);
+    }
+
+    fn predicate(&mut self, predicate: &Located<Expression>) {
+        if is_constant(&predicate.value) {
+            self.warn(Warning::ConstantCondition, &predicate.location);
+        }
+        self.expression(predicate);
+    }
+
+    fn expression(&mut self, expression: &Located<Expression>) {
+        match &expression.value {
+            Expression::Identifier(identifier) => self.use_name(identifier),
+            Expression::FunctionCall(identifier, args) => {
+                // Calling an argument is an error, but it's still a use of it.
+                self.use_name(identifier);
+                for arg in args {
+                    self.expression(arg);
+                }
+            }
+            Expression::Literal(Literal::InterpolatedString(parts)) => {
+                for part in parts {
+                    if let StringPart::Expression(expression) = part {
+                        self.expression(expression);
+                    }
+                }
+            }
+            Expression::Literal(_) => {}
+            Expression::BinaryExpression(lhs, _, rhs) | Expression::Index(lhs, rhs) => {
+                self.expression(lhs);
+                self.expression(rhs);
+            }
+            Expression::UnaryExpression(_, operand) => self.expression(operand),
+            Expression::Assignment(assignment) => self.assignment(assignment, &expression.location),
+        }
+    }
+}
+
+// Whether an expression is made only of literals, so it has the same value
+// every time.
+fn is_constant(expression: &Expression) -> bool {
+    match expression {
+        Expression::Identifier(_) | Expression::FunctionCall(..) | Expression::Assignment(_) => {
+            false
+        }
+        Expression::Literal(Literal::InterpolatedString(parts)) => {
+            parts.iter().all(|part| match part {
+                StringPart::Text(_) => true,
+                StringPart::Expression(expression) => is_constant(&expression.value),
+            })
+        }
+        Expression::Literal(_) => true,
+        Expression::BinaryExpression(lhs, _, rhs) | Expression::Index(lhs, rhs) => {
+            is_constant(&lhs.value) && is_constant(&rhs.value)
+        }
+        Expression::UnaryExpression(_, operand) => is_constant(&operand.value),
+    }
+}
+
+#[test]
+fn test_lint() {
+    use crate::parser;
+
+    let warnings = |source, lints| {
+        let block = parser::parse(source).expect("parsing failed");
+        lint(&block, lints)
+            .into_iter()
+            .map(|warning| {
+                let location = &warning.location;
+                (location.line, location.column, warning.value.to_string())
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let source = "def f(x, y, _z) {\n\
+                  \x20 let a = 1\n\
+                  \x20 let b = 2\n\
+                  \x20 b = b\n\
+                  \x20 let a = x\n\
+                  \x20 if 1 < 2 { return a }\n\
+                  \x20 return 0\n\
+                  \x20 a\n\
+                  }\n\
+                  let n = 0\n\
+                  while n < 3 { n += 1 }";
+
+    assert_eq!(
+        warnings(source, Lints::default()),
+        vec![
+            (1, 10, "argument 'y' is never used".to_owned()),
+            (2, 7, "variable 'a' is never used".to_owned()),
+            (4, 3, "'b' is assigned to itself".to_owned()),
+            (6, 6, "condition is always the same".to_owned()),
+            (8, 3, "unreachable code after return".to_owned()),
+        ]
+    );
+
+    let mut lints = Lints::default();
+    lints.allow(Lint::UnusedArgument);
+    lints.allow(Lint::from_name("constant-condition").unwrap());
+    assert_eq!(
+        warnings(source, lints),
+        vec![
+            (2, 7, "variable 'a' is never used".to_owned()),
+            (4, 3, "'b' is assigned to itself".to_owned()),
+            (8, 3, "unreachable code after return".to_owned()),
+        ]
+    );
+
+    // Calling an argument uses it, and a repeated argument is only reported
+    // by the compiler.
+    assert_eq!(
+        warnings(
+            "def f(f) { f(1) }
+def g(x, x) { x }",
+            Lints::default()
+        ),
+        vec![]
+    );
+}
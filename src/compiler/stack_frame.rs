@@ -6,6 +6,9 @@ use crate::{compiler::Function, parser::Identifier};
 pub enum Symbol {
     Argument(usize),
     Function(Rc<Function>, usize),
+    // The function being compiled, which can call itself before it has an
+    // address.
+    CurrentFunction(usize),
     StackVariable(usize),
 }
 
@@ -14,17 +17,30 @@ pub struct StackFrame<'a> {
     parent: Option<&'a StackFrame<'a>>,
     definitions: HashMap<Identifier, Symbol>,
     stack_slots: usize,
+    // The name of the function the frame belongs to, if it isn't the top
+    // level.
+    function_name: Option<Identifier>,
 }
 
 impl<'a> StackFrame<'a> {
-    pub fn push(&self) -> StackFrame {
+    pub fn push(&self) -> StackFrame<'_> {
         StackFrame {
             parent: Some(self),
             definitions: HashMap::new(),
             stack_slots: 0,
+            function_name: None,
         }
     }
 
+    // A frame for the body of a function, in which the function can refer to
+    // itself.
+    pub fn push_function(&self, name: &Identifier, arity: usize) -> StackFrame<'_> {
+        let mut stack_frame = self.push();
+        stack_frame.function_name = Some(name.clone());
+        stack_frame.insert(name, Symbol::CurrentFunction(arity));
+        stack_frame
+    }
+
     pub fn insert(&mut self, name: &Identifier, symbol: Symbol) {
         self.definitions.insert(name.clone(), symbol);
     }
@@ -38,6 +54,14 @@ impl<'a> StackFrame<'a> {
         symbol
     }
 
+    // A stack slot with no name, for values that are kept on the stack
+    // because there aren't enough registers for them.
+    pub fn insert_anonymous_stack_slot(&mut self) -> usize {
+        let offset = self.stack_slots;
+        self.stack_slots += 1;
+        offset
+    }
+
     pub fn resolve(&self, name: &Identifier) -> Option<Symbol> {
         self.resolve_with_offset(name, 0)
     }
@@ -60,4 +84,8 @@ impl<'a> StackFrame<'a> {
     pub(crate) fn stack_slots(&self) -> usize {
         self.stack_slots
     }
+
+    pub(crate) fn function_name(&self) -> Option<&Identifier> {
+        self.function_name.as_ref()
+    }
 }
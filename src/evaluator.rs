@@ -6,61 +6,103 @@ use crate::{
     compiler::{
         self,
         stack_frame::{StackFrame, Symbol},
+        CompileOptions, Warning,
     },
-    parser::{self, Statement},
+    parser::{self, Located, Statement},
+    runtime,
     value::Value,
 };
 
-use self::error::EvaluationError;
+pub use self::error::EvaluationError;
+
+// How much stack evaluated code can use, unless told otherwise. Running out
+// is reported as an error, so this is kept well short of the stack of any
+// thread the evaluator is likely to run on.
+pub const DEFAULT_MAX_STACK_SIZE: usize = 1024 * 1024;
 
-#[derive(Default)]
 pub struct Evaluator<'a> {
     stack_frame: StackFrame<'a>,
+    options: CompileOptions,
+    warnings: Vec<Located<Warning>>,
+    max_stack_size: usize,
+}
+
+impl<'a> Default for Evaluator<'a> {
+    fn default() -> Self {
+        Self::with_options(CompileOptions::default())
+    }
 }
 
 impl<'a> Evaluator<'a> {
+    pub fn with_options(options: CompileOptions) -> Self {
+        Self {
+            stack_frame: StackFrame::default(),
+            options,
+            warnings: Vec::new(),
+            max_stack_size: DEFAULT_MAX_STACK_SIZE,
+        }
+    }
+
+    // Limits how many bytes of stack evaluated code can use. Recursing any
+    // deeper raises an error.
+    pub fn set_max_stack_size(&mut self, max_stack_size: usize) {
+        self.max_stack_size = max_stack_size;
+    }
+
     pub fn evaluate<'b>(&mut self, line: &'b str) -> Result<Value, EvaluationError<'b>> {
-        let (remainder, block) = parser::parse(line)?;
+        let block = parser::parse(line)?;
+        self.warnings
+            .extend(compiler::lint(&block, self.options.lints));
 
-        for statement in &block.value.0 {
+        for statement in &block.0 {
             self.evaluate_statement(statement)?;
         }
 
-        let function = compiler::compile(&mut self.stack_frame, &block.value)?;
-        let result = function.call();
+        // The rest is compiled in a frame of its own, so the variables it
+        // declares are gone once it's been run.
+        let mut stack_frame = self.stack_frame.push();
+        let function = compiler::compile(&mut stack_frame, &block, self.options)?;
+        let result = runtime::with_stack_limit(self.max_stack_size, || function.call())?;
 
-        if remainder.is_empty() {
-            Ok(result)
-        } else {
-            self.evaluate(remainder.fragment())
-        }
+        Ok(result)
+    }
+
+    // Warnings about everything evaluated since they were last taken.
+    pub fn take_warnings(&mut self) -> Vec<Located<Warning>> {
+        std::mem::take(&mut self.warnings)
     }
 
     pub fn evaluate_statement<'b>(
         &mut self,
-        statement: &Statement,
+        statement: &Located<Statement>,
     ) -> Result<(), EvaluationError<'b>> {
-        match statement {
-            Statement::FunctionDefinition(ref definition) => {
-                let mut stack_frame = self.stack_frame.push();
+        match &statement.value {
+            Statement::FunctionDefinition(definition) => {
+                compiler::check_arguments(&definition.args)?;
+
+                let arity = definition.args.len();
+                let mut stack_frame = self
+                    .stack_frame
+                    .push_function(&definition.name.value, arity);
 
                 for (index, arg) in definition.args.iter().enumerate() {
-                    stack_frame.insert(arg, Symbol::Argument(index));
+                    stack_frame.insert(&arg.value, Symbol::Argument(index));
                 }
 
-                let function = compiler::compile(&mut stack_frame, &definition.body)?;
-                let symbol = Symbol::Function(Rc::new(function), definition.args.len());
-                println!("Function {} defined", definition.name);
-                self.stack_frame.insert(&definition.name, symbol);
-            }
-            Statement::VariableDeclaration(_declaration) => {
-                todo!("evaluate variable declaration — requires maintaining a stack in the REPL")
+                let function = compiler::compile(&mut stack_frame, &definition.body, self.options)?;
+                let symbol = Symbol::Function(Rc::new(function), arity);
+                println!("Function {} defined", definition.name.value);
+                self.stack_frame.insert(&definition.name.value, symbol);
             }
-            Statement::Expression(_expression) => {}
-            Statement::Condition(_condition) => {}
-            Statement::Return(_) => todo!(),
-            Statement::Assignment(_) => todo!(),
-            Statement::Loop(_) => todo!(),
+            // Everything else is compiled along with the rest of the input,
+            // so variables only last until the end of it.
+            Statement::VariableDeclaration(_)
+            | Statement::Expression(_)
+            | Statement::Condition(_)
+            | Statement::Return(_)
+            | Statement::Assignment(_)
+            | Statement::Loop(_)
+            | Statement::Error(_) => {}
         }
         Ok(())
     }
@@ -1,15 +1,22 @@
 use std::fmt::Display;
 
-use crate::{codegen, compiler, parser};
+use crate::{
+    codegen, compiler,
+    parser::{self, ArithmeticOperator, Location},
+    runtime,
+};
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum EvaluationError<'a> {
-    ParseError(nom::Err<(parser::Span<'a>, nom::error::ErrorKind)>),
+    ParseError(nom::Err<parser::ParseError<'a>>),
     CompilerError(compiler::CompilerError),
+    // Along with the location of the code that raised it, if it's known.
+    RuntimeError(runtime::RuntimeError, Option<Location>),
 }
 
-impl<'a> From<nom::Err<(parser::Span<'a>, nom::error::ErrorKind)>> for EvaluationError<'a> {
-    fn from(err: nom::Err<(parser::Span<'a>, nom::error::ErrorKind)>) -> Self {
+impl<'a> From<nom::Err<parser::ParseError<'a>>> for EvaluationError<'a> {
+    fn from(err: nom::Err<parser::ParseError<'a>>) -> Self {
         EvaluationError::ParseError(err)
     }
 }
@@ -20,50 +27,156 @@ impl From<compiler::CompilerError> for EvaluationError<'_> {
     }
 }
 
+impl From<(runtime::RuntimeError, Option<Location>)> for EvaluationError<'_> {
+    fn from((err, location): (runtime::RuntimeError, Option<Location>)) -> Self {
+        EvaluationError::RuntimeError(err, location)
+    }
+}
+
 impl<'a> Display for EvaluationError<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             EvaluationError::ParseError(error) => match error {
-                nom::Err::Incomplete(needed) => write!(f, "expected {:?}", needed),
-                nom::Err::Error(error) => write!(f, "error {:?}", error),
-                nom::Err::Failure(failure) => write!(f, "failure {:?}", failure),
+                nom::Err::Incomplete(_) => write!(f, "unexpected end of input"),
+                nom::Err::Error(error) | nom::Err::Failure(error) => write!(f, "{}", error),
             },
-            EvaluationError::CompilerError(error) => match error {
-                compiler::CompilerError::CompileError(error) => match error {
-                    compiler::CompileError::IncorrectArity(identifier, expected, actual) => write!(
-                        f,
-                        "function '{}' expects {} parameters but {} were given",
-                        identifier, expected, actual
-                    ),
-                    compiler::CompileError::NotImplemented(message) => {
-                        write!(f, "not yet implemented: {}", message)
+            EvaluationError::CompilerError(error) => {
+                match error {
+                    compiler::CompilerError::CompileError(error, _) => match error {
+                        compiler::CompileError::AssignmentToFunction(identifier) => {
+                            write!(f, "cannot assign to function '{}'", identifier)
+                        }
+                        compiler::CompileError::DuplicateArgument(identifier) => {
+                            write!(f, "argument '{}' is declared more than once", identifier)
+                        }
+                        compiler::CompileError::EmptyBlock => write!(f, "block is empty"),
+                        compiler::CompileError::IncorrectArity(identifier, expected, actual) => {
+                            write!(
+                                f,
+                                "function '{}' expects {} {} but {} {} given",
+                                identifier,
+                                expected,
+                                if *expected == 1 {
+                                    "parameter"
+                                } else {
+                                    "parameters"
+                                },
+                                actual,
+                                if *actual == 1 { "was" } else { "were" }
+                            )
+                        }
+                        compiler::CompileError::NotAFunction(identifier) => {
+                            write!(f, "'{}' is not a function", identifier)
+                        }
+                        compiler::CompileError::NotAValue(identifier) => {
+                            write!(f, "function '{}' cannot be used as a value", identifier)
+                        }
+                        compiler::CompileError::UnresolvedSymbol(identifier) => {
+                            write!(f, "{} is not defined", identifier)
+                        }
+                    },
+                    compiler::CompilerError::CodegenError(error, _) => match error {
+                        codegen::CodegenError::MmapError(error) => {
+                            write!(f, "could not create memory map: {}", error)
+                        }
+                        codegen::CodegenError::NotImplemented(message) => {
+                            write!(f, "not yet implemented: {}", message)
+                        }
+                        codegen::CodegenError::InternalError(message) => {
+                            write!(f, "internal error: {}", message)
+                        }
+
+                        // codegen::CodegenError::ValueDecodeError(err) => {
+                        //     write!(f, "value decoding error: {:?}", err)
+                        // }
+                        codegen::CodegenError::IcedError(err) => {
+                            write!(f, "assembly error: {:?}", err)
+                        }
+                    },
+                }?;
+
+                match error {
+                    compiler::CompilerError::CompileError(_, location)
+                    | compiler::CompilerError::CodegenError(_, location) => {
+                        write_location(f, location)
                     }
-                    compiler::CompileError::UnresolvedSymbol(identifier) => {
-                        write!(f, "{} is not defined", identifier)
+                }
+            }
+            EvaluationError::RuntimeError(error, location) => {
+                match error {
+                    runtime::RuntimeError::TypeMismatch(operator, lhs, rhs) => {
+                        let verb = match operator {
+                            ArithmeticOperator::Add => "add",
+                            ArithmeticOperator::Subtract => "subtract",
+                            ArithmeticOperator::Multiply => "multiply",
+                            ArithmeticOperator::Divide => "divide",
+                            ArithmeticOperator::Remainder => "take the remainder of",
+                            ArithmeticOperator::Power => "exponentiate",
+                            ArithmeticOperator::BitwiseAnd => "bitwise and",
+                            ArithmeticOperator::BitwiseOr => "bitwise or",
+                            ArithmeticOperator::BitwiseXor => "bitwise xor",
+                            ArithmeticOperator::ShiftLeft
+                            | ArithmeticOperator::ShiftRight
+                            | ArithmeticOperator::LogicalShiftRight => "shift",
+                        };
+                        write!(f, "cannot {} {} and {}", verb, lhs, rhs)
                     }
-                },
-                compiler::CompilerError::CodegenError(error) => match error {
-                    codegen::CodegenError::MmapError(error) => {
-                        write!(f, "could not create memory map: {}", error)
+                    runtime::RuntimeError::IncomparableTypes(lhs, rhs) => {
+                        write!(f, "cannot compare {} and {}", lhs, rhs)
                     }
-                    codegen::CodegenError::NotImplemented(message) => {
-                        write!(f, "not yet implemented: {}", message)
+                    runtime::RuntimeError::DivisionByZero => write!(f, "division by zero"),
+                    runtime::RuntimeError::InvalidShiftAmount(amount) => {
+                        write!(f, "cannot shift by {}", amount)
                     }
-                    codegen::CodegenError::InternalError(message) => {
-                        write!(f, "internal error: {}", message)
+                    runtime::RuntimeError::InvalidExponent(exponent) => {
+                        write!(f, "cannot raise to the power of {}", exponent)
                     }
-
-                    codegen::CodegenError::ValueEncodeError(err) => {
-                        write!(f, "value encoding error: {:?}", err)
+                    runtime::RuntimeError::NotABoolean(value_type) => {
+                        write!(f, "expected a boolean condition, found {}", value_type)
+                    }
+                    runtime::RuntimeError::InvalidArgument(function, expected, actual) => write!(
+                        f,
+                        "{} expects a {} argument but was given a {}",
+                        function, expected, actual
+                    ),
+                    runtime::RuntimeError::IndexOutOfRange(index, length) => write!(
+                        f,
+                        "index {} is out of range for a string of length {}",
+                        index, length
+                    ),
+                    runtime::RuntimeError::InvalidRange(start, end) => {
+                        write!(f, "range start {} is after its end {}", start, end)
+                    }
+                    runtime::RuntimeError::PieceOutOfRange(index, count) => write!(
+                        f,
+                        "piece {} is out of range for a string split into {} {}",
+                        index,
+                        count,
+                        if *count == 1 { "piece" } else { "pieces" }
+                    ),
+                    runtime::RuntimeError::EmptySeparator => {
+                        write!(f, "split expects a separator that isn't empty")
                     }
-                    // codegen::CodegenError::ValueDecodeError(err) => {
-                    //     write!(f, "value decoding error: {:?}", err)
-                    // }
-                    codegen::CodegenError::IcedError(err) => {
-                        write!(f, "assembly error: {:?}", err)
+                    runtime::RuntimeError::InvalidFormat(message) => {
+                        write!(f, "invalid format: {}", message)
                     }
-                },
-            },
+                    runtime::RuntimeError::StackOverflow(name) => {
+                        write!(f, "maximum recursion depth exceeded in '{}'", name)
+                    }
+                }?;
+
+                write_location(f, location)
+            }
         }
     }
 }
+
+fn write_location(
+    f: &mut std::fmt::Formatter<'_>,
+    location: &Option<Location>,
+) -> std::fmt::Result {
+    match location {
+        Some(location) => write!(f, " at {}", location),
+        None => Ok(()),
+    }
+}
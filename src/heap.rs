@@ -0,0 +1,244 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+};
+
+use num_bigint::BigInt;
+
+use crate::value::EncodedValue;
+
+// Values that don't fit in a machine word live on this heap, and generated
+// code only ever sees pointers to them. Objects are immutable once they've
+// been allocated, so the same pointer can be copied, stored and returned any
+// number of times, and decoding a value copies what it needs out of the
+// object rather than taking ownership of it.
+//
+// Every object is owned by the heap rather than by the values pointing at it,
+// and is freed by a mark-and-sweep collection once nothing refers to it.
+// Constants that are baked into generated code are interned and pinned, since
+// there's no telling how many times that code will run.
+#[derive(Debug)]
+pub enum HeapObject {
+    String(String),
+    BigInteger(BigInt),
+}
+
+#[derive(Debug)]
+struct Allocation {
+    // Only read through the pointers handed out to generated code.
+    #[allow(dead_code)]
+    object: Box<HeapObject>,
+    pinned: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GcStats {
+    pub collections: usize,
+    pub allocated_objects: usize,
+    pub freed_objects: usize,
+    pub live_objects: usize,
+}
+
+impl std::fmt::Display for GcStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} collections, {} objects allocated, {} freed, {} live",
+            self.collections, self.allocated_objects, self.freed_objects, self.live_objects
+        )
+    }
+}
+
+// Collections are triggered by allocating this many objects on top of the
+// ones that survived the last collection.
+const INITIAL_COLLECTION_THRESHOLD: usize = 1024;
+
+#[derive(Debug)]
+struct Heap {
+    // Keyed by the address of the object, which is what generated code holds.
+    objects: HashMap<u64, Allocation>,
+    interned_strings: HashMap<String, u64>,
+    next_collection: usize,
+    stats: GcStats,
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self {
+            objects: HashMap::new(),
+            interned_strings: HashMap::new(),
+            next_collection: INITIAL_COLLECTION_THRESHOLD,
+            stats: GcStats::default(),
+        }
+    }
+}
+
+impl Heap {
+    fn insert(&mut self, object: HeapObject, pinned: bool) -> u64 {
+        let object = Box::new(object);
+        let address = &*object as *const HeapObject as u64;
+        self.objects.insert(address, Allocation { object, pinned });
+        self.stats.allocated_objects += 1;
+        address
+    }
+
+    // Frees every object that isn't pinned or referred to by one of `roots`,
+    // returning how many were freed. Roots are checked conservatively, so
+    // anything that looks like a pointer to an object keeps it alive.
+    fn collect(&mut self, roots: impl Iterator<Item = u64>) -> usize {
+        let mut marked = HashSet::new();
+
+        // Objects don't refer to other objects yet, so marking doesn't need
+        // to trace any further than the roots.
+        for root in roots {
+            let root = unsafe { EncodedValue::from_encoded_value(root) };
+            if let Some(address) = root.heap_address() {
+                if self.objects.contains_key(&address) {
+                    marked.insert(address);
+                }
+            }
+        }
+
+        let live_before = self.objects.len();
+        self.objects
+            .retain(|address, allocation| allocation.pinned || marked.contains(address));
+        let freed = live_before - self.objects.len();
+
+        self.next_collection = INITIAL_COLLECTION_THRESHOLD.max(self.objects.len() * 2);
+        self.stats.collections += 1;
+        self.stats.freed_objects += freed;
+
+        freed
+    }
+}
+
+thread_local! {
+    static HEAP: RefCell<Heap> = RefCell::new(Heap::default());
+
+    // The highest stack address that generated code may be using, while any
+    // generated code is running.
+    static STACK_BASE: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+// Moves an object onto the heap, returning its address. This is a safepoint,
+// so it may run a collection first.
+pub fn allocate(object: HeapObject) -> u64 {
+    if HEAP.with(|heap| {
+        let heap = heap.borrow();
+        heap.objects.len() >= heap.next_collection
+    }) {
+        collect();
+    }
+
+    HEAP.with(|heap| heap.borrow_mut().insert(object, false))
+}
+
+// Moves a constant onto the heap, returning its address. Constants are never
+// freed, and constant strings with the same contents share a single object.
+pub fn allocate_constant(object: HeapObject) -> u64 {
+    HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+
+        match object {
+            HeapObject::String(string) => {
+                if let Some(address) = heap.interned_strings.get(&string) {
+                    return *address;
+                }
+
+                let address = heap.insert(HeapObject::String(string.clone()), true);
+                heap.interned_strings.insert(string, address);
+                address
+            }
+            object => heap.insert(object, true),
+        }
+    })
+}
+
+// Returns the object at `address`.
+//
+// This is unsafe because `address` must have been returned by `allocate` or
+// `allocate_constant`, and the object must still be alive.
+pub unsafe fn object<'a>(address: u64) -> &'a HeapObject {
+    &*(address as *const HeapObject)
+}
+
+// Runs `f`, which calls into generated code, scanning the stack below this
+// frame for roots if a collection happens in the meantime.
+//
+// Generated code only ever holds values in the registers `abi::emit_call`
+// saves, and only reaches the heap through calls it makes, so every value it
+// holds is on the stack by the time a collection can run.
+pub fn with_stack_roots<T>(f: impl FnOnce() -> T) -> T {
+    let marker = 0u64;
+    let base = std::hint::black_box(&marker) as *const u64 as u64;
+
+    // Calls made from inside generated code are already covered by the
+    // outermost one.
+    if STACK_BASE.with(|stack_base| stack_base.get()).is_some() {
+        return f();
+    }
+
+    STACK_BASE.with(|stack_base| stack_base.set(Some(base)));
+    let result = f();
+    STACK_BASE.with(|stack_base| stack_base.set(None));
+
+    result
+}
+
+// Runs a collection now, returning how many objects were freed.
+pub fn collect() -> usize {
+    let marker = 0u64;
+    let top = std::hint::black_box(&marker) as *const u64 as u64;
+
+    // With no generated code running, nothing refers to the heap besides
+    // constants.
+    let stack = match STACK_BASE.with(|stack_base| stack_base.get()) {
+        Some(base) => top..base,
+        None => 0..0,
+    };
+
+    let roots = stack
+        .step_by(8)
+        .map(|address| unsafe { std::ptr::read_volatile(address as *const u64) });
+
+    HEAP.with(|heap| heap.borrow_mut().collect(roots))
+}
+
+pub fn stats() -> GcStats {
+    HEAP.with(|heap| {
+        let heap = heap.borrow();
+        GcStats {
+            live_objects: heap.objects.len(),
+            ..heap.stats
+        }
+    })
+}
+
+#[test]
+fn test_interned_strings() {
+    let first = allocate_constant(HeapObject::String("risp".to_owned()));
+    let second = allocate_constant(HeapObject::String("risp".to_owned()));
+    let other = allocate_constant(HeapObject::String("other".to_owned()));
+
+    assert_eq!(first, second);
+    assert_ne!(first, other);
+    assert!(matches!(
+        unsafe { object(first) },
+        HeapObject::String(string) if string == "risp"
+    ));
+}
+
+#[test]
+fn test_collect_unreachable_objects() {
+    let constant = allocate_constant(HeapObject::String("constant".to_owned()));
+    allocate(HeapObject::String("garbage".to_owned()));
+
+    let freed = collect();
+
+    assert_eq!(freed, 1);
+    assert!(matches!(
+        unsafe { object(constant) },
+        HeapObject::String(string) if string == "constant"
+    ));
+    assert_eq!(stats().freed_objects, 1);
+}
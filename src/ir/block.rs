@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use crate::{
     compiler::{StackFrame, Symbol},
     ir,
-    parser::Identifier,
+    parser::{Identifier, Location},
 };
 
 use super::{instruction::Instruction, opcode::Opcode, slot::Slot, AssignmentTarget, Label};
@@ -13,14 +13,23 @@ pub struct Block<'a, 'b> {
     stack_frame: &'a mut StackFrame<'b>,
     instructions: Vec<Instruction>,
     cache: HashMap<Symbol, Slot>,
+    strict_conditions: bool,
+    // The location of the code being compiled, which is given to every
+    // opcode pushed until it changes.
+    location: Option<Location>,
 }
 
 impl<'a, 'b> Block<'a, 'b> {
-    pub(crate) fn new(stack_frame: &'a mut StackFrame<'b>) -> Block<'a, 'b> {
+    pub(crate) fn new(
+        stack_frame: &'a mut StackFrame<'b>,
+        strict_conditions: bool,
+    ) -> Block<'a, 'b> {
         Self {
             stack_frame,
             instructions: Vec::new(),
             cache: HashMap::new(),
+            strict_conditions,
+            location: None,
         }
     }
 
@@ -30,10 +39,23 @@ impl<'a, 'b> Block<'a, 'b> {
 
     pub fn push_op(&mut self, opcode: Opcode) -> Slot {
         let destination = self.slot();
-        self.push(Instruction::opcode(destination, opcode));
+        self.push(Instruction::opcode(
+            destination,
+            opcode,
+            self.location.clone(),
+        ));
         destination
     }
 
+    // Returns the location that was being compiled before.
+    pub fn set_location(&mut self, location: Option<Location>) -> Option<Location> {
+        std::mem::replace(&mut self.location, location)
+    }
+
+    pub fn location(&self) -> Option<&Location> {
+        self.location.as_ref()
+    }
+
     pub fn set_label(&mut self, label: Label) {
         self.instructions.push(Instruction::label(label));
     }
@@ -78,7 +100,8 @@ impl<'a, 'b> Block<'a, 'b> {
                         // self.cache.insert(symbol, slot);
                         Some(slot)
                     }
-                    Symbol::Function(_function, _arity) => todo!("resolve function to slot"),
+                    // Functions aren't values yet.
+                    Symbol::Function(..) | Symbol::CurrentFunction(_) => None,
                     Symbol::StackVariable(offset) => {
                         let slot = self.push_op(ir::Opcode::StackVariable(offset));
                         // self.cache.insert(symbol, slot);
@@ -86,7 +109,7 @@ impl<'a, 'b> Block<'a, 'b> {
                     }
                 }
             }
-            None => unimplemented!("undefined symbol"),
+            None => None,
         }
     }
 
@@ -94,9 +117,145 @@ impl<'a, 'b> Block<'a, 'b> {
         self.stack_frame.stack_slots()
     }
 
+    pub(crate) fn strict_conditions(&self) -> bool {
+        self.strict_conditions
+    }
+
+    pub(crate) fn function_name(&self) -> Option<&Identifier> {
+        self.stack_frame.function_name()
+    }
+
     pub(crate) fn requires_stack_frame(&self) -> bool {
         self.stack_slots() > 0
     }
+
+    // Keeps the value of `slot` on the stack rather than in a register, for
+    // when there aren't enough registers to go round. Literals and stack
+    // variables are produced again right before each use instead.
+    pub(crate) fn spill(&mut self, slot: Slot) {
+        let Some(definition) = self.instructions.iter().position(|instruction| {
+            matches!(instruction, Instruction::Opcode { destination, .. } if *destination == slot)
+        }) else {
+            return;
+        };
+
+        let Instruction::Opcode { opcode, .. } = &self.instructions[definition] else {
+            unreachable!()
+        };
+
+        match opcode {
+            Opcode::Literal(value) => {
+                let value = value.clone();
+                self.load_before_uses(slot, definition, || Opcode::Literal(value.clone()));
+            }
+            Opcode::StackVariable(offset) => {
+                let offset = *offset;
+                self.load_before_uses(slot, definition, || Opcode::StackVariable(offset));
+            }
+            // The values of the branches of a condition are all stored in the
+            // same place, and the condition's result is loaded from there.
+            Opcode::PhiStart(_) => {
+                let offset = self.stack_frame.insert_anonymous_stack_slot();
+                let mut phis = vec![slot];
+
+                for instruction in &mut self.instructions {
+                    if let Instruction::Opcode {
+                        destination,
+                        opcode: Opcode::PhiEnd(slots),
+                        location,
+                    } = instruction
+                    {
+                        if slots.contains(&slot) {
+                            phis = std::mem::take(slots);
+                            *instruction = Instruction::opcode(
+                                *destination,
+                                Opcode::StackVariable(offset),
+                                location.take(),
+                            );
+                        }
+                    }
+                }
+
+                for instruction in &mut self.instructions {
+                    if let Instruction::Opcode {
+                        destination,
+                        opcode: Opcode::PhiStart(value),
+                        ..
+                    } = instruction
+                    {
+                        if phis.contains(destination) {
+                            *instruction = Instruction::Assign(
+                                AssignmentTarget::StackVariable(offset),
+                                *value,
+                            );
+                        }
+                    }
+                }
+            }
+            _ => {
+                let offset = self.stack_frame.insert_anonymous_stack_slot();
+                self.load_before_uses(slot, definition, || Opcode::StackVariable(offset));
+                self.instructions.insert(
+                    definition + 1,
+                    Instruction::Assign(AssignmentTarget::StackVariable(offset), slot),
+                );
+            }
+        }
+    }
+
+    // Variables are read right where their value is used, so reading one and
+    // then assigning to it, as in `x + (x = 1)`, would see the new value. This
+    // copies the value of `slot` aside if any instruction from `start` on
+    // assigns to the variable it reads.
+    pub(crate) fn keep_value(&mut self, slot: Slot, start: usize) -> Slot {
+        let Some(definition) = self.instructions.iter().position(|instruction| {
+            matches!(instruction, Instruction::Opcode { destination, .. } if *destination == slot)
+        }) else {
+            return slot;
+        };
+
+        let target = match &self.instructions[definition] {
+            Instruction::Opcode {
+                opcode: Opcode::StackVariable(offset),
+                ..
+            } => AssignmentTarget::StackVariable(*offset),
+            Instruction::Opcode {
+                opcode: Opcode::FunctionArgument(index),
+                ..
+            } => AssignmentTarget::FunctionArgument(*index),
+            _ => return slot,
+        };
+
+        let assigned = self.instructions[start..].iter().any(
+            |instruction| matches!(instruction, Instruction::Assign(assigned, _) if *assigned == target),
+        );
+        if !assigned {
+            return slot;
+        }
+
+        let offset = self.stack_frame.insert_anonymous_stack_slot();
+        self.instructions.insert(
+            definition + 1,
+            Instruction::Assign(AssignmentTarget::StackVariable(offset), slot),
+        );
+        self.push_op(Opcode::StackVariable(offset))
+    }
+
+    // Gives each instruction after `definition` that uses `slot` a slot of its
+    // own, produced by `opcode` right before it.
+    fn load_before_uses(&mut self, slot: Slot, definition: usize, opcode: impl Fn() -> Opcode) {
+        let uses = (definition + 1..self.instructions.len())
+            .filter(|index| self.instructions[*index].operands().contains(&slot))
+            .collect::<Vec<_>>();
+
+        for index in uses.into_iter().rev() {
+            let location = self.instructions[index].location().cloned();
+            let replacement = Slot::new();
+            self.instructions[index].replace_operand(slot, replacement);
+            self.instructions
+                .insert(index, Instruction::opcode(replacement, opcode(), location));
+        }
+    }
 }
 
 impl<'a, 'b> std::fmt::Display for Block<'a, 'b> {
@@ -109,6 +268,7 @@ impl<'a, 'b> std::fmt::Display for Block<'a, 'b> {
                 Instruction::Opcode {
                     destination,
                     opcode,
+                    ..
                 } => {
                     writeln!(f, "  {} = {}", destination, opcode)?;
                 }
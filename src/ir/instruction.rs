@@ -1,21 +1,112 @@
-use super::{opcode::Opcode, slot::Slot, AssignmentTarget, Label};
+use crate::parser::Location;
+
+use super::{opcode::Opcode, slot::Slot, AssignmentTarget, JumpCondition, Label};
 
 #[derive(Debug)]
 pub enum Instruction {
     Label(Label),
-    Opcode { destination: Slot, opcode: Opcode },
+    // The location is that of the code the opcode was compiled from.
+    Opcode {
+        destination: Slot,
+        opcode: Opcode,
+        location: Option<Location>,
+    },
     Assign(AssignmentTarget, Slot),
 }
 
 impl Instruction {
-    pub fn opcode(destination: Slot, opcode: Opcode) -> Self {
+    pub fn opcode(destination: Slot, opcode: Opcode, location: Option<Location>) -> Self {
         Self::Opcode {
             destination,
             opcode,
+            location,
+        }
+    }
+
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            Instruction::Opcode { location, .. } => location.as_ref(),
+            Instruction::Label(_) | Instruction::Assign(..) => None,
         }
     }
 
     pub fn label(label: Label) -> Self {
         Self::Label(label)
     }
+
+    // The slots whose values the instruction uses.
+    pub fn operands(&self) -> Vec<Slot> {
+        match self {
+            Instruction::Label(_) => Vec::new(),
+            Instruction::Opcode { opcode, .. } => match opcode {
+                Opcode::Literal(_)
+                | Opcode::FunctionArgument(_)
+                | Opcode::StackVariable(_)
+                | Opcode::Return => Vec::new(),
+                Opcode::SetReturnValue(slot) | Opcode::PhiStart(slot) => vec![*slot],
+                Opcode::Jump(condition, _) => match condition {
+                    JumpCondition::Unconditional => Vec::new(),
+                    JumpCondition::Truthy(slot) | JumpCondition::StrictlyTrue(slot) => {
+                        vec![*slot]
+                    }
+                    JumpCondition::Greater(lhs, rhs)
+                    | JumpCondition::GreaterOrEqual(lhs, rhs)
+                    | JumpCondition::Less(lhs, rhs)
+                    | JumpCondition::LessOrEqual(lhs, rhs)
+                    | JumpCondition::Equal(lhs, rhs)
+                    | JumpCondition::NotEqual(lhs, rhs) => vec![*lhs, *rhs],
+                },
+                Opcode::BinaryOperator(lhs, _, rhs) => vec![*lhs, *rhs],
+                Opcode::CallFunction(_, slots)
+                | Opcode::TailCall(_, slots)
+                | Opcode::CallBuiltin(_, slots)
+                | Opcode::PhiEnd(slots) => slots.clone(),
+            },
+            Instruction::Assign(_, slot) => vec![*slot],
+        }
+    }
+
+    // Makes the instruction use `replacement` wherever it used `slot`.
+    pub fn replace_operand(&mut self, slot: Slot, replacement: Slot) {
+        let replace = |operand: &mut Slot| {
+            if *operand == slot {
+                *operand = replacement;
+            }
+        };
+
+        match self {
+            Instruction::Label(_) => {}
+            Instruction::Opcode { opcode, .. } => match opcode {
+                Opcode::Literal(_)
+                | Opcode::FunctionArgument(_)
+                | Opcode::StackVariable(_)
+                | Opcode::Return => {}
+                Opcode::SetReturnValue(operand) | Opcode::PhiStart(operand) => replace(operand),
+                Opcode::Jump(condition, _) => match condition {
+                    JumpCondition::Unconditional => {}
+                    JumpCondition::Truthy(operand) | JumpCondition::StrictlyTrue(operand) => {
+                        replace(operand)
+                    }
+                    JumpCondition::Greater(lhs, rhs)
+                    | JumpCondition::GreaterOrEqual(lhs, rhs)
+                    | JumpCondition::Less(lhs, rhs)
+                    | JumpCondition::LessOrEqual(lhs, rhs)
+                    | JumpCondition::Equal(lhs, rhs)
+                    | JumpCondition::NotEqual(lhs, rhs) => {
+                        replace(lhs);
+                        replace(rhs);
+                    }
+                },
+                Opcode::BinaryOperator(lhs, _, rhs) => {
+                    replace(lhs);
+                    replace(rhs);
+                }
+                Opcode::CallFunction(_, slots)
+                | Opcode::TailCall(_, slots)
+                | Opcode::CallBuiltin(_, slots)
+                | Opcode::PhiEnd(slots) => slots.iter_mut().for_each(replace),
+            },
+            Instruction::Assign(_, operand) => replace(operand),
+        }
+    }
 }
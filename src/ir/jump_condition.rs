@@ -3,8 +3,12 @@ use super::Slot;
 #[derive(Debug)]
 pub enum JumpCondition {
     Unconditional,
-    Zero(Slot),
-    NotZero(Slot),
+    // Taken if the value counts as true. `false`, zero and the empty string
+    // are false, and everything else is true.
+    Truthy(Slot),
+    // Taken if the value is `true`. Any value other than a boolean is a
+    // runtime error.
+    StrictlyTrue(Slot),
     Greater(Slot, Slot),
     GreaterOrEqual(Slot, Slot),
     Less(Slot, Slot),
@@ -17,8 +21,8 @@ impl std::fmt::Display for JumpCondition {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             JumpCondition::Unconditional => write!(f, "always"),
-            JumpCondition::Zero(slot) => write!(f, "{} == 0", slot),
-            JumpCondition::NotZero(slot) => write!(f, "{} != 0", slot),
+            JumpCondition::Truthy(slot) => write!(f, "truthy {}", slot),
+            JumpCondition::StrictlyTrue(slot) => write!(f, "{} == true", slot),
             JumpCondition::Equal(lhs, rhs) => write!(f, "{} == {}", lhs, rhs),
             JumpCondition::NotEqual(lhs, rhs) => write!(f, "{} != {}", lhs, rhs),
             JumpCondition::Greater(lhs, rhs) => write!(f, "{} > {}", lhs, rhs),
@@ -1,10 +1,10 @@
 use std::rc::Rc;
 
-use crate::{codegen::Function, parser::BinaryOperator, value::Value};
+use crate::{codegen::Function, parser::BinaryOperator, runtime::Builtin, value::Value};
 
 use super::{jump_condition::JumpCondition, slot::Slot, Label};
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum AssignmentTarget {
     StackVariable(usize),
     FunctionArgument(usize),
@@ -19,6 +19,23 @@ impl std::fmt::Display for AssignmentTarget {
     }
 }
 
+// The function a call goes to.
+#[derive(Debug)]
+pub enum Callee {
+    Function(Rc<Function>),
+    // The function being compiled.
+    Current,
+}
+
+impl std::fmt::Display for Callee {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Callee::Function(func) => write!(f, "{func}"),
+            Callee::Current => write!(f, "self"),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum Opcode {
     Literal(Value),
@@ -28,7 +45,11 @@ pub enum Opcode {
     Jump(JumpCondition, Label),
 
     BinaryOperator(Slot, BinaryOperator, Slot),
-    CallFunction(Rc<Function>, Vec<Slot>),
+    CallFunction(Callee, Vec<Slot>),
+    // Leaves the current function for the one called, which returns straight
+    // to our caller. Nothing after it runs.
+    TailCall(Callee, Vec<Slot>),
+    CallBuiltin(Builtin, Vec<Slot>),
     StackVariable(usize),
     PhiStart(Slot),
     PhiEnd(Vec<Slot>),
@@ -40,17 +61,19 @@ impl std::fmt::Display for Opcode {
             Opcode::Literal(Value::Integer(value)) => write!(f, "literal {value}"),
             Opcode::Literal(Value::String(value)) => write!(f, "literal {value}"),
             Opcode::Literal(Value::Boolean(value)) => write!(f, "literal {value}"),
+            Opcode::Literal(Value::BigInteger(value)) => write!(f, "literal {value}"),
             Opcode::BinaryOperator(lhs, op, rhs) => write!(f, "{lhs} {op} {rhs}"),
-            Opcode::CallFunction(func, args) => {
-                write!(f, "call {func} (")?;
-                for (index, arg) in args.iter().enumerate() {
-                    write!(f, "{arg}")?;
-
-                    if index < args.len() - 1 {
-                        write!(f, ", ")?;
-                    }
-                }
-                write!(f, ")")
+            Opcode::CallFunction(callee, args) => {
+                write!(f, "call {callee} (")?;
+                write_arguments(f, args)
+            }
+            Opcode::TailCall(callee, args) => {
+                write!(f, "tail call {callee} (")?;
+                write_arguments(f, args)
+            }
+            Opcode::CallBuiltin(builtin, args) => {
+                write!(f, "call builtin {builtin} (")?;
+                write_arguments(f, args)
             }
             Opcode::FunctionArgument(index) => write!(f, "arg {index}"),
             Opcode::SetReturnValue(slot) => write!(f, "return_value = {slot}"),
@@ -76,3 +99,14 @@ impl std::fmt::Display for Opcode {
         }
     }
 }
+
+fn write_arguments(f: &mut std::fmt::Formatter<'_>, args: &[Slot]) -> std::fmt::Result {
+    for (index, arg) in args.iter().enumerate() {
+        write!(f, "{arg}")?;
+
+        if index < args.len() - 1 {
+            write!(f, ", ")?;
+        }
+    }
+    write!(f, ")")
+}
@@ -1,20 +1,44 @@
 mod codegen;
 mod compiler;
 mod evaluator;
+mod heap;
 mod ir;
 mod parser;
+mod runtime;
 mod tests;
 mod value;
 
+use std::rc::Rc;
+
 use value::Value;
 
-use crate::evaluator::Evaluator;
+use crate::{
+    compiler::{CompileOptions, Lint, Lints, Warning},
+    evaluator::{EvaluationError, Evaluator},
+    parser::Located,
+};
 
 fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    let lints = lints(&args);
+
+    if let [_, command, path, ..] = args.as_slice() {
+        if command == "check" {
+            std::process::exit(check(path, lints));
+        }
+    }
+
     let mut readline = rustyline::Editor::<()>::new().expect("readline error");
     let _ = readline.load_history("~/.risp-history");
 
-    let mut evaluator = Evaluator::default();
+    let options = CompileOptions {
+        strict_conditions: std::env::args().any(|arg| arg == "--strict"),
+        lints,
+    };
+    let mut evaluator = Evaluator::with_options(options);
+    if let Some(max_stack_size) = max_stack_size(&args) {
+        evaluator.set_max_stack_size(max_stack_size);
+    }
 
     loop {
         let line = readline.readline("risp> ");
@@ -23,11 +47,16 @@ fn main() {
             Ok(line) => {
                 let result = evaluator.evaluate(&line);
 
+                for warning in evaluator.take_warnings() {
+                    eprintln!("Warning: {}", describe_warning(&warning));
+                }
+
                 match result {
                     Ok(value) => match value {
                         Value::Integer(value) => println!("(integer) {:?}", value),
                         Value::String(value) => println!("(string) {:?}", value),
                         Value::Boolean(value) => println!("(boolean) {:?}", value),
+                        Value::BigInteger(value) => println!("(integer) {}", value),
                     },
                     Err(error) => eprintln!("Evaluation error: {}", error),
                 }
@@ -45,4 +74,107 @@ fn main() {
     if let Err(err) = readline.save_history("~/.risp-history") {
         eprintln!("Failed to save history: {}", err);
     }
+
+    if std::env::args().any(|arg| arg == "--gc-stats") {
+        println!("GC: {}", heap::stats());
+    }
+}
+
+// Turns off the lints named by `--allow=<lint>` arguments.
+fn lints(args: &[String]) -> Lints {
+    let mut lints = Lints::default();
+
+    for name in args.iter().filter_map(|arg| arg.strip_prefix("--allow=")) {
+        match Lint::from_name(name) {
+            Some(lint) => lints.allow(lint),
+            None => eprintln!("Unknown lint: {}", name),
+        }
+    }
+
+    lints
+}
+
+// The limit given by a `--max-stack-size=<bytes>` argument, if there is one.
+fn max_stack_size(args: &[String]) -> Option<usize> {
+    let value = args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--max-stack-size="))?;
+
+    match value.parse() {
+        Ok(max_stack_size) => Some(max_stack_size),
+        Err(_) => {
+            eprintln!("Invalid stack size: {}", value);
+            None
+        }
+    }
+}
+
+fn describe_warning(warning: &Located<Warning>) -> String {
+    format!(
+        "{} at {} [{}]",
+        warning.value,
+        warning.location,
+        warning.value.lint().name()
+    )
+}
+
+// Reports every syntax error in the file at `path`, every error the compiler
+// would find in the code that did parse, and warnings about all of it,
+// returning the exit code.
+fn check(path: &str, lints: Lints) -> i32 {
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("{}: {}", path, error);
+            return 2;
+        }
+    };
+
+    let file = Rc::from(path);
+    let (block, errors) = parser::parse_with_recovery(&source, Some(&file));
+    for error in &errors {
+        eprintln!("{}: {}\n", path, error);
+    }
+
+    let compile_errors = compiler::analyze_program(&block);
+    let compile_error_count = compile_errors.len();
+    for error in compile_errors {
+        eprintln!("{}", EvaluationError::CompilerError(error));
+    }
+
+    let warnings = compiler::lint(&block, lints);
+    for warning in &warnings {
+        eprintln!(
+            "{}: warning: {} [{}]",
+            warning.location,
+            warning.value,
+            warning.value.lint().name()
+        );
+    }
+
+    let mut summary = Vec::new();
+    match errors.len() {
+        0 => {}
+        1 => summary.push("1 syntax error".to_owned()),
+        count => summary.push(format!("{} syntax errors", count)),
+    }
+    match compile_error_count {
+        0 => {}
+        1 => summary.push("1 compile error".to_owned()),
+        count => summary.push(format!("{} compile errors", count)),
+    }
+    match warnings.len() {
+        0 => {}
+        1 => summary.push("1 warning".to_owned()),
+        count => summary.push(format!("{} warnings", count)),
+    }
+    if !summary.is_empty() {
+        eprintln!("{}", summary.join(", "));
+    }
+
+    if errors.is_empty() && compile_error_count == 0 {
+        0
+    } else {
+        1
+    }
 }
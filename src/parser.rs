@@ -1,29 +1,45 @@
 mod block;
+mod comment;
+mod error;
 mod expression;
 mod identifier;
 mod literal;
+mod location;
+mod recovery;
 mod statement;
 mod tokens;
 mod util;
 
+use std::rc::Rc;
+
+use nom::Slice;
+
 use crate::parser::block::parse_block_inner;
 
 pub use self::{
     block::{parse_block, Block},
+    error::{ParseError, ParseErrorKind},
     expression::{
         parse_expression, ArithmeticOperator, BinaryOperator, ComparisonOperator, Expression,
+        LogicalOperator, UnaryOperator,
     },
     identifier::{parse_identifier, Identifier},
-    literal::{parse_literal, Literal},
+    literal::{parse_literal, Literal, StringPart},
+    location::{Located, Location},
     statement::{
         parse_statement, Assignment, Condition, Loop, LoopPredicatePosition, Statement,
         VariableDeclaration,
     },
 };
 
-pub type Span<'a> = nom_locate::LocatedSpan<&'a str>;
-pub type ParseResult<'a, O, E = (Span<'a>, nom::error::ErrorKind), I = Span<'a>> =
-    nom::IResult<I, Token<'a, O>, E>;
+// Not used by the interpreter itself, which skips comments, but kept for
+// tools that need to see them.
+#[allow(unused_imports)]
+pub use self::comment::{parse_comments, Comment};
+
+// The extra field is the name of the file being parsed, if there is one.
+pub type Span<'a> = nom_locate::LocatedSpan<&'a str, Option<&'a Rc<str>>>;
+pub type ParseResult<'a, O, E = ParseError<'a>, I = Span<'a>> = nom::IResult<I, Token<'a, O>, E>;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Token<'a, T>
@@ -34,7 +50,58 @@ where
     pub value: T,
 }
 
-pub fn parse(line: &str) -> ParseResult<Block> {
-    println!("Parsing:\n{}", line);
-    parse_block_inner(Span::new(line))
+// Parses all of `source`, stopping at the first syntax error.
+pub fn parse(source: &str) -> Result<Block, nom::Err<ParseError<'_>>> {
+    println!("Parsing:\n{}", source);
+
+    let (block, errors) = parse_with_recovery(source, None);
+    match errors.into_iter().next() {
+        Some(error) => Err(nom::Err::Error(error)),
+        None => Ok(block),
+    }
+}
+
+// Parses as much of `source` as possible, returning every syntax error found
+// along the way. Statements that failed to parse are left in the block as
+// `Statement::Error`s.
+pub fn parse_with_recovery<'a>(
+    source: &'a str,
+    file: Option<&'a Rc<str>>,
+) -> (Block, Vec<ParseError<'a>>) {
+    let source = Span::new_extra(source, file);
+    let mut input = source;
+    let mut statements = Vec::new();
+
+    loop {
+        let (rest, block) = match parse_block_inner(input) {
+            Ok(result) => result,
+            Err(error) => {
+                statements.push(recovery::error_statement(input, error));
+                break;
+            }
+        };
+        statements.extend(block.value.0);
+
+        // Blocks only stop early at a closing brace, which doesn't belong
+        // to anything at the top level.
+        if rest.fragment().is_empty() {
+            break;
+        }
+        statements.push(Located::new(
+            Location::new(rest, 1),
+            Statement::Error(ParseErrorKind::UnexpectedInput),
+        ));
+        input = rest.slice(1..);
+    }
+
+    if statements.is_empty() {
+        statements.push(Located::new(
+            Location::new(input, 0),
+            Statement::Error(ParseErrorKind::Expected("a statement")),
+        ));
+    }
+
+    let block = Block(statements);
+    let errors = recovery::collect_errors(source, &block);
+    (block, errors)
 }
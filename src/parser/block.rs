@@ -1,24 +1,112 @@
-use nom::{multi::many1, sequence::delimited};
+use nom::{
+    branch::alt,
+    character::complete::{char, line_ending},
+    combinator::{eof, peek, value},
+    IResult,
+};
 use nom_locate::position;
 
+// we use this but Rust Analyzer doesn't notice it...?
+#[allow(unused_imports)]
+use nom::Slice;
+
 use super::{
+    comment::{line_whitespace, whitespace},
     parse_statement,
+    recovery::{error_statement, skip_statement},
     tokens::{close_brace_token, open_brace_token},
-    ParseResult, Span, Statement, Token,
+    Located, Location, ParseError, ParseErrorKind, ParseResult, Span, Statement, Token,
 };
 
 #[derive(Clone, Debug, PartialEq)]
-pub struct Block(pub Vec<Statement>);
+pub struct Block(pub Vec<Located<Statement>>);
 
 pub fn parse_block(input: Span) -> ParseResult<Block> {
-    delimited(open_brace_token, parse_block_inner, close_brace_token)(input)
+    let (input, _) = open_brace_token(input)?;
+    let (input, mut block) = parse_block_inner(input)?;
+
+    if block.value.0.is_empty() {
+        block.value.0.push(Located::new(
+            Location::new(input, 0),
+            Statement::Error(ParseErrorKind::Expected("a statement")),
+        ));
+    }
+
+    // The block can only have stopped at a closing brace or the end of the
+    // input, so a missing brace is kept as an error like any other, along
+    // with whatever the block did contain.
+    match close_brace_token(input) {
+        Ok((input, _)) => Ok((input, block)),
+        Err(_) => {
+            block.value.0.push(Located::new(
+                Location::new(input, 0),
+                Statement::Error(ParseErrorKind::ExpectedToken("}")),
+            ));
+            Ok((input, block))
+        }
+    }
 }
 
+// Parses statements up to the end of the input or a closing brace. Each
+// statement ends at a newline or `;`, unless it's the last thing in the
+// block. A statement that fails to parse is kept as a `Statement::Error`, and
+// parsing carries on with the next one, so that one mistake doesn't hide the
+// rest.
 pub fn parse_block_inner(input: Span) -> ParseResult<Block> {
-    let (input, position) = position(input)?;
-    let (input, statements) = many1(parse_statement)(input)?;
+    let (mut input, position) = position(input)?;
+    let mut statements = Vec::new();
+
+    loop {
+        match whitespace(input) {
+            Ok((rest, _)) => input = rest,
+            Err(error) => {
+                // Only an unterminated comment stops whitespace, and it runs
+                // to the end of the input.
+                statements.push(error_statement(input, error));
+                input = input.slice(input.fragment().len()..);
+            }
+        }
+
+        if input.fragment().is_empty() || input.fragment().starts_with('}') {
+            break;
+        }
+
+        // An empty statement.
+        if input.fragment().starts_with(';') {
+            input = input.slice(1..);
+            continue;
+        }
+
+        match parse_statement(input) {
+            Ok((rest, statement)) => {
+                let location = Location::between(statement.position, rest);
+                statements.push(Located::new(location, statement.value));
+
+                let ends_with_block = matches!(
+                    statements.last().map(|statement| &statement.value),
+                    Some(
+                        Statement::FunctionDefinition(_)
+                            | Statement::Condition(_)
+                            | Statement::Loop(_)
+                    )
+                );
+
+                match parse_statement_end(rest, ends_with_block) {
+                    Ok((rest, ())) => input = rest,
+                    Err(error) => {
+                        statements.push(error_statement(input, error));
+                        input = skip_statement(rest);
+                    }
+                }
+            }
+            Err(nom::Err::Incomplete(needed)) => return Err(nom::Err::Incomplete(needed)),
+            Err(error) => {
+                statements.push(error_statement(input, error));
+                input = skip_statement(input);
+            }
+        }
+    }
 
-    let statements = statements.iter().map(|t| t.value.clone()).collect();
     Ok((
         input,
         Token {
@@ -27,3 +115,28 @@ pub fn parse_block_inner(input: Span) -> ParseResult<Block> {
         },
     ))
 }
+
+// The end of a statement: a `;` or a line break, or the closing brace or end
+// of input that ends the block. Anything else on the same line is an error,
+// so that `x = 1 y = 2` isn't taken as two statements. A statement that ends
+// with a block, such as a function definition, is ended by its closing brace.
+fn parse_statement_end(input: Span, ends_with_block: bool) -> IResult<Span, (), ParseError> {
+    let (input, _) = line_whitespace(input)?;
+
+    if ends_with_block {
+        return Ok((input, ()));
+    }
+
+    alt((
+        value((), char(';')),
+        value((), line_ending),
+        value((), peek(char('}'))),
+        value((), eof),
+    ))(input)
+    .map_err(|_: nom::Err<ParseError>| {
+        nom::Err::Error(ParseError::new(
+            input,
+            ParseErrorKind::Expected("`;` or a new line"),
+        ))
+    })
+}
@@ -0,0 +1,209 @@
+use std::cell::RefCell;
+
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{multispace1, not_line_ending, space1},
+    combinator::recognize,
+    multi::many0_count,
+    sequence::pair,
+    IResult,
+};
+
+// we use this but Rust Analyzer doesn't notice it...?
+#[allow(unused_imports)]
+use nom::Slice;
+
+use nom_locate::position;
+
+use super::{parse_with_recovery, ParseError, ParseErrorKind, ParseResult, Span, Token};
+
+// The text of a comment as it was written, including its delimiters.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Comment {
+    // A `#` or `//` comment, which runs to the end of the line.
+    Line(String),
+    // A `/* */` comment, which can span lines and contain other block
+    // comments.
+    Block(String),
+}
+
+// Skips any whitespace and comments.
+pub fn whitespace(input: Span) -> IResult<Span, Span, ParseError> {
+    recognize(many0_count(alt((
+        multispace1,
+        recognize(line_comment),
+        recognize(block_comment),
+    ))))(input)
+}
+
+// Skips whitespace and comments up to the end of the line, leaving the line
+// break itself. A block comment is skipped whole, even if it spans lines.
+pub fn line_whitespace(input: Span) -> IResult<Span, Span, ParseError> {
+    recognize(many0_count(alt((
+        space1,
+        recognize(line_comment),
+        recognize(block_comment),
+    ))))(input)
+}
+
+pub(super) fn comment(input: Span) -> ParseResult<Comment> {
+    let (input, position) = position(input)?;
+    let (input, value) = alt((line_comment, block_comment))(input)?;
+    Ok((input, Token { position, value }))
+}
+
+fn line_comment(input: Span) -> IResult<Span, Comment, ParseError> {
+    let (rest, text) = recognize(pair(alt((tag("#"), tag("//"))), not_line_ending))(input)?;
+    let comment = Comment::Line(text.fragment().to_string());
+    record_comment(input, &comment);
+    Ok((rest, comment))
+}
+
+// An unterminated block comment is a failure, reported at its start.
+fn block_comment(input: Span) -> IResult<Span, Comment, ParseError> {
+    let (mut rest, _) = tag("/*")(input)?;
+    let mut depth = 1;
+
+    while depth > 0 {
+        let fragment = rest.fragment();
+        if fragment.starts_with("/*") {
+            depth += 1;
+            rest = rest.slice(2..);
+        } else if fragment.starts_with("*/") {
+            depth -= 1;
+            rest = rest.slice(2..);
+        } else if let Some(c) = fragment.chars().next() {
+            rest = rest.slice(c.len_utf8()..);
+        } else {
+            return Err(nom::Err::Failure(ParseError::new(
+                input,
+                ParseErrorKind::UnterminatedComment,
+            )));
+        }
+    }
+
+    let length = rest.location_offset() - input.location_offset();
+    let comment = Comment::Block(input.fragment()[..length].to_owned());
+    record_comment(input, &comment);
+    Ok((rest, comment))
+}
+
+// Comments skipped by the parser while `parse_comments` is running, along
+// with their offsets.
+thread_local! {
+    static RECORDED_COMMENTS: RefCell<Option<Vec<(usize, Comment)>>> =
+        const { RefCell::new(None) };
+}
+
+fn record_comment(input: Span, comment: &Comment) {
+    RECORDED_COMMENTS.with(|recorded| {
+        if let Some(recorded) = recorded.borrow_mut().as_mut() {
+            recorded.push((input.location_offset(), comment.clone()));
+        }
+    });
+}
+
+// Forgets the comments skipped by `parser` if it fails. What it took for a
+// comment might not be one, such as the `#` in `r#"..."#` after taking `r`
+// for an identifier, and whatever parses the input instead skips the real
+// comments again.
+pub(super) fn forget_comments_on_error<'a, O: std::fmt::Debug + std::cmp::PartialEq>(
+    mut parser: impl FnMut(Span<'a>) -> ParseResult<'a, O>,
+) -> impl FnMut(Span<'a>) -> ParseResult<'a, O> {
+    move |input| {
+        let recorded_count = RECORDED_COMMENTS
+            .with(|recorded| recorded.borrow().as_ref().map(Vec::len))
+            .unwrap_or(0);
+
+        let result = parser(input);
+        if result.is_err() {
+            RECORDED_COMMENTS.with(|recorded| {
+                if let Some(recorded) = recorded.borrow_mut().as_mut() {
+                    recorded.truncate(recorded_count);
+                }
+            });
+        }
+        result
+    }
+}
+
+// Parses `source` and returns every comment the parser skipped, along with
+// its position, for tools such as a formatter that need to keep them.
+#[allow(unused)]
+pub fn parse_comments(source: &str) -> Vec<Token<'_, Comment>> {
+    RECORDED_COMMENTS.with(|recorded| *recorded.borrow_mut() = Some(Vec::new()));
+    parse_with_recovery(source, None);
+    let mut recorded = RECORDED_COMMENTS
+        .with(|recorded| recorded.borrow_mut().take())
+        .unwrap_or_default();
+
+    // The same comment is skipped again each time the parser backtracks over
+    // it.
+    recorded.sort_by_key(|(offset, _)| *offset);
+    recorded.dedup_by_key(|(offset, _)| *offset);
+
+    let source = Span::new_extra(source, None);
+    recorded
+        .into_iter()
+        .map(|(offset, value)| Token {
+            position: source.slice(offset..offset),
+            value,
+        })
+        .collect()
+}
+
+#[test]
+fn test_whitespace() {
+    let input = Span::new_extra("  # one\n // two\n/* three /* nested */ */\t four", None);
+    let (rest, _) = whitespace(input).unwrap();
+    assert_eq!(*rest.fragment(), "four");
+
+    let input = Span::new_extra("/* unterminated /* */", None);
+    assert_eq!(
+        whitespace(input),
+        Err(nom::Err::Failure(ParseError::new(
+            input,
+            ParseErrorKind::UnterminatedComment
+        )))
+    );
+}
+
+#[test]
+fn test_parse_comments() {
+    let source = "# header\nlet s = \"not // a ${x /* but this is */} comment\" // trailing\n\
+                  r#\"# raw\"# /* a /* nested */ one */";
+    let comments = parse_comments(source)
+        .into_iter()
+        .map(|comment| {
+            (
+                comment.position.location_line(),
+                comment.position.get_column(),
+                comment.value,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        comments,
+        vec![
+            (1, 1, Comment::Line("# header".to_owned())),
+            (2, 23, Comment::Block("/* but this is */".to_owned())),
+            (2, 51, Comment::Line("// trailing".to_owned())),
+            (3, 12, Comment::Block("/* a /* nested */ one */".to_owned())),
+        ]
+    );
+
+    // Comments in a statement that failed to parse are skipped by recovery.
+    let comments = parse_comments("let = /* a */ 1 // b\n")
+        .into_iter()
+        .map(|comment| comment.value)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        comments,
+        vec![
+            Comment::Block("/* a */".to_owned()),
+            Comment::Line("// b".to_owned()),
+        ]
+    );
+}
@@ -0,0 +1,172 @@
+use std::cmp::Ordering;
+
+use nom::error::{ContextError, ErrorKind};
+
+use super::{comment::whitespace, Span};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError<'a> {
+    pub span: Span<'a>,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    // A token or keyword, such as `}` or `def`.
+    ExpectedToken(&'static str),
+    ExpectedChar(char),
+    // Something described in words, such as "an expression".
+    Expected(&'static str),
+    InvalidEscape,
+    // `${` that isn't followed by an expression and `}`.
+    InvalidInterpolation,
+    InvalidDigit,
+    IntegerTooLarge,
+    UnterminatedString,
+    UnterminatedComment,
+    // Input left over after everything that could be parsed.
+    UnexpectedInput,
+    // Anything nom reports that hasn't been given a better description.
+    Nom(ErrorKind),
+}
+
+impl<'a> ParseError<'a> {
+    pub fn new(span: Span<'a>, kind: ParseErrorKind) -> Self {
+        Self { span, kind }
+    }
+}
+
+impl<'a> nom::error::ParseError<Span<'a>> for ParseError<'a> {
+    fn from_error_kind(input: Span<'a>, kind: ErrorKind) -> Self {
+        Self::new(input, ParseErrorKind::Nom(kind))
+    }
+
+    fn append(_input: Span<'a>, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn from_char(input: Span<'a>, c: char) -> Self {
+        Self::new(input, ParseErrorKind::ExpectedChar(c))
+    }
+
+    // When every alternative fails, the one that got furthest is the most
+    // likely to be what was meant.
+    fn or(self, other: Self) -> Self {
+        match self
+            .span
+            .location_offset()
+            .cmp(&other.span.location_offset())
+        {
+            Ordering::Greater => self,
+            Ordering::Less => other,
+            Ordering::Equal if matches!(other.kind, ParseErrorKind::Nom(_)) => self,
+            Ordering::Equal => other,
+        }
+    }
+}
+
+impl<'a> ContextError<Span<'a>> for ParseError<'a> {
+    // Describes an error in terms of the parser it happened in, unless
+    // that parser got far enough to fail on something more specific.
+    fn add_context(input: Span<'a>, context: &'static str, other: Self) -> Self {
+        let start = match whitespace(input) {
+            Ok((rest, _)) => rest.location_offset(),
+            Err(_) => input.location_offset(),
+        };
+
+        let is_generic = matches!(other.kind, ParseErrorKind::Nom(_));
+        let is_expected = matches!(
+            other.kind,
+            ParseErrorKind::ExpectedToken(_)
+                | ParseErrorKind::ExpectedChar(_)
+                | ParseErrorKind::Expected(_)
+        );
+
+        if is_generic || (is_expected && other.span.location_offset() <= start) {
+            Self::new(other.span, ParseErrorKind::Expected(context))
+        } else {
+            other
+        }
+    }
+}
+
+impl std::fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseErrorKind::ExpectedToken(token) => write!(f, "expected `{}`", token),
+            ParseErrorKind::ExpectedChar(c) => write!(f, "expected `{}`", c),
+            ParseErrorKind::Expected(description) => write!(f, "expected {}", description),
+            ParseErrorKind::InvalidEscape => write!(f, "invalid escape sequence"),
+            ParseErrorKind::InvalidInterpolation => write!(f, "invalid string interpolation"),
+            ParseErrorKind::InvalidDigit => write!(f, "invalid integer literal"),
+            ParseErrorKind::IntegerTooLarge => write!(f, "integer literal is out of range"),
+            ParseErrorKind::UnterminatedString => write!(f, "unterminated string literal"),
+            ParseErrorKind::UnterminatedComment => write!(f, "unterminated block comment"),
+            ParseErrorKind::UnexpectedInput => write!(f, "unexpected input"),
+            ParseErrorKind::Nom(_) => write!(f, "unexpected input"),
+        }
+    }
+}
+
+// Shows the message with the line it's on, and a caret under the column:
+//
+//     expected `}` at line 1, column 17
+//       |
+//     1 | def f(x) { x + 1
+//       |                 ^
+impl<'a> std::fmt::Display for ParseError<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let line = self.span.location_line();
+        let column = self.span.get_utf8_column();
+
+        match self.kind {
+            ParseErrorKind::Nom(_) if self.span.fragment().is_empty() => {
+                write!(f, "unexpected end of input")?
+            }
+            _ => write!(f, "{}", self.kind)?,
+        }
+        writeln!(f, " at line {}, column {}", line, column)?;
+
+        write_snippet(f, &self.span)
+    }
+}
+
+fn write_snippet(f: &mut std::fmt::Formatter<'_>, span: &Span) -> std::fmt::Result {
+    let line_number = span.location_line().to_string();
+    let gutter = " ".repeat(line_number.len());
+
+    // The span may not reach the end of the line, so the line is pieced
+    // together from the text before the span and the span itself.
+    let before = &span.get_line_beginning()[..span.get_column() - 1];
+    let after = span.fragment().split('\n').next().unwrap_or_default();
+    let line = String::from_utf8_lossy(before) + after;
+    let line = line.trim_end_matches('\r');
+
+    // Tabs are kept so that the caret lines up however wide they're shown.
+    let indent = line
+        .chars()
+        .take(span.get_utf8_column() - 1)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect::<String>();
+
+    writeln!(f, "{} |", gutter)?;
+    writeln!(f, "{} | {}", line_number, line)?;
+    write!(f, "{} | {}^", gutter, indent)
+}
+
+#[test]
+fn test_render_error() {
+    use nom::Slice;
+
+    let source = Span::new_extra("def f(x) {\n\tx + 1", None);
+    let error = ParseError::new(source.slice(17..), ParseErrorKind::ExpectedChar('}'));
+    assert_eq!(
+        error.to_string(),
+        "expected `}` at line 2, column 7\n  |\n2 | \tx + 1\n  | \t     ^"
+    );
+
+    let error = ParseError::new(source.slice(17..), ParseErrorKind::Nom(ErrorKind::Tag));
+    assert!(error
+        .to_string()
+        .starts_with("unexpected end of input at line 2, column 7\n"));
+}
@@ -1,43 +1,79 @@
 mod binary_operator;
-mod function_call;
 mod identifier;
 mod literal;
+mod precedence;
+mod unary_operator;
 
-use nom::branch::alt;
+use nom::{branch::alt, error::context, sequence::terminated};
+use nom_locate::position;
 
 #[cfg(test)]
-use {super::Token, crate::tests::parse_test, nom::Slice};
+use {
+    crate::tests::{node, parse_test},
+    nom::Slice,
+};
 
 use self::{
-    binary_operator::parse_binary_operator_expression,
-    function_call::parse_function_call_expression, identifier::parse_identifier_expression,
-    literal::parse_literal_expression,
+    identifier::parse_identifier_expression, literal::parse_literal_expression,
+    precedence::parse_operator_expression,
 };
 
-pub use self::binary_operator::{ArithmeticOperator, BinaryOperator, ComparisonOperator};
-use super::{util::bracketed, Identifier, Literal, ParseResult, Span};
+pub use self::{
+    binary_operator::{ArithmeticOperator, BinaryOperator, ComparisonOperator, LogicalOperator},
+    unary_operator::UnaryOperator,
+};
+use super::{
+    comment::whitespace, statement::Assignment, util::bracketed, Identifier, Literal, Located,
+    ParseResult, Span, Token,
+};
 
 #[derive(Clone, Debug, PartialEq)]
+#[allow(clippy::enum_variant_names)]
 pub enum Expression {
     Identifier(Identifier),
-    FunctionCall(Identifier, Vec<Expression>),
+    FunctionCall(Identifier, Vec<Located<Expression>>),
     Literal(Literal),
-    BinaryExpression(Box<Expression>, BinaryOperator, Box<Expression>),
+    BinaryExpression(
+        Box<Located<Expression>>,
+        BinaryOperator,
+        Box<Located<Expression>>,
+    ),
+    UnaryExpression(UnaryOperator, Box<Located<Expression>>),
+    // `s[i]`, the value being indexed and the index.
+    Index(Box<Located<Expression>>, Box<Located<Expression>>),
+    // An assignment used as a value, such as the `y = 1` in `x = y = 1`,
+    // which is the value assigned.
+    Assignment(Box<Assignment>),
 }
 
 pub fn parse_expression(input: Span) -> ParseResult<Expression> {
-    alt((parse_binary_operator_expression, parse_factor_expression))(input)
+    context("an expression", parse_operator_expression)(input)
 }
 
-pub fn parse_factor_expression(input: Span) -> ParseResult<Expression> {
+// An expression that operators and suffixes can be applied to without
+// brackets.
+fn parse_primary_expression(input: Span) -> ParseResult<Expression> {
     alt((
-        bracketed(parse_expression),
-        parse_function_call_expression,
+        parse_bracketed_expression,
         parse_literal_expression,
         parse_identifier_expression,
     ))(input)
 }
 
+// A bracketed expression's location includes the brackets.
+fn parse_bracketed_expression(input: Span) -> ParseResult<Expression> {
+    let (input, _) = whitespace(input)?;
+    let (input, position) = position(input)?;
+    let (input, expression) = bracketed(terminated(parse_expression, whitespace))(input)?;
+    Ok((
+        input,
+        Token {
+            position,
+            value: expression.value,
+        },
+    ))
+}
+
 #[test]
 fn test_expressions_with_identifiers() {
     parse_test(parse_expression, "x * x\n", |input| {
@@ -46,11 +82,101 @@ fn test_expressions_with_identifiers() {
             Token {
                 position: input.slice(0..0),
                 value: Expression::BinaryExpression(
-                    Box::new(Expression::Identifier(Identifier::new("x"))),
+                    Box::new(node(Expression::Identifier(Identifier::new("x")))),
                     BinaryOperator::ArithmeticOperator(ArithmeticOperator::Multiply),
-                    Box::new(Expression::Identifier(Identifier::new("x"))),
+                    Box::new(node(Expression::Identifier(Identifier::new("x")))),
+                ),
+            },
+        )
+    })
+}
+
+#[test]
+fn test_not_expression() {
+    parse_test(parse_expression, "!x && !(y)", |input| {
+        (
+            input.slice(10..),
+            Token {
+                position: input.slice(0..0),
+                value: Expression::BinaryExpression(
+                    Box::new(node(Expression::UnaryExpression(
+                        UnaryOperator::Not,
+                        Box::new(node(Expression::Identifier(Identifier::new("x")))),
+                    ))),
+                    BinaryOperator::LogicalOperator(LogicalOperator::And),
+                    Box::new(node(Expression::UnaryExpression(
+                        UnaryOperator::Not,
+                        Box::new(node(Expression::Identifier(Identifier::new("y")))),
+                    ))),
                 ),
             },
         )
     })
 }
+
+#[test]
+fn test_assignment_expression() {
+    let identifier = |name| node(Identifier::new(name));
+    parse_test(parse_expression, "x = y += 1 * 2", |input| {
+        (
+            input.slice(14..),
+            Token {
+                position: input.slice(0..0),
+                value: Expression::Assignment(Box::new(Assignment {
+                    lhs: identifier("x"),
+                    operator: None,
+                    rhs: node(Expression::Assignment(Box::new(Assignment {
+                        lhs: identifier("y"),
+                        operator: Some(ArithmeticOperator::Add),
+                        rhs: node(Expression::BinaryExpression(
+                            Box::new(node(Expression::Literal(Literal::Integer(1)))),
+                            BinaryOperator::ArithmeticOperator(ArithmeticOperator::Multiply),
+                            Box::new(node(Expression::Literal(Literal::Integer(2)))),
+                        )),
+                    }))),
+                })),
+            },
+        )
+    })
+}
+
+#[test]
+fn test_expression_locations() {
+    use super::util::located;
+
+    let offsets =
+        |expression: &Located<Expression>| (expression.location.offset, expression.location.length);
+
+    let input = Span::new_extra(" !(y + 22) || f(x,  1)", None);
+    let (_, expression) = located(parse_expression)(input).unwrap();
+    assert_eq!(offsets(&expression), (1, 21));
+
+    let Expression::BinaryExpression(lhs, _, rhs) = &expression.value else {
+        panic!("expected a binary expression");
+    };
+    assert_eq!(offsets(lhs), (1, 9));
+    assert_eq!(offsets(rhs), (14, 8));
+
+    let Expression::UnaryExpression(_, operand) = &lhs.value else {
+        panic!("expected a unary expression");
+    };
+    assert_eq!(offsets(operand), (2, 8));
+
+    let Expression::FunctionCall(_, args) = &rhs.value else {
+        panic!("expected a function call");
+    };
+    assert_eq!(
+        args.iter().map(offsets).collect::<Vec<_>>(),
+        vec![(16, 1), (20, 1)]
+    );
+
+    let input = Span::new_extra("f(s)[ i + 1 ]", None);
+    let (_, expression) = located(parse_expression)(input).unwrap();
+    assert_eq!(offsets(&expression), (0, 13));
+
+    let Expression::Index(value, index) = &expression.value else {
+        panic!("expected an index expression");
+    };
+    assert_eq!(offsets(value), (0, 4));
+    assert_eq!(offsets(index), (6, 5));
+}
@@ -1,21 +1,9 @@
-use std::fmt::Debug;
-
-use nom::{branch::alt, multi::fold_many0, sequence::tuple};
-
-use crate::parser::{
-    tokens::{
-        add_token, divide_token, equality_token, greater_or_equal_token, greater_than_token,
-        inequality_token, less_or_equal_token, less_than_token, multiply_token, subtract_token,
-    },
-    ParseResult, Span, Token,
-};
-
-use super::{parse_factor_expression, Expression};
-
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
 pub enum BinaryOperator {
     ArithmeticOperator(ArithmeticOperator),
     ComparisonOperator(ComparisonOperator),
+    LogicalOperator(LogicalOperator),
 }
 
 impl std::fmt::Display for BinaryOperator {
@@ -23,6 +11,7 @@ impl std::fmt::Display for BinaryOperator {
         match self {
             BinaryOperator::ArithmeticOperator(op) => write!(f, "{}", op),
             BinaryOperator::ComparisonOperator(op) => write!(f, "{}", op),
+            BinaryOperator::LogicalOperator(op) => write!(f, "{}", op),
         }
     }
 }
@@ -33,6 +22,18 @@ pub enum ArithmeticOperator {
     Subtract,
     Multiply,
     Divide,
+    Remainder,
+    // Raises the left hand side to the power of the right.
+    Power,
+    BitwiseAnd,
+    BitwiseOr,
+    BitwiseXor,
+    ShiftLeft,
+    // Shifts in copies of the sign bit.
+    ShiftRight,
+    // Shifts in zeros, treating the value as a 64-bit two's complement
+    // integer.
+    LogicalShiftRight,
 }
 
 impl std::fmt::Display for ArithmeticOperator {
@@ -42,6 +43,14 @@ impl std::fmt::Display for ArithmeticOperator {
             ArithmeticOperator::Subtract => write!(f, "-"),
             ArithmeticOperator::Multiply => write!(f, "*"),
             ArithmeticOperator::Divide => write!(f, "/"),
+            ArithmeticOperator::Remainder => write!(f, "%"),
+            ArithmeticOperator::Power => write!(f, "**"),
+            ArithmeticOperator::BitwiseAnd => write!(f, "&"),
+            ArithmeticOperator::BitwiseOr => write!(f, "|"),
+            ArithmeticOperator::BitwiseXor => write!(f, "^"),
+            ArithmeticOperator::ShiftLeft => write!(f, "<<"),
+            ArithmeticOperator::ShiftRight => write!(f, ">>"),
+            ArithmeticOperator::LogicalShiftRight => write!(f, ">>>"),
         }
     }
 }
@@ -69,66 +78,18 @@ impl std::fmt::Display for ComparisonOperator {
     }
 }
 
-pub fn parse_binary_operator_expression(input: Span) -> ParseResult<Expression> {
-    let (input, expression) = parse_binary_operators(input, 1)?;
-    println!("Binary expression: {:?}", expression);
-
-    Ok((input, expression))
-}
-
-pub fn parse_binary_operators(input: Span, precedence: usize) -> ParseResult<Expression> {
-    let token_parser = |input| match precedence {
-        1 => alt((
-            equality_token,
-            inequality_token,
-            less_or_equal_token,
-            less_than_token,
-            greater_or_equal_token,
-            greater_than_token,
-        ))(input),
-
-        2 => alt((add_token, subtract_token))(input),
-        3 => alt((multiply_token, divide_token))(input),
-        _ => unreachable!(),
-    };
-
-    let next_parser = |input| match precedence {
-        1..=2 => parse_binary_operators(input, precedence + 1),
-        _ => parse_factor_expression(input),
-    };
-
-    let (input, lhs) = next_parser(input)?;
-    let (input, value) = fold_many0(
-        tuple((token_parser, next_parser)),
-        || lhs.clone(),
-        accumulate_expression,
-    )(input)?;
-
-    Ok((input, value))
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogicalOperator {
+    And,
+    Or,
 }
 
-fn accumulate_expression<'a>(
-    acc: Token<'a, Expression>,
-    (operator, rhs): (Token<'a, String>, Token<'a, Expression>),
-) -> Token<'a, Expression> {
-    let operator = match operator.value.as_str() {
-        "+" => BinaryOperator::ArithmeticOperator(ArithmeticOperator::Add),
-        "-" => BinaryOperator::ArithmeticOperator(ArithmeticOperator::Subtract),
-        "*" => BinaryOperator::ArithmeticOperator(ArithmeticOperator::Multiply),
-        "/" => BinaryOperator::ArithmeticOperator(ArithmeticOperator::Divide),
-        "==" => BinaryOperator::ComparisonOperator(ComparisonOperator::Equal),
-        "!=" => BinaryOperator::ComparisonOperator(ComparisonOperator::NotEqual),
-        "<" => BinaryOperator::ComparisonOperator(ComparisonOperator::LessThan),
-        "<=" => BinaryOperator::ComparisonOperator(ComparisonOperator::LessOrEqual),
-        ">" => BinaryOperator::ComparisonOperator(ComparisonOperator::GreaterThan),
-        ">=" => BinaryOperator::ComparisonOperator(ComparisonOperator::GreaterOrEqual),
-
-        // unreachable because it means a parser fucked up and gave us a token we don't expect
-        _ => unreachable!("unknown operator {}", operator.value),
-    };
-    Token {
-        position: acc.position,
-        value: Expression::BinaryExpression(Box::new(acc.value), operator, Box::new(rhs.value)),
+impl std::fmt::Display for LogicalOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogicalOperator::And => write!(f, "&&"),
+            LogicalOperator::Or => write!(f, "||"),
+        }
     }
 }
 
@@ -137,29 +98,27 @@ mod tests {
     use nom::Slice;
 
     use crate::{
-        parser::{Expression, Identifier, Literal, Token},
-        tests::parse_test,
+        parser::{parse_expression, Expression, Identifier, Literal, Token},
+        tests::{node, parse_test},
     };
 
-    use super::{
-        parse_binary_operator_expression, ArithmeticOperator, BinaryOperator, ComparisonOperator,
-    };
+    use super::{ArithmeticOperator, BinaryOperator, ComparisonOperator, LogicalOperator};
 
     #[test]
     fn test_addition() {
-        parse_test(parse_binary_operator_expression, "2 + 3 + 4", |input| {
+        parse_test(parse_expression, "2 + 3 + 4", |input| {
             (
                 input.slice(9..),
                 Token {
                     position: input.slice(0..0),
                     value: Expression::BinaryExpression(
-                        Box::new(Expression::BinaryExpression(
-                            Box::new(Expression::Literal(Literal::Integer(2))),
+                        Box::new(node(Expression::BinaryExpression(
+                            Box::new(node(Expression::Literal(Literal::Integer(2)))),
                             BinaryOperator::ArithmeticOperator(ArithmeticOperator::Add),
-                            Box::new(Expression::Literal(Literal::Integer(3))),
-                        )),
+                            Box::new(node(Expression::Literal(Literal::Integer(3)))),
+                        ))),
                         BinaryOperator::ArithmeticOperator(ArithmeticOperator::Add),
-                        Box::new(Expression::Literal(Literal::Integer(4))),
+                        Box::new(node(Expression::Literal(Literal::Integer(4)))),
                     ),
                 },
             )
@@ -168,19 +127,19 @@ mod tests {
 
     #[test]
     fn test_multiplication_division() {
-        parse_test(parse_binary_operator_expression, "2 * 3 / 4", |input| {
+        parse_test(parse_expression, "2 * 3 / 4", |input| {
             (
                 input.slice(9..),
                 Token {
                     position: input.slice(0..0),
                     value: Expression::BinaryExpression(
-                        Box::new(Expression::BinaryExpression(
-                            Box::new(Expression::Literal(Literal::Integer(2))),
+                        Box::new(node(Expression::BinaryExpression(
+                            Box::new(node(Expression::Literal(Literal::Integer(2)))),
                             BinaryOperator::ArithmeticOperator(ArithmeticOperator::Multiply),
-                            Box::new(Expression::Literal(Literal::Integer(3))),
-                        )),
+                            Box::new(node(Expression::Literal(Literal::Integer(3)))),
+                        ))),
                         BinaryOperator::ArithmeticOperator(ArithmeticOperator::Divide),
-                        Box::new(Expression::Literal(Literal::Integer(4))),
+                        Box::new(node(Expression::Literal(Literal::Integer(4)))),
                     ),
                 },
             )
@@ -189,19 +148,19 @@ mod tests {
 
     #[test]
     fn test_mixed_expression_1() {
-        parse_test(parse_binary_operator_expression, "2 + 3*4", |input| {
+        parse_test(parse_expression, "2 + 3*4", |input| {
             (
                 input.slice(7..),
                 Token {
                     position: input.slice(0..0),
                     value: Expression::BinaryExpression(
-                        Box::new(Expression::Literal(Literal::Integer(2))),
+                        Box::new(node(Expression::Literal(Literal::Integer(2)))),
                         BinaryOperator::ArithmeticOperator(ArithmeticOperator::Add),
-                        Box::new(Expression::BinaryExpression(
-                            Box::new(Expression::Literal(Literal::Integer(3))),
+                        Box::new(node(Expression::BinaryExpression(
+                            Box::new(node(Expression::Literal(Literal::Integer(3)))),
                             BinaryOperator::ArithmeticOperator(ArithmeticOperator::Multiply),
-                            Box::new(Expression::Literal(Literal::Integer(4))),
-                        )),
+                            Box::new(node(Expression::Literal(Literal::Integer(4)))),
+                        ))),
                     ),
                 },
             )
@@ -210,23 +169,23 @@ mod tests {
 
     #[test]
     fn test_mixed_expression_2() {
-        parse_test(parse_binary_operator_expression, "1*2 + 3*4", |input| {
+        parse_test(parse_expression, "1*2 + 3*4", |input| {
             (
                 input.slice(9..),
                 Token {
                     position: input.slice(0..0),
                     value: Expression::BinaryExpression(
-                        Box::new(Expression::BinaryExpression(
-                            Box::new(Expression::Literal(Literal::Integer(1))),
+                        Box::new(node(Expression::BinaryExpression(
+                            Box::new(node(Expression::Literal(Literal::Integer(1)))),
                             BinaryOperator::ArithmeticOperator(ArithmeticOperator::Multiply),
-                            Box::new(Expression::Literal(Literal::Integer(2))),
-                        )),
+                            Box::new(node(Expression::Literal(Literal::Integer(2)))),
+                        ))),
                         BinaryOperator::ArithmeticOperator(ArithmeticOperator::Add),
-                        Box::new(Expression::BinaryExpression(
-                            Box::new(Expression::Literal(Literal::Integer(3))),
+                        Box::new(node(Expression::BinaryExpression(
+                            Box::new(node(Expression::Literal(Literal::Integer(3)))),
                             BinaryOperator::ArithmeticOperator(ArithmeticOperator::Multiply),
-                            Box::new(Expression::Literal(Literal::Integer(4))),
-                        )),
+                            Box::new(node(Expression::Literal(Literal::Integer(4)))),
+                        ))),
                     ),
                 },
             )
@@ -235,15 +194,94 @@ mod tests {
 
     #[test]
     fn test_one_plus_x() {
-        parse_test(parse_binary_operator_expression, "1 + x", |input| {
+        parse_test(parse_expression, "1 + x", |input| {
             (
                 input.slice(5..),
                 Token {
                     position: input.slice(0..0),
                     value: Expression::BinaryExpression(
-                        Box::new(Expression::Literal(Literal::Integer(1))),
+                        Box::new(node(Expression::Literal(Literal::Integer(1)))),
                         BinaryOperator::ArithmeticOperator(ArithmeticOperator::Add),
-                        Box::new(Expression::Identifier(Identifier("x".to_string()))),
+                        Box::new(node(Expression::Identifier(Identifier("x".to_string())))),
+                    ),
+                },
+            )
+        })
+    }
+
+    #[test]
+    fn test_bitwise_operator_precedence() {
+        let binary = |lhs: Expression, op: ArithmeticOperator, rhs: Expression| {
+            Expression::BinaryExpression(
+                Box::new(node(lhs)),
+                BinaryOperator::ArithmeticOperator(op),
+                Box::new(node(rhs)),
+            )
+        };
+        let int = |value: i64| Expression::Literal(Literal::Integer(value));
+
+        parse_test(
+            parse_expression,
+            "1 | 2 ^ 3 & 4 << 5 + 6 >>> 7 >> 8",
+            |input| {
+                (
+                    input.slice(33..),
+                    Token {
+                        position: input.slice(0..0),
+                        value: binary(
+                            int(1),
+                            ArithmeticOperator::BitwiseOr,
+                            binary(
+                                int(2),
+                                ArithmeticOperator::BitwiseXor,
+                                binary(
+                                    int(3),
+                                    ArithmeticOperator::BitwiseAnd,
+                                    binary(
+                                        binary(
+                                            binary(
+                                                int(4),
+                                                ArithmeticOperator::ShiftLeft,
+                                                binary(int(5), ArithmeticOperator::Add, int(6)),
+                                            ),
+                                            ArithmeticOperator::LogicalShiftRight,
+                                            int(7),
+                                        ),
+                                        ArithmeticOperator::ShiftRight,
+                                        int(8),
+                                    ),
+                                ),
+                            ),
+                        ),
+                    },
+                )
+            },
+        )
+    }
+
+    #[test]
+    fn test_logical_operators() {
+        parse_test(parse_expression, "x < 1 || x > 2 && y", |input| {
+            (
+                input.slice(19..),
+                Token {
+                    position: input.slice(0..0),
+                    value: Expression::BinaryExpression(
+                        Box::new(node(Expression::BinaryExpression(
+                            Box::new(node(Expression::Identifier(Identifier::new("x")))),
+                            BinaryOperator::ComparisonOperator(ComparisonOperator::LessThan),
+                            Box::new(node(Expression::Literal(Literal::Integer(1)))),
+                        ))),
+                        BinaryOperator::LogicalOperator(LogicalOperator::Or),
+                        Box::new(node(Expression::BinaryExpression(
+                            Box::new(node(Expression::BinaryExpression(
+                                Box::new(node(Expression::Identifier(Identifier::new("x")))),
+                                BinaryOperator::ComparisonOperator(ComparisonOperator::GreaterThan),
+                                Box::new(node(Expression::Literal(Literal::Integer(2)))),
+                            ))),
+                            BinaryOperator::LogicalOperator(LogicalOperator::And),
+                            Box::new(node(Expression::Identifier(Identifier::new("y")))),
+                        ))),
                     ),
                 },
             )
@@ -253,7 +291,7 @@ mod tests {
     #[test]
     pub fn test_rainbow_operators() {
         parse_test(
-            parse_binary_operator_expression,
+            parse_expression,
             "1 + 2 - 3 * 4 / 5 == 7 != 8 < 9 <= 10 > 11 >= 12",
             |input| {
                 (
@@ -261,71 +299,81 @@ mod tests {
                     Token {
                         position: input.slice(0..0),
                         value: Expression::BinaryExpression(
-                            Box::new(Expression::BinaryExpression(
-                                Box::new(Expression::BinaryExpression(
-                                    Box::new(Expression::BinaryExpression(
-                                        Box::new(Expression::BinaryExpression(
-                                            Box::new(Expression::BinaryExpression(
-                                                Box::new(Expression::BinaryExpression(
-                                                    Box::new(Expression::BinaryExpression(
-                                                        Box::new(Expression::Literal(
+                            Box::new(node(Expression::BinaryExpression(
+                                Box::new(node(Expression::BinaryExpression(
+                                    Box::new(node(Expression::BinaryExpression(
+                                        Box::new(node(Expression::BinaryExpression(
+                                            Box::new(node(Expression::BinaryExpression(
+                                                Box::new(node(Expression::BinaryExpression(
+                                                    Box::new(node(Expression::BinaryExpression(
+                                                        Box::new(node(Expression::Literal(
                                                             Literal::Integer(1),
-                                                        )),
+                                                        ))),
                                                         BinaryOperator::ArithmeticOperator(
                                                             ArithmeticOperator::Add,
                                                         ),
-                                                        Box::new(Expression::Literal(
+                                                        Box::new(node(Expression::Literal(
                                                             Literal::Integer(2),
-                                                        )),
-                                                    )),
+                                                        ))),
+                                                    ))),
                                                     BinaryOperator::ArithmeticOperator(
                                                         ArithmeticOperator::Subtract,
                                                     ),
-                                                    Box::new(Expression::BinaryExpression(
-                                                        Box::new(Expression::BinaryExpression(
-                                                            Box::new(Expression::Literal(
-                                                                Literal::Integer(3),
-                                                            )),
-                                                            BinaryOperator::ArithmeticOperator(
-                                                                ArithmeticOperator::Multiply,
+                                                    Box::new(node(Expression::BinaryExpression(
+                                                        Box::new(node(
+                                                            Expression::BinaryExpression(
+                                                                Box::new(node(
+                                                                    Expression::Literal(
+                                                                        Literal::Integer(3),
+                                                                    ),
+                                                                )),
+                                                                BinaryOperator::ArithmeticOperator(
+                                                                    ArithmeticOperator::Multiply,
+                                                                ),
+                                                                Box::new(node(
+                                                                    Expression::Literal(
+                                                                        Literal::Integer(4),
+                                                                    ),
+                                                                )),
                                                             ),
-                                                            Box::new(Expression::Literal(
-                                                                Literal::Integer(4),
-                                                            )),
                                                         )),
                                                         BinaryOperator::ArithmeticOperator(
                                                             ArithmeticOperator::Divide,
                                                         ),
-                                                        Box::new(Expression::Literal(
+                                                        Box::new(node(Expression::Literal(
                                                             Literal::Integer(5),
-                                                        )),
-                                                    )),
-                                                )),
+                                                        ))),
+                                                    ))),
+                                                ))),
                                                 BinaryOperator::ComparisonOperator(
                                                     ComparisonOperator::Equal,
                                                 ),
-                                                Box::new(Expression::Literal(Literal::Integer(7))),
-                                            )),
+                                                Box::new(node(Expression::Literal(
+                                                    Literal::Integer(7),
+                                                ))),
+                                            ))),
                                             BinaryOperator::ComparisonOperator(
                                                 ComparisonOperator::NotEqual,
                                             ),
-                                            Box::new(Expression::Literal(Literal::Integer(8))),
-                                        )),
+                                            Box::new(node(Expression::Literal(Literal::Integer(
+                                                8,
+                                            )))),
+                                        ))),
                                         BinaryOperator::ComparisonOperator(
                                             ComparisonOperator::LessThan,
                                         ),
-                                        Box::new(Expression::Literal(Literal::Integer(9))),
-                                    )),
+                                        Box::new(node(Expression::Literal(Literal::Integer(9)))),
+                                    ))),
                                     BinaryOperator::ComparisonOperator(
                                         ComparisonOperator::LessOrEqual,
                                     ),
-                                    Box::new(Expression::Literal(Literal::Integer(10))),
-                                )),
+                                    Box::new(node(Expression::Literal(Literal::Integer(10)))),
+                                ))),
                                 BinaryOperator::ComparisonOperator(ComparisonOperator::GreaterThan),
-                                Box::new(Expression::Literal(Literal::Integer(11))),
-                            )),
+                                Box::new(node(Expression::Literal(Literal::Integer(11)))),
+                            ))),
                             BinaryOperator::ComparisonOperator(ComparisonOperator::GreaterOrEqual),
-                            Box::new(Expression::Literal(Literal::Integer(12))),
+                            Box::new(node(Expression::Literal(Literal::Integer(12)))),
                         ),
                     },
                 )
@@ -0,0 +1,514 @@
+use nom::{
+    character::complete::char,
+    combinator::{cut, map},
+    error::context,
+    multi::separated_list0,
+    sequence::{preceded, terminated},
+    IResult,
+};
+use nom_locate::position;
+
+use crate::parser::{
+    comment::{line_whitespace, whitespace},
+    statement::{assignment_operator, assignment_operator_token, Assignment},
+    tokens::{
+        add_token, and_token, bitwise_and_token, bitwise_not_token, bitwise_or_token,
+        bitwise_xor_token, comma_token, divide_token, equality_token, greater_or_equal_token,
+        greater_than_token, inequality_token, less_or_equal_token, less_than_token,
+        logical_shift_right_token, multiply_token, not_token, or_token, power_token,
+        remainder_token, shift_left_token, shift_right_token, subtract_token,
+    },
+    util::located,
+    Located, Location, ParseError, ParseErrorKind, ParseResult, Span, Token,
+};
+
+use super::{
+    parse_expression, parse_primary_expression, ArithmeticOperator, BinaryOperator,
+    ComparisonOperator, Expression, LogicalOperator, UnaryOperator,
+};
+
+type TokenParser = fn(Span) -> ParseResult<String>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Associativity {
+    // `a - b - c` is `(a - b) - c`.
+    Left,
+    // `a ** b ** c` is `a ** (b ** c)`.
+    Right,
+}
+
+struct InfixOperator {
+    token: TokenParser,
+    operator: Infix,
+    precedence: u8,
+    associativity: Associativity,
+}
+
+#[derive(Clone, Copy)]
+enum Infix {
+    Binary(BinaryOperator),
+    // `=` or a compound assignment such as `+=`, depending on the token. The
+    // left hand side must be a variable.
+    Assignment,
+}
+
+impl InfixOperator {
+    // Only a variable can be assigned to.
+    fn applies_to(&self, lhs: &Expression) -> bool {
+        match self.operator {
+            Infix::Binary(_) => true,
+            Infix::Assignment => matches!(lhs, Expression::Identifier(_)),
+        }
+    }
+
+    fn apply(
+        &self,
+        token: &str,
+        lhs: Located<Expression>,
+        rhs: Located<Expression>,
+    ) -> Located<Expression> {
+        let location = lhs.location.to(&rhs.location);
+        let expression = match (self.operator, lhs.value) {
+            (Infix::Binary(operator), value) => Expression::BinaryExpression(
+                Box::new(Located::new(lhs.location, value)),
+                operator,
+                Box::new(rhs),
+            ),
+            (Infix::Assignment, Expression::Identifier(identifier)) => {
+                Expression::Assignment(Box::new(Assignment {
+                    lhs: Located::new(lhs.location, identifier),
+                    operator: assignment_operator(token),
+                    rhs,
+                }))
+            }
+            (Infix::Assignment, _) => unreachable!("only variables can be assigned to"),
+        };
+        Located::new(location, expression)
+    }
+}
+
+struct PrefixOperator {
+    token: TokenParser,
+    operator: UnaryOperator,
+    // The operand is everything after the operator that binds at least this
+    // tightly, so a prefix operator applies before any infix operator with a
+    // lower precedence.
+    precedence: u8,
+}
+
+struct PostfixOperator {
+    // Parses what follows the operand, such as a call's arguments.
+    parse: fn(Span) -> IResult<Span, Postfix, ParseError>,
+    precedence: u8,
+}
+
+// What a postfix operator parsed, to be applied to its operand.
+enum Postfix {
+    Call(Vec<Located<Expression>>),
+    Index(Located<Expression>),
+}
+
+impl Postfix {
+    // Only a function can be called, and functions are only referred to by
+    // name.
+    fn applies_to(&self, operand: &Expression) -> bool {
+        match self {
+            Postfix::Call(_) => matches!(operand, Expression::Identifier(_)),
+            Postfix::Index(_) => true,
+        }
+    }
+
+    fn apply(self, operand: Located<Expression>, end: Span) -> Located<Expression> {
+        let location = operand.location.up_to(end);
+        let expression = match (self, operand.value) {
+            (Postfix::Call(args), Expression::Identifier(identifier)) => {
+                Expression::FunctionCall(identifier, args)
+            }
+            (Postfix::Call(_), _) => unreachable!("only identifiers can be called"),
+            (Postfix::Index(index), value) => Expression::Index(
+                Box::new(Located::new(operand.location, value)),
+                Box::new(index),
+            ),
+        };
+        Located::new(location, expression)
+    }
+}
+
+const fn infix(
+    token: TokenParser,
+    operator: BinaryOperator,
+    precedence: u8,
+    associativity: Associativity,
+) -> InfixOperator {
+    InfixOperator {
+        token,
+        operator: Infix::Binary(operator),
+        precedence,
+        associativity,
+    }
+}
+
+const fn assignment(token: TokenParser, precedence: u8) -> InfixOperator {
+    InfixOperator {
+        token,
+        operator: Infix::Assignment,
+        precedence,
+        associativity: Associativity::Right,
+    }
+}
+
+const fn arithmetic(operator: ArithmeticOperator) -> BinaryOperator {
+    BinaryOperator::ArithmeticOperator(operator)
+}
+
+const fn comparison(operator: ComparisonOperator) -> BinaryOperator {
+    BinaryOperator::ComparisonOperator(operator)
+}
+
+const fn logical(operator: LogicalOperator) -> BinaryOperator {
+    BinaryOperator::LogicalOperator(operator)
+}
+
+// The operators an expression can contain.
+struct Operators {
+    infix: &'static [InfixOperator],
+    prefix: &'static [PrefixOperator],
+    postfix: &'static [PostfixOperator],
+}
+
+const OPERATORS: Operators = Operators {
+    infix: INFIX_OPERATORS,
+    prefix: PREFIX_OPERATORS,
+    postfix: POSTFIX_OPERATORS,
+};
+
+// Higher precedences bind more tightly. Operators are tried in order, so an
+// operator must come before any other that its token starts with.
+const INFIX_OPERATORS: &[InfixOperator] = {
+    use self::Associativity::{Left, Right};
+    use ArithmeticOperator::*;
+    use ComparisonOperator::*;
+    use LogicalOperator::*;
+
+    &[
+        infix(or_token, logical(Or), 1, Left),
+        infix(and_token, logical(And), 2, Left),
+        infix(equality_token, comparison(Equal), 3, Left),
+        infix(inequality_token, comparison(NotEqual), 3, Left),
+        infix(less_or_equal_token, comparison(LessOrEqual), 3, Left),
+        infix(less_than_token, comparison(LessThan), 3, Left),
+        infix(greater_or_equal_token, comparison(GreaterOrEqual), 3, Left),
+        infix(greater_than_token, comparison(GreaterThan), 3, Left),
+        infix(bitwise_or_token, arithmetic(BitwiseOr), 4, Left),
+        infix(bitwise_xor_token, arithmetic(BitwiseXor), 5, Left),
+        infix(bitwise_and_token, arithmetic(BitwiseAnd), 6, Left),
+        infix(shift_left_token, arithmetic(ShiftLeft), 7, Left),
+        infix(
+            logical_shift_right_token,
+            arithmetic(LogicalShiftRight),
+            7,
+            Left,
+        ),
+        infix(shift_right_token, arithmetic(ShiftRight), 7, Left),
+        infix(add_token, arithmetic(Add), 8, Left),
+        infix(subtract_token, arithmetic(Subtract), 8, Left),
+        infix(multiply_token, arithmetic(Multiply), 9, Left),
+        infix(divide_token, arithmetic(Divide), 9, Left),
+        infix(remainder_token, arithmetic(Remainder), 9, Left),
+        infix(power_token, arithmetic(Power), 10, Right),
+        // Last, as `=` is the start of `==`.
+        assignment(assignment_operator_token, 0),
+    ]
+};
+
+const PREFIX_OPERATORS: &[PrefixOperator] = &[
+    PrefixOperator {
+        token: not_token,
+        operator: UnaryOperator::Not,
+        precedence: 10,
+    },
+    PrefixOperator {
+        token: bitwise_not_token,
+        operator: UnaryOperator::BitwiseNot,
+        precedence: 10,
+    },
+    PrefixOperator {
+        token: subtract_token,
+        operator: UnaryOperator::Negate,
+        precedence: 10,
+    },
+];
+
+// Calls and indexing bind more tightly than any other operator, so `-f(x)[0]`
+// is `-((f(x))[0])`.
+const POSTFIX_OPERATORS: &[PostfixOperator] = &[
+    PostfixOperator {
+        parse: call_arguments,
+        precedence: 11,
+    },
+    PostfixOperator {
+        parse: index,
+        precedence: 11,
+    },
+];
+
+// Parses an expression made of operands and operators, by precedence
+// climbing over the tables above.
+pub fn parse_operator_expression(input: Span) -> ParseResult<Expression> {
+    let (input, _) = whitespace(input)?;
+    let (input, position) = position(input)?;
+    let (input, expression) = parse_with_precedence(&OPERATORS, input, 0)?;
+
+    Ok((
+        input,
+        Token {
+            position,
+            value: expression.value,
+        },
+    ))
+}
+
+// An operand, then as many postfix operators, and infix operators and their
+// right hand sides, as bind at least as tightly as `min_precedence`.
+fn parse_with_precedence<'a>(
+    operators: &Operators,
+    input: Span<'a>,
+    min_precedence: u8,
+) -> IResult<Span<'a>, Located<Expression>, ParseError<'a>> {
+    let (mut input, mut lhs) = parse_operand(operators, input)?;
+
+    loop {
+        match parse_postfix_operator(operators, input, &lhs.value, min_precedence) {
+            Ok((rest, postfix)) => {
+                lhs = postfix.apply(lhs, rest);
+                input = rest;
+                continue;
+            }
+            Err(nom::Err::Error(_)) => {}
+            Err(error) => return Err(error),
+        }
+
+        let (rest, (operator, token)) = match parse_infix_operator(operators, input) {
+            Ok((rest, (operator, token)))
+                if operator.precedence >= min_precedence && operator.applies_to(&lhs.value) =>
+            {
+                (rest, (operator, token))
+            }
+            Ok(_) | Err(nom::Err::Error(_)) => break,
+            Err(error) => return Err(error),
+        };
+
+        let rhs_precedence = match operator.associativity {
+            Associativity::Left => operator.precedence + 1,
+            Associativity::Right => operator.precedence,
+        };
+
+        let (rest, rhs) = parse_required_operand(operators, rest, rhs_precedence)?;
+
+        lhs = operator.apply(&token, lhs, rhs);
+        input = rest;
+    }
+
+    Ok((input, lhs))
+}
+
+// An operator must be on the same line as its left hand side, since a line
+// break ends the statement. Ending a line with an operator continues the
+// expression onto the next one instead.
+fn parse_infix_operator<'a>(
+    operators: &Operators,
+    input: Span<'a>,
+) -> IResult<Span<'a>, (&'static InfixOperator, String), ParseError<'a>> {
+    let (input, _) = line_whitespace(input)?;
+
+    if !input.fragment().starts_with(['\n', '\r']) {
+        for operator in operators.infix {
+            match (operator.token)(input) {
+                Ok((rest, token)) => return Ok((rest, (operator, token.value))),
+                Err(nom::Err::Error(_)) => continue,
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    Err(nom::Err::Error(ParseError::new(
+        input,
+        ParseErrorKind::Expected("an operator"),
+    )))
+}
+
+// The operand after an operator that's already been taken. It's an error for
+// it to be missing, rather than something to backtrack from.
+fn parse_required_operand<'a>(
+    operators: &Operators,
+    input: Span<'a>,
+    min_precedence: u8,
+) -> IResult<Span<'a>, Located<Expression>, ParseError<'a>> {
+    cut(context("an expression", |input| {
+        parse_with_precedence(operators, input, min_precedence)
+    }))(input)
+}
+
+// A postfix operator that applies to `operand` and binds at least as tightly
+// as `min_precedence`. It must follow the operand directly, so that an
+// expression on the next line isn't mistaken for arguments.
+fn parse_postfix_operator<'a>(
+    operators: &Operators,
+    input: Span<'a>,
+    operand: &Expression,
+    min_precedence: u8,
+) -> IResult<Span<'a>, Postfix, ParseError<'a>> {
+    for operator in operators.postfix {
+        if operator.precedence < min_precedence {
+            continue;
+        }
+
+        match (operator.parse)(input) {
+            Ok((rest, postfix)) if postfix.applies_to(operand) => return Ok((rest, postfix)),
+            Ok(_) | Err(nom::Err::Error(_)) => continue,
+            Err(error) => return Err(error),
+        }
+    }
+
+    Err(nom::Err::Error(ParseError::new(
+        input,
+        ParseErrorKind::Expected("an operator"),
+    )))
+}
+
+// A primary expression, or a prefix operator and its operand.
+fn parse_operand<'a>(
+    operators: &Operators,
+    input: Span<'a>,
+) -> IResult<Span<'a>, Located<Expression>, ParseError<'a>> {
+    for operator in operators.prefix {
+        let (rest, token) = match (operator.token)(input) {
+            Ok(result) => result,
+            Err(nom::Err::Error(_)) => continue,
+            Err(error) => return Err(error),
+        };
+
+        let (rest, operand) = parse_required_operand(operators, rest, operator.precedence)?;
+        let location = Location::between(token.position, rest);
+        return Ok((
+            rest,
+            Located::new(
+                location,
+                Expression::UnaryExpression(operator.operator, Box::new(operand)),
+            ),
+        ));
+    }
+
+    located(parse_primary_expression)(input)
+}
+
+// The arguments can be spread over several lines, with the closing bracket
+// on a line of its own.
+fn call_arguments(input: Span) -> IResult<Span, Postfix, ParseError> {
+    map(
+        preceded(
+            char('('),
+            terminated(
+                separated_list0(comma_token, located(parse_expression)),
+                preceded(whitespace, char(')')),
+            ),
+        ),
+        Postfix::Call,
+    )(input)
+}
+
+fn index(input: Span) -> IResult<Span, Postfix, ParseError> {
+    map(
+        preceded(
+            char('['),
+            terminated(located(parse_expression), preceded(whitespace, char(']'))),
+        ),
+        Postfix::Index,
+    )(input)
+}
+
+#[test]
+fn test_associativity() {
+    use crate::tests::node;
+
+    // `-` is made right-associative, `!` looser than `+` and indexing looser
+    // than `!`, to check that the tables decide how expressions are grouped.
+    const OPERATORS: Operators = Operators {
+        infix: &[
+            infix(
+                add_token,
+                arithmetic(ArithmeticOperator::Add),
+                2,
+                Associativity::Left,
+            ),
+            infix(
+                subtract_token,
+                arithmetic(ArithmeticOperator::Subtract),
+                1,
+                Associativity::Right,
+            ),
+        ],
+        prefix: &[PrefixOperator {
+            token: not_token,
+            operator: UnaryOperator::Not,
+            precedence: 2,
+        }],
+        postfix: &[PostfixOperator {
+            parse: index,
+            precedence: 1,
+        }],
+    };
+
+    let parse = |source| {
+        let (rest, expression) =
+            parse_with_precedence(&OPERATORS, Span::new_extra(source, None), 0).unwrap();
+        assert!(rest.fragment().is_empty());
+        expression
+    };
+    let binary = |lhs, operator, rhs| {
+        node(Expression::BinaryExpression(
+            Box::new(lhs),
+            arithmetic(operator),
+            Box::new(rhs),
+        ))
+    };
+    let int = |value| node(Expression::Literal(crate::parser::Literal::Integer(value)));
+
+    assert_eq!(
+        parse("1 - 2 - 3 + 4"),
+        binary(
+            int(1),
+            ArithmeticOperator::Subtract,
+            binary(
+                int(2),
+                ArithmeticOperator::Subtract,
+                binary(int(3), ArithmeticOperator::Add, int(4))
+            )
+        )
+    );
+    assert_eq!(
+        parse("!1 + 2 - 3"),
+        binary(
+            node(Expression::UnaryExpression(
+                UnaryOperator::Not,
+                Box::new(binary(int(1), ArithmeticOperator::Add, int(2)))
+            )),
+            ArithmeticOperator::Subtract,
+            int(3)
+        )
+    );
+    assert_eq!(
+        parse("!1[2] - 3[4]"),
+        binary(
+            node(Expression::Index(
+                Box::new(node(Expression::UnaryExpression(
+                    UnaryOperator::Not,
+                    Box::new(int(1))
+                ))),
+                Box::new(int(2))
+            )),
+            ArithmeticOperator::Subtract,
+            node(Expression::Index(Box::new(int(3)), Box::new(int(4))))
+        )
+    );
+}
@@ -1,7 +1,6 @@
 use nom::{
-    character::complete::multispace0,
     combinator::fail,
-    error::{ErrorKind, ParseError},
+    error::{context, ErrorKind, ParseError},
     AsChar, IResult, InputTakeAtPosition,
 };
 
@@ -11,7 +10,7 @@ use nom::Slice;
 
 use nom_locate::position;
 
-use super::{ParseResult, Span, Token};
+use super::{comment::whitespace, tokens::is_keyword, ParseResult, Span, Token};
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct Identifier(pub String);
@@ -43,14 +42,17 @@ where
 }
 
 pub fn parse_identifier(input: Span) -> ParseResult<Identifier> {
-    let (input, _) = multispace0(input)?;
+    context("an identifier", identifier)(input)
+}
+
+fn identifier(input: Span) -> ParseResult<Identifier> {
+    let (input, _) = whitespace(input)?;
     let (before_token_input, position) = position(input)?;
     let (input, value) = identifier_name(before_token_input)?;
 
-    let (input, _) = match *value.fragment() {
-        "def" | "let" | "if" | "else" => fail(before_token_input)?,
-        _ => (input, ()),
-    };
+    if is_keyword(value.fragment()) {
+        return fail(before_token_input);
+    }
 
     Ok((
         input,
@@ -63,7 +65,7 @@ pub fn parse_identifier(input: Span) -> ParseResult<Identifier> {
 
 #[test]
 fn test_identifier() {
-    let input = Span::new("foo");
+    let input = Span::new_extra("foo", None);
     assert_eq!(
         parse_identifier(input),
         Ok((
@@ -75,7 +77,7 @@ fn test_identifier() {
         ))
     );
 
-    let input = Span::new(" foo");
+    let input = Span::new_extra(" foo", None);
     assert_eq!(
         parse_identifier(input),
         Ok((
@@ -87,7 +89,7 @@ fn test_identifier() {
         ))
     );
 
-    let input = Span::new("foo ");
+    let input = Span::new_extra("foo ", None);
     assert_eq!(
         parse_identifier(input),
         Ok((
@@ -99,7 +101,7 @@ fn test_identifier() {
         ))
     );
 
-    let input = Span::new("foo_bar");
+    let input = Span::new_extra("foo_bar", None);
     assert_eq!(
         parse_identifier(input),
         Ok((
@@ -110,4 +112,20 @@ fn test_identifier() {
             }
         ))
     );
+
+    for keyword in ["return", "while", "true"] {
+        assert!(parse_identifier(Span::new_extra(keyword, None)).is_err());
+    }
+
+    let input = Span::new_extra("returned", None);
+    assert_eq!(
+        parse_identifier(input),
+        Ok((
+            input.slice(8..),
+            Token {
+                position: input.slice(0..0),
+                value: Identifier("returned".to_owned())
+            }
+        ))
+    );
 }
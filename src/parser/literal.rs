@@ -1,9 +1,12 @@
 use nom::{
     branch::alt,
-    bytes::complete::escaped,
-    character::complete::{char, digit1, multispace0, one_of, space0},
-    combinator::{map, map_res},
-    sequence::delimited,
+    bytes::complete::{tag, tag_no_case, take_while, take_while_m_n},
+    character::complete::{char, multispace0, satisfy},
+    combinator::{fail, map, peek, value},
+    error::ErrorKind,
+    multi::many0,
+    sequence::{delimited, preceded, terminated},
+    IResult,
 };
 
 // we use this but Rust Analyzer doesn't notice it...?
@@ -12,53 +15,254 @@ use nom::Slice;
 
 use nom_locate::position;
 
-use super::{ParseResult, Span, Token};
+use super::{
+    comment::whitespace, expression::parse_expression, identifier::identifier_name, util::located,
+    Expression, Located, ParseError, ParseErrorKind, ParseResult, Span, Token,
+};
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Literal {
     String(String),
+    // A string containing `${...}` expressions, which are converted to
+    // strings and concatenated with the text around them.
+    InterpolatedString(Vec<StringPart>),
     Integer(i64),
+    Boolean(bool),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StringPart {
+    Text(String),
+    Expression(Located<Expression>),
 }
 
 fn literal_string(input: Span) -> ParseResult<Literal> {
-    let (input, _) = space0(input)?;
+    let (input, _) = whitespace(input)?;
     let (input, position) = position(input)?;
-    let (input, chars) = delimited(
-        char('"'),
-        escaped(
-            one_of("ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz01234567890 !@#$%^&*()"),
-            '\\',
-            one_of("\"n\\"),
-        ),
-        char('"'),
-    )(input)?;
-
-    let value = Literal::String(String::from(*chars));
+
+    match raw_string(input) {
+        Ok((input, text)) => {
+            let value = Literal::String(text.fragment().to_string());
+            return Ok((input, Token { position, value }));
+        }
+        Err(nom::Err::Error(_)) => {}
+        Err(error) => return Err(error),
+    }
+
+    let start = input;
+    let (input, _) = char('"')(input)?;
+    let (input, parts) = many0(string_part)(input)?;
+    let (input, _) = char::<_, ParseError>('"')(input).map_err(|_| {
+        nom::Err::Failure(ParseError::new(start, ParseErrorKind::UnterminatedString))
+    })?;
+
+    // Adjacent pieces of text are merged, so a string without any
+    // interpolation ends up as a single piece of text.
+    let mut merged_parts = Vec::<StringPart>::new();
+    for part in parts {
+        match (merged_parts.last_mut(), part) {
+            (Some(StringPart::Text(text)), StringPart::Text(next)) => text.push_str(&next),
+            (_, part) => merged_parts.push(part),
+        }
+    }
+
+    let value = match merged_parts.as_slice() {
+        [] => Literal::String(String::new()),
+        [StringPart::Text(text)] => Literal::String(text.clone()),
+        _ => Literal::InterpolatedString(merged_parts),
+    };
+
     Ok((input, Token { position, value }))
 }
 
+// Raw strings are written `r"..."`, and their contents are taken exactly as
+// they are, without escapes or interpolation. Any number of `#`s can go
+// between the `r` and the opening quote, and the string then ends at a quote
+// followed by the same number of `#`s, so it can contain quotes itself.
+pub(super) fn raw_string(input: Span) -> IResult<Span, Span, ParseError> {
+    let start = input;
+    let (input, _) = char('r')(input)?;
+    let (input, hashes) = take_while(|c| c == '#')(input)?;
+    let (input, _) = char('"')(input)?;
+
+    let terminator = format!("\"{}", hashes.fragment());
+    let Some(length) = input.fragment().find(&terminator) else {
+        return Err(nom::Err::Failure(ParseError::new(
+            start,
+            ParseErrorKind::UnterminatedString,
+        )));
+    };
+
+    Ok((
+        input.slice(length + terminator.len()..),
+        input.slice(..length),
+    ))
+}
+
+fn string_part(input: Span) -> IResult<Span, StringPart, ParseError> {
+    alt((interpolation, escape_sequence, string_text))(input)
+}
+
+// `${` followed by an expression and `}`. Anything else after the `${` is an
+// error, reported at the `${`, unless it's a more specific one such as an
+// invalid escape in a string inside it.
+fn interpolation(input: Span) -> IResult<Span, StringPart, ParseError> {
+    let (rest, _) = tag("${")(input)?;
+    map(
+        terminated(located(parse_expression), preceded(whitespace, char('}'))),
+        StringPart::Expression,
+    )(rest)
+    .map_err(|error| match error {
+        nom::Err::Error(error) | nom::Err::Failure(error)
+            if matches!(
+                error.kind,
+                ParseErrorKind::ExpectedToken(_)
+                    | ParseErrorKind::ExpectedChar(_)
+                    | ParseErrorKind::Expected(_)
+                    | ParseErrorKind::Nom(_)
+            ) =>
+        {
+            nom::Err::Failure(ParseError::new(input, ParseErrorKind::InvalidInterpolation))
+        }
+        error => error,
+    })
+}
+
+// Any run of characters up to the end of the string, an escape sequence or an
+// interpolation. Strings can span multiple lines.
+fn string_text(input: Span) -> IResult<Span, StringPart, ParseError> {
+    let fragment = input.fragment();
+    let length = fragment
+        .char_indices()
+        .find(|(index, c)| *c == '"' || *c == '\\' || fragment[*index..].starts_with("${"))
+        .map_or(fragment.len(), |(index, _)| index);
+
+    if length == 0 {
+        return Err(nom::Err::Error(ParseError::new(
+            input,
+            ParseErrorKind::Nom(ErrorKind::TakeTill1),
+        )));
+    }
+
+    Ok((
+        input.slice(length..),
+        StringPart::Text(fragment[..length].to_owned()),
+    ))
+}
+
+// A backslash followed by one of `n`, `r`, `t`, `0`, `"`, `\\` or `$`, or by
+// `u{...}` with the hex code of any Unicode character. A backslash at the end
+// of a line skips the line break and any indentation after it. Anything else
+// is an error, reported at the backslash.
+fn escape_sequence(input: Span) -> IResult<Span, StringPart, ParseError> {
+    let (rest, _) = char('\\')(input)?;
+    let invalid_escape =
+        || nom::Err::Failure(ParseError::new(input, ParseErrorKind::InvalidEscape));
+
+    let Some(escaped) = rest.fragment().chars().next() else {
+        return Err(invalid_escape());
+    };
+    let rest = rest.slice(escaped.len_utf8()..);
+
+    let text = match escaped {
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        '0' => '\0',
+        '"' => '"',
+        '\\' => '\\',
+        '$' => '$',
+        '\n' => {
+            let (rest, _) = multispace0(rest)?;
+            return Ok((rest, StringPart::Text(String::new())));
+        }
+        'u' => {
+            let (rest, code) = delimited(
+                char('{'),
+                take_while_m_n(1, 6, |c: char| c.is_ascii_hexdigit()),
+                char('}'),
+            )(rest)
+            .map_err(|_: nom::Err<ParseError>| invalid_escape())?;
+
+            let code = u32::from_str_radix(code.fragment(), 16).expect("invalid hex digits");
+            let Some(c) = char::from_u32(code) else {
+                return Err(invalid_escape());
+            };
+
+            return Ok((rest, StringPart::Text(c.to_string())));
+        }
+        _ => return Err(invalid_escape()),
+    };
+
+    Ok((rest, StringPart::Text(text.to_string())))
+}
+
+// Integers can be written in decimal, or in hex, binary or octal with a `0x`,
+// `0b` or `0o` prefix, and digits can be separated by underscores. Integers
+// that don't fit in an i64 are a failure, reported at the start of the
+// literal.
 fn parse_int(input: Span) -> ParseResult<i64> {
-    let (input, _) = multispace0(input)?;
-    let (input, position) = position(input)?;
-    let (input, value) = map_res(digit1, |s: Span| s.parse::<i64>())(input)?;
+    let (input, _) = whitespace(input)?;
+    let (literal_start, position) = position(input)?;
+
+    let (input, radix) = alt((
+        value(16, tag_no_case("0x")),
+        value(2, tag_no_case("0b")),
+        value(8, tag_no_case("0o")),
+        value(10, peek(satisfy(|c| c.is_ascii_digit()))),
+    ))(literal_start)?;
+
+    let (input, digits) = take_while(|c: char| c.is_ascii_alphanumeric() || c == '_')(input)?;
+    let digits = digits.fragment().replace('_', "");
+
+    // Anything that isn't a digit in this base is an error, rather than the
+    // start of whatever comes next.
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return Err(nom::Err::Failure(ParseError::new(
+            literal_start,
+            ParseErrorKind::InvalidDigit,
+        )));
+    }
+
+    let Ok(value) = i64::from_str_radix(&digits, radix) else {
+        return Err(nom::Err::Failure(ParseError::new(
+            literal_start,
+            ParseErrorKind::IntegerTooLarge,
+        )));
+    };
+
     Ok((input, Token { position, value }))
 }
 
 fn literal_int(input: Span) -> ParseResult<Literal> {
-    let (input, _) = multispace0(input)?;
+    let (input, _) = whitespace(input)?;
     let (input, position) = position(input)?;
     let (input, value) = map(parse_int, |i| Literal::Integer(i.value))(input)?;
 
     Ok((input, Token { position, value }))
 }
 
+fn literal_boolean(input: Span) -> ParseResult<Literal> {
+    let (input, _) = whitespace(input)?;
+    let (before_token_input, position) = position(input)?;
+    let (input, word) = identifier_name(before_token_input)?;
+
+    let value = match *word.fragment() {
+        "true" => Literal::Boolean(true),
+        "false" => Literal::Boolean(false),
+        _ => return fail(before_token_input),
+    };
+
+    Ok((input, Token { position, value }))
+}
+
 pub fn parse_literal(input: Span) -> ParseResult<Literal> {
-    alt((literal_string, literal_int))(input)
+    alt((literal_string, literal_int, literal_boolean))(input)
 }
 
 #[test]
 fn test_literal() {
-    let input = Span::new("  \"foo\"  ");
+    let input = Span::new_extra("  \"foo\"  ", None);
     assert_eq!(
         parse_literal(input),
         Ok((
@@ -70,7 +274,7 @@ fn test_literal() {
         ))
     );
 
-    let input = Span::new("  1234");
+    let input = Span::new_extra("  1234", None);
     assert_eq!(
         parse_literal(input),
         Ok((
@@ -82,7 +286,7 @@ fn test_literal() {
         ))
     );
 
-    let input = Span::new("\"foo\"blah");
+    let input = Span::new_extra("\"foo\"blah", None);
     assert_eq!(
         parse_literal(input),
         Ok((
@@ -93,4 +297,164 @@ fn test_literal() {
             }
         ))
     );
+
+    let input = Span::new_extra(" true", None);
+    assert_eq!(
+        parse_literal(input),
+        Ok((
+            input.slice(5..),
+            Token {
+                position: input.slice(1..1),
+                value: Literal::Boolean(true)
+            }
+        ))
+    );
+
+    let input = Span::new_extra("falsehood", None);
+    assert!(parse_literal(input).is_err());
+}
+
+#[test]
+fn test_interpolated_string() {
+    use crate::tests::node;
+
+    use super::{ArithmeticOperator, BinaryOperator, Identifier};
+
+    let input = Span::new_extra("\"x is ${x} and y is ${ y + 1 }$\"", None);
+    assert_eq!(
+        parse_literal(input),
+        Ok((
+            input.slice(32..),
+            Token {
+                position: input.slice(0..0),
+                value: Literal::InterpolatedString(vec![
+                    StringPart::Text("x is ".to_owned()),
+                    StringPart::Expression(node(Expression::Identifier(Identifier::new("x")))),
+                    StringPart::Text(" and y is ".to_owned()),
+                    StringPart::Expression(node(Expression::BinaryExpression(
+                        Box::new(node(Expression::Identifier(Identifier::new("y")))),
+                        BinaryOperator::ArithmeticOperator(ArithmeticOperator::Add),
+                        Box::new(node(Expression::Literal(Literal::Integer(1)))),
+                    ))),
+                    StringPart::Text("$".to_owned()),
+                ])
+            }
+        ))
+    );
+}
+
+#[test]
+fn test_string_syntax() {
+    let string = |input: &str| match parse_literal(Span::new_extra(input, None)) {
+        Ok((
+            _,
+            Token {
+                value: Literal::String(string),
+                ..
+            },
+        )) => string,
+        result => panic!("expected a string but got {:?}", result),
+    };
+
+    assert_eq!(string("\"a, b. c: d-e\""), "a, b. c: d-e");
+    assert_eq!(string("\"héllo wörld 🦀\""), "héllo wörld 🦀");
+    assert_eq!(
+        string(r#""tab\tnew\nline \"quoted\" \\ \$ \u{1F980}\u{e9}""#),
+        "tab\tnew\nline \"quoted\" \\ $ 🦀é"
+    );
+    assert_eq!(string("\"first\nsecond\""), "first\nsecond");
+    assert_eq!(string("\"joined \\\n      line\""), "joined line");
+    assert_eq!(string(r#"r"C:\path\${x}""#), r"C:\path\${x}");
+    assert_eq!(string(r###"r#"say "hi""#"###), r#"say "hi""#);
+}
+
+#[test]
+fn test_string_escape_errors() {
+    for (input, offset) in [
+        ("\"ok \\q\"", 4),
+        ("\"\\u{110000}\"", 1),
+        ("\"\\u{}\"", 1),
+        ("  \"a\\u12\"", 4),
+    ] {
+        match parse_literal(Span::new_extra(input, None)) {
+            Err(nom::Err::Failure(ParseError {
+                span,
+                kind: ParseErrorKind::InvalidEscape,
+            })) => {
+                assert_eq!(span.location_offset(), offset, "wrong span for {input}")
+            }
+            result => panic!("expected an escape error for {input} but got {:?}", result),
+        }
+    }
+}
+
+#[test]
+fn test_interpolation_errors() {
+    for (input, offset) in [
+        ("\"${}\"", 1),
+        ("\"${1 +}\"", 1),
+        ("\"a ${x\"", 3),
+        ("  \"${x} ${(}\"", 8),
+    ] {
+        match parse_literal(Span::new_extra(input, None)) {
+            Err(nom::Err::Failure(ParseError {
+                span,
+                kind: ParseErrorKind::InvalidInterpolation,
+            })) => {
+                assert_eq!(span.location_offset(), offset, "wrong span for {input}")
+            }
+            result => panic!(
+                "expected an interpolation error for {input} but got {:?}",
+                result
+            ),
+        }
+    }
+}
+
+#[test]
+fn test_integer_syntax() {
+    let integer = |input: &str| match parse_literal(Span::new_extra(input, None)) {
+        Ok((
+            _,
+            Token {
+                value: Literal::Integer(value),
+                ..
+            },
+        )) => value,
+        result => panic!("expected an integer but got {:?}", result),
+    };
+
+    assert_eq!(integer("0"), 0);
+    assert_eq!(integer("1_000_000"), 1_000_000);
+    assert_eq!(integer("0xFF"), 255);
+    assert_eq!(integer("0x_dead_beef"), 0xdead_beef);
+    assert_eq!(integer("0b1010"), 10);
+    assert_eq!(integer("0o755"), 0o755);
+    assert_eq!(integer("9223372036854775807"), i64::MAX);
+}
+
+#[test]
+fn test_integer_errors() {
+    for (input, kind) in [
+        ("  9223372036854775808", ParseErrorKind::IntegerTooLarge),
+        ("0x1_0000_0000_0000_0000", ParseErrorKind::IntegerTooLarge),
+        ("0b102", ParseErrorKind::InvalidDigit),
+        ("0x", ParseErrorKind::InvalidDigit),
+        ("12abc", ParseErrorKind::InvalidDigit),
+    ] {
+        match parse_literal(Span::new_extra(input, None)) {
+            Err(nom::Err::Failure(ParseError {
+                span,
+                kind: error_kind,
+            })) => {
+                assert_eq!(error_kind, kind, "wrong error for {input}");
+                assert_eq!(
+                    span.location_offset(),
+                    input.len() - input.trim_start().len(),
+                    "wrong span for {input}"
+                );
+            }
+            result => panic!("expected an error for {input} but got {:?}", result),
+        }
+    }
 }
@@ -0,0 +1,107 @@
+use std::rc::Rc;
+
+use super::Span;
+
+// Where a node came from in the source. Offsets and lengths are in bytes,
+// while the column counts characters, as an editor would.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Location {
+    pub file: Option<Rc<str>>,
+    pub offset: usize,
+    pub line: u32,
+    pub column: usize,
+    pub length: usize,
+}
+
+impl Location {
+    pub fn new(span: Span, length: usize) -> Self {
+        Self {
+            file: span.extra.cloned(),
+            offset: span.location_offset(),
+            line: span.location_line(),
+            column: span.get_utf8_column(),
+            length,
+        }
+    }
+
+    // Covers everything from `start` up to, but not including, `end`.
+    pub fn between(start: Span, end: Span) -> Self {
+        Self::new(start, end.location_offset() - start.location_offset())
+    }
+
+    // Covers everything from the start of this location to the end of
+    // `other`.
+    pub fn to(&self, other: &Location) -> Self {
+        Self {
+            length: other.offset + other.length - self.offset,
+            ..self.clone()
+        }
+    }
+
+    // Covers everything from the start of this location up to, but not
+    // including, `end`.
+    pub fn up_to(&self, end: Span) -> Self {
+        Self {
+            length: end.location_offset() - self.offset,
+            ..self.clone()
+        }
+    }
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.file {
+            Some(ref file) => write!(f, "{}:{}:{}", file, self.line, self.column),
+            None => write!(f, "line {}, column {}", self.line, self.column),
+        }
+    }
+}
+
+// A node in the AST along with where it was written.
+#[derive(Clone, Debug)]
+pub struct Located<T> {
+    pub location: Location,
+    pub value: T,
+}
+
+impl<T> Located<T> {
+    pub fn new(location: Location, value: T) -> Self {
+        Self { location, value }
+    }
+}
+
+// Nodes compare equal if they mean the same thing, wherever they were
+// written, so that trees can be compared without spelling out every
+// location.
+impl<T: PartialEq> PartialEq for Located<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+#[test]
+fn test_location() {
+    use nom::Slice;
+
+    let file = Rc::from("test.risp");
+    let source = Span::new_extra("let x = 1\nlet é = x + 2", Some(&file));
+
+    let location = Location::between(source.slice(19..), source.slice(20..));
+    assert_eq!(
+        location,
+        Location {
+            file: Some(file.clone()),
+            offset: 19,
+            line: 2,
+            column: 9,
+            length: 1,
+        }
+    );
+    assert_eq!(location.to_string(), "test.risp:2:9");
+
+    let end = Location::new(source.slice(23..), 1);
+    assert_eq!(location.to(&end).length, 5);
+
+    let location = Location::new(Span::new_extra("x", None), 1);
+    assert_eq!(location.to_string(), "line 1, column 1");
+}
@@ -0,0 +1,183 @@
+// we use this but Rust Analyzer doesn't notice it...?
+#[allow(unused_imports)]
+use nom::Slice;
+
+use super::{
+    comment::comment, identifier::identifier_name, literal::raw_string, Block, Located, Location,
+    ParseError, ParseErrorKind, Span, Statement,
+};
+
+// Turns the error from a statement that failed to parse into a placeholder
+// for it. An error right at the start of the statement means nothing there
+// made sense as a statement at all.
+pub(super) fn error_statement(input: Span, error: nom::Err<ParseError>) -> Located<Statement> {
+    let (span, kind) = match error {
+        nom::Err::Failure(error) => (error.span, error.kind),
+        nom::Err::Error(error) if error.span.location_offset() > input.location_offset() => {
+            (error.span, error.kind)
+        }
+        _ => (input, ParseErrorKind::UnexpectedInput),
+    };
+
+    Located::new(Location::new(span, 0), Statement::Error(kind))
+}
+
+// Skips a statement that failed to parse, up to the end of its line or up to
+// a closing brace that ends the block it's in. Braces inside the statement
+// are skipped along with everything between them, even over several lines.
+pub(super) fn skip_statement(input: Span) -> Span {
+    skip_code(input, true)
+}
+
+// Skips code up to a closing brace that isn't matched within it, or if
+// `to_line_end`, past the end of the line if that comes first. Comments and
+// strings are skipped whole, so nothing inside them is taken for a brace.
+fn skip_code(mut input: Span, to_line_end: bool) -> Span {
+    let mut depth = 0;
+
+    loop {
+        if let Ok((rest, _)) = comment(input) {
+            input = rest;
+            continue;
+        }
+        if let Ok((rest, _)) = raw_string(input) {
+            input = rest;
+            continue;
+        }
+        if let Ok((rest, _)) = identifier_name::<_, ParseError>(input) {
+            input = rest;
+            continue;
+        }
+
+        let Some(c) = input.fragment().chars().next() else {
+            return input;
+        };
+
+        match c {
+            '"' => {
+                input = skip_string(input.slice(1..));
+                continue;
+            }
+            '\n' if to_line_end && depth == 0 => return input.slice(1..),
+            '}' if depth == 0 => return input,
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => {}
+        }
+
+        input = input.slice(c.len_utf8()..);
+    }
+}
+
+// Skips the rest of a string literal after its opening quote, including any
+// code interpolated into it, and its closing quote.
+fn skip_string(mut input: Span) -> Span {
+    loop {
+        let fragment = *input.fragment();
+
+        if fragment.starts_with("${") {
+            input = skip_code(input.slice(2..), false);
+            if input.fragment().starts_with('}') {
+                input = input.slice(1..);
+            }
+            continue;
+        }
+
+        let mut chars = fragment.chars();
+        match chars.next() {
+            None => return input,
+            Some('"') => return input.slice(1..),
+            Some('\\') => {
+                let escaped = chars.next().map_or(0, char::len_utf8);
+                input = input.slice(1 + escaped..);
+            }
+            Some(c) => input = input.slice(c.len_utf8()..),
+        }
+    }
+}
+
+// Finds the errors left in `block` by statements that failed to parse, in the
+// order they appear in `source`.
+pub(super) fn collect_errors<'a>(source: Span<'a>, block: &Block) -> Vec<ParseError<'a>> {
+    let mut errors = Vec::new();
+    collect_block_errors(source, block, &mut errors);
+    errors.sort_by_key(|error| error.span.location_offset());
+    errors
+}
+
+fn collect_block_errors<'a>(source: Span<'a>, block: &Block, errors: &mut Vec<ParseError<'a>>) {
+    for statement in &block.0 {
+        match &statement.value {
+            Statement::Error(kind) => errors.push(ParseError::new(
+                source.slice(statement.location.offset..),
+                kind.clone(),
+            )),
+            Statement::FunctionDefinition(definition) => {
+                collect_block_errors(source, &definition.body, errors)
+            }
+            Statement::Condition(condition) => {
+                for (_predicate, block) in &condition.branches {
+                    collect_block_errors(source, block, errors);
+                }
+            }
+            Statement::Loop(loop_statement) => {
+                collect_block_errors(source, &loop_statement.block, errors)
+            }
+            Statement::Expression(_)
+            | Statement::VariableDeclaration(_)
+            | Statement::Return(_)
+            | Statement::Assignment(_) => {}
+        }
+    }
+}
+
+#[test]
+fn test_error_recovery() {
+    use super::{parse_with_recovery, statement::FunctionDefinition, Identifier};
+
+    let source = "def f(x) {\n    let = x\n    x + 1\n}\n\ndef g(y {\n    y\n}\n\n1 @ 2\n}\ndef h(z) { z * \"oops }";
+    let (block, errors) = parse_with_recovery(source, None);
+
+    let errors = errors
+        .into_iter()
+        .map(|error| {
+            (
+                error.span.location_line(),
+                error.span.get_utf8_column(),
+                error.kind,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        vec![
+            (2, 9, ParseErrorKind::Expected("an identifier")),
+            (6, 8, ParseErrorKind::ExpectedChar(')')),
+            (10, 3, ParseErrorKind::Expected("`;` or a new line")),
+            (11, 1, ParseErrorKind::UnexpectedInput),
+            (12, 16, ParseErrorKind::UnterminatedString),
+            (12, 23, ParseErrorKind::ExpectedToken("}")),
+        ]
+    );
+
+    // Everything that did parse is kept.
+    let names = block
+        .0
+        .iter()
+        .filter_map(|statement| match &statement.value {
+            Statement::FunctionDefinition(FunctionDefinition { name, .. }) => {
+                Some(name.value.clone())
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec![Identifier::new("f"), Identifier::new("h")]);
+    let Statement::FunctionDefinition(FunctionDefinition { body, .. }) = &block.0[0].value else {
+        panic!("expected a function definition");
+    };
+    assert_eq!(body.0.len(), 2);
+    assert!(matches!(
+        (&body.0[0].value, &body.0[1].value),
+        (Statement::Error(_), Statement::Expression(_))
+    ));
+}
@@ -6,9 +6,11 @@ mod loop_statement;
 mod return_statement;
 mod variable_declaration;
 
-use nom::branch::alt;
+use nom::{branch::alt, error::context};
 
-use super::{Expression, ParseResult, Span};
+use super::{
+    comment::forget_comments_on_error, Expression, Located, ParseErrorKind, ParseResult, Span,
+};
 
 use self::{
     assignment::parse_assignment_statement, condition::parse_condition_statement,
@@ -19,7 +21,7 @@ use self::{
 };
 
 pub use self::{
-    assignment::Assignment,
+    assignment::{assignment_operator, assignment_operator_token, Assignment},
     condition::Condition,
     function_definition::FunctionDefinition,
     loop_statement::{Loop, LoopPredicatePosition},
@@ -28,23 +30,29 @@ pub use self::{
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Statement {
-    Expression(Expression),
+    Expression(Located<Expression>),
     FunctionDefinition(FunctionDefinition),
     VariableDeclaration(VariableDeclaration),
     Condition(Condition),
-    Return(Expression),
+    Return(Located<Expression>),
     Assignment(Assignment),
     Loop(Loop),
+    // A statement that failed to parse. Its location is where the error
+    // happened.
+    Error(ParseErrorKind),
 }
 
 pub fn parse_statement(input: Span) -> ParseResult<Statement> {
-    alt((
-        parse_function_definition_statement,
-        parse_variable_declaration_statement,
-        parse_condition_statement,
-        parse_loop_statement,
-        parse_return_statement,
-        parse_assignment_statement,
-        parse_expression_statement,
-    ))(input)
+    context(
+        "a statement",
+        alt((
+            forget_comments_on_error(parse_function_definition_statement),
+            forget_comments_on_error(parse_variable_declaration_statement),
+            forget_comments_on_error(parse_condition_statement),
+            forget_comments_on_error(parse_loop_statement),
+            forget_comments_on_error(parse_return_statement),
+            forget_comments_on_error(parse_assignment_statement),
+            forget_comments_on_error(parse_expression_statement),
+        )),
+    )(input)
 }
@@ -1,29 +1,65 @@
+use nom::branch::alt;
+
 use crate::parser::{
-    parse_expression, parse_identifier, tokens::assignment_token, Expression, Identifier,
-    ParseResult, Span, Token,
+    parse_expression, parse_identifier,
+    tokens::{assignment_token, compound_assignment_token},
+    util::located,
+    ArithmeticOperator, Expression, Identifier, Located, Location, ParseResult, Span, Token,
 };
 
 use super::Statement;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Assignment {
-    pub lhs: Identifier,
-    pub rhs: Expression,
+    pub lhs: Located<Identifier>,
+    // For compound assignments such as `x += 1`, the operator that combines
+    // the current value with the right hand side.
+    pub operator: Option<ArithmeticOperator>,
+    pub rhs: Located<Expression>,
 }
 
 pub fn parse_assignment_statement(input: Span) -> ParseResult<Statement> {
-    let (input, lhs) = parse_identifier(input)?;
-    let (input, _) = assignment_token(input)?;
-    let (input, rhs) = parse_expression(input)?;
+    let (input, lhs_token) = parse_identifier(input)?;
+    let position = lhs_token.position;
+    let lhs = Located::new(Location::between(position, input), lhs_token.value);
+    let (input, token) = assignment_operator_token(input)?;
+    let (input, rhs) = located(parse_expression)(input)?;
 
     Ok((
         input,
         Token {
-            position: lhs.position,
+            position,
             value: Statement::Assignment(Assignment {
-                lhs: lhs.value,
-                rhs: rhs.value,
+                lhs,
+                operator: assignment_operator(&token.value),
+                rhs,
             }),
         },
     ))
 }
+
+// `=` or a compound assignment operator such as `+=`.
+pub fn assignment_operator_token(input: Span) -> ParseResult<String> {
+    alt((compound_assignment_token, assignment_token))(input)
+}
+
+// The operator that an assignment token combines the current value with the
+// right hand side using, or `None` for a plain `=`.
+pub fn assignment_operator(token: &str) -> Option<ArithmeticOperator> {
+    match token {
+        "=" => None,
+        "+=" => Some(ArithmeticOperator::Add),
+        "-=" => Some(ArithmeticOperator::Subtract),
+        "*=" => Some(ArithmeticOperator::Multiply),
+        "/=" => Some(ArithmeticOperator::Divide),
+        "%=" => Some(ArithmeticOperator::Remainder),
+        "**=" => Some(ArithmeticOperator::Power),
+        "&=" => Some(ArithmeticOperator::BitwiseAnd),
+        "|=" => Some(ArithmeticOperator::BitwiseOr),
+        "^=" => Some(ArithmeticOperator::BitwiseXor),
+        "<<=" => Some(ArithmeticOperator::ShiftLeft),
+        ">>=" => Some(ArithmeticOperator::ShiftRight),
+        ">>>=" => Some(ArithmeticOperator::LogicalShiftRight),
+        _ => unreachable!("unknown assignment operator {}", token),
+    }
+}
@@ -3,7 +3,8 @@ use nom::{combinator::opt, multi::many0};
 use crate::parser::{
     parse_block, parse_expression,
     tokens::{else_keyword, if_keyword},
-    Block, Expression, ParseResult, Span, Token,
+    util::located,
+    Block, Expression, Located, ParseResult, Span, Token,
 };
 
 use super::Statement;
@@ -15,7 +16,7 @@ pub struct Condition {
     //  - (Some(x), 1)
     //  - (Some(y), 2)
     //  - (None, 3)
-    pub branches: Vec<(Option<Expression>, Block)>,
+    pub branches: Vec<(Option<Located<Expression>>, Block)>,
 }
 
 pub fn parse_condition_statement(input: Span) -> ParseResult<Statement> {
@@ -34,9 +35,9 @@ fn parse_condition(input: Span) -> ParseResult<Condition> {
     let mut branches = Vec::new();
 
     let (input, if_keyword) = if_keyword(input)?;
-    let (input, if_predicate) = parse_expression(input)?;
+    let (input, if_predicate) = located(parse_expression)(input)?;
     let (input, if_block) = parse_block(input)?;
-    branches.push((Some(if_predicate.value), if_block.value));
+    branches.push((Some(if_predicate), if_block.value));
 
     let (input, else_if_branches) = many0(parse_else_if_branch)(input)?;
     for branch in else_if_branches {
@@ -57,22 +58,22 @@ fn parse_condition(input: Span) -> ParseResult<Condition> {
     ))
 }
 
-fn parse_else_if_branch(input: Span) -> ParseResult<(Option<Expression>, Block)> {
+fn parse_else_if_branch(input: Span) -> ParseResult<(Option<Located<Expression>>, Block)> {
     let (input, else_keyword) = else_keyword(input)?;
     let (input, _if_keyword) = if_keyword(input)?;
-    let (input, predicate) = parse_expression(input)?;
+    let (input, predicate) = located(parse_expression)(input)?;
     let (input, block) = parse_block(input)?;
 
     Ok((
         input,
         Token {
             position: else_keyword.position,
-            value: (Some(predicate.value), block.value),
+            value: (Some(predicate), block.value),
         },
     ))
 }
 
-fn parse_else_branch(input: Span) -> ParseResult<(Option<Expression>, Block)> {
+fn parse_else_branch(input: Span) -> ParseResult<(Option<Located<Expression>>, Block)> {
     let (input, else_keyword) = else_keyword(input)?;
     let (input, block) = parse_block(input)?;
 
@@ -94,7 +95,7 @@ mod tests {
             statement::condition::parse_condition, BinaryOperator, Block, ComparisonOperator,
             Expression, Literal, Statement, Token,
         },
-        tests::parse_test,
+        tests::{node, parse_test},
     };
 
     use super::Condition;
@@ -108,14 +109,14 @@ mod tests {
                     position: input.slice(0..0),
                     value: Condition {
                         branches: vec![(
-                            Some(Expression::BinaryExpression(
-                                Box::new(Expression::Literal(Literal::Integer(1))),
+                            Some(node(Expression::BinaryExpression(
+                                Box::new(node(Expression::Literal(Literal::Integer(1)))),
                                 BinaryOperator::ComparisonOperator(ComparisonOperator::LessThan),
-                                Box::new(Expression::Literal(Literal::Integer(2))),
-                            )),
-                            Block(vec![Statement::Expression(Expression::Literal(
-                                Literal::String("hello".to_string()),
-                            ))]),
+                                Box::new(node(Expression::Literal(Literal::Integer(2)))),
+                            ))),
+                            Block(vec![node(Statement::Expression(node(
+                                Expression::Literal(Literal::String("hello".to_string())),
+                            )))]),
                         )],
                     },
                 },
@@ -1,15 +1,16 @@
-use crate::parser::{parse_expression, ParseResult, Span, Token};
+use crate::parser::{parse_expression, Located, Location, ParseResult, Span, Token};
 
 use super::Statement;
 
 pub fn parse_expression_statement(input: Span) -> ParseResult<Statement> {
     let (input, expression) = parse_expression(input)?;
+    let location = Location::between(expression.position, input);
 
     Ok((
         input,
         Token {
             position: expression.position,
-            value: Statement::Expression(expression.value),
+            value: Statement::Expression(Located::new(location, expression.value)),
         },
     ))
 }
@@ -1,25 +1,26 @@
-use nom::{character::complete::space0, multi::separated_list0};
+use nom::multi::separated_list0;
 use nom_locate::position;
 
 use crate::parser::{
+    comment::whitespace,
     parse_block, parse_identifier,
     tokens::{comma_token, def_keyword},
-    util::bracketed,
-    Block, Identifier, ParseResult, Span, Token,
+    util::{bracketed, located},
+    Block, Identifier, Located, ParseResult, Span, Token,
 };
 
 #[cfg(test)]
 use crate::{
     parser::{ArithmeticOperator, VariableDeclaration},
-    tests::parse_test,
+    tests::{node, parse_test},
 };
 
 use super::Statement;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct FunctionDefinition {
-    pub name: Identifier,
-    pub args: Vec<Identifier>,
+    pub name: Located<Identifier>,
+    pub args: Vec<Located<Identifier>>,
     pub body: Block,
 }
 
@@ -37,7 +38,7 @@ pub fn parse_function_definition_statement(input: Span) -> ParseResult<Statement
 
 pub fn parse_function_definition(input: Span) -> ParseResult<FunctionDefinition> {
     let (input, def_token) = def_keyword(input)?;
-    let (input, name) = parse_identifier(input)?;
+    let (input, name) = located(parse_identifier)(input)?;
     let (input, args) = parse_arguments_list(input)?;
     let (input, body) = parse_block(input)?;
 
@@ -46,7 +47,7 @@ pub fn parse_function_definition(input: Span) -> ParseResult<FunctionDefinition>
         Token {
             position: def_token.position,
             value: FunctionDefinition {
-                name: name.value,
+                name,
                 args: args.value,
                 body: body.value,
             },
@@ -54,12 +55,11 @@ pub fn parse_function_definition(input: Span) -> ParseResult<FunctionDefinition>
     ))
 }
 
-fn parse_arguments_list(input: Span) -> ParseResult<Vec<Identifier>> {
-    let (input, _) = space0(input)?;
+fn parse_arguments_list(input: Span) -> ParseResult<Vec<Located<Identifier>>> {
+    let (input, _) = whitespace(input)?;
     let (input, position) = position(input)?;
-    let (input, value) = bracketed(separated_list0(comma_token, parse_identifier))(input)?;
+    let (input, value) = bracketed(separated_list0(comma_token, located(parse_identifier)))(input)?;
 
-    let value = value.iter().map(|t| t.value.clone()).collect();
     Ok((input, Token { position, value }))
 }
 
@@ -77,13 +77,15 @@ fn test_function_definition_1() {
                 Token {
                     position: input.slice(0..0),
                     value: FunctionDefinition {
-                        name: Identifier("add_one".to_string()),
-                        args: vec![Identifier("x".to_string())],
-                        body: Block(vec![Statement::Expression(Expression::BinaryExpression(
-                            Box::new(Expression::Literal(Literal::Integer(1))),
-                            BinaryOperator::ArithmeticOperator(ArithmeticOperator::Add),
-                            Box::new(Expression::Identifier(Identifier("x".to_string()))),
-                        ))]),
+                        name: node(Identifier("add_one".to_string())),
+                        args: vec![node(Identifier("x".to_string()))],
+                        body: Block(vec![node(Statement::Expression(node(
+                            Expression::BinaryExpression(
+                                Box::new(node(Expression::Literal(Literal::Integer(1)))),
+                                BinaryOperator::ArithmeticOperator(ArithmeticOperator::Add),
+                                Box::new(node(Expression::Identifier(Identifier("x".to_string())))),
+                            ),
+                        )))]),
                     },
                 },
             )
@@ -107,13 +109,15 @@ fn test_function_definition_2() {
                 Token {
                     position: input.slice(0..0),
                     value: FunctionDefinition {
-                        name: Identifier("add_one".to_string()),
-                        args: vec![Identifier("x".to_string())],
-                        body: Block(vec![Statement::Expression(Expression::BinaryExpression(
-                            Box::new(Expression::Literal(Literal::Integer(1))),
-                            BinaryOperator::ArithmeticOperator(ArithmeticOperator::Add),
-                            Box::new(Expression::Identifier(Identifier("x".to_string()))),
-                        ))]),
+                        name: node(Identifier("add_one".to_string())),
+                        args: vec![node(Identifier("x".to_string()))],
+                        body: Block(vec![node(Statement::Expression(node(
+                            Expression::BinaryExpression(
+                                Box::new(node(Expression::Literal(Literal::Integer(1)))),
+                                BinaryOperator::ArithmeticOperator(ArithmeticOperator::Add),
+                                Box::new(node(Expression::Identifier(Identifier("x".to_string())))),
+                            ),
+                        )))]),
                     },
                 },
             )
@@ -135,22 +139,26 @@ fn test_function_definition_3() {
                 Token {
                     position: input.slice(0..0),
                     value: FunctionDefinition {
-                        name: Identifier("square".to_string()),
-                        args: vec![Identifier("x".to_string())],
+                        name: node(Identifier("square".to_string())),
+                        args: vec![node(Identifier("x".to_string()))],
                         body: Block(vec![
-                            Statement::VariableDeclaration(VariableDeclaration {
-                                name: Identifier::new("result"),
-                                value: Expression::BinaryExpression(
-                                    Box::new(Expression::Identifier(Identifier("x".to_string()))),
+                            node(Statement::VariableDeclaration(VariableDeclaration {
+                                name: node(Identifier::new("result")),
+                                value: node(Expression::BinaryExpression(
+                                    Box::new(node(Expression::Identifier(Identifier(
+                                        "x".to_string(),
+                                    )))),
                                     BinaryOperator::ArithmeticOperator(
                                         ArithmeticOperator::Multiply,
                                     ),
-                                    Box::new(Expression::Identifier(Identifier("x".to_string()))),
-                                ),
-                            }),
-                            Statement::Expression(Expression::Identifier(Identifier::new(
-                                "result",
-                            ))),
+                                    Box::new(node(Expression::Identifier(Identifier(
+                                        "x".to_string(),
+                                    )))),
+                                )),
+                            })),
+                            node(Statement::Expression(node(Expression::Identifier(
+                                Identifier::new("result"),
+                            )))),
                         ]),
                     },
                 },
@@ -158,3 +166,21 @@ fn test_function_definition_3() {
         },
     )
 }
+
+#[test]
+fn test_function_definition_name_locations() {
+    let input = Span::new_extra("def add(x, total) { x }", None);
+    let (_, definition) = parse_function_definition(input).unwrap();
+
+    let location = |name: &Located<Identifier>| (name.location.column, name.location.length);
+    assert_eq!(location(&definition.value.name), (5, 3));
+    assert_eq!(
+        definition
+            .value
+            .args
+            .iter()
+            .map(location)
+            .collect::<Vec<_>>(),
+        vec![(9, 1), (12, 5)]
+    );
+}
@@ -1,8 +1,8 @@
 use nom::branch::alt;
 
 use crate::parser::{
-    parse_block, parse_expression, tokens::while_keyword, Block, Expression, ParseResult, Span,
-    Token,
+    parse_block, parse_expression, tokens::while_keyword, util::located, Block, Expression,
+    Located, ParseResult, Span, Token,
 };
 
 use super::Statement;
@@ -18,7 +18,7 @@ pub enum LoopPredicatePosition {
 #[derive(Clone, Debug, PartialEq)]
 pub struct Loop {
     pub predicate_position: LoopPredicatePosition,
-    pub predicate: Expression,
+    pub predicate: Located<Expression>,
     pub block: Block,
 }
 
@@ -28,7 +28,7 @@ pub fn parse_loop_statement(input: Span) -> ParseResult<Statement> {
 
 pub fn parse_while_loop_statement(input: Span) -> ParseResult<Statement> {
     let (input, while_keyword) = while_keyword(input)?;
-    let (input, predicate) = parse_expression(input)?;
+    let (input, predicate) = located(parse_expression)(input)?;
     let (input, block) = parse_block(input)?;
 
     Ok((
@@ -37,7 +37,7 @@ pub fn parse_while_loop_statement(input: Span) -> ParseResult<Statement> {
             position: while_keyword.position,
             value: Statement::Loop(Loop {
                 predicate_position: LoopPredicatePosition::BeforeBlock,
-                predicate: predicate.value,
+                predicate,
                 block: block.value,
             }),
         },
@@ -1,16 +1,18 @@
-use crate::parser::{parse_expression, tokens::return_keyword, ParseResult, Span, Token};
+use crate::parser::{
+    parse_expression, tokens::return_keyword, util::located, ParseResult, Span, Token,
+};
 
 use super::Statement;
 
 pub fn parse_return_statement(input: Span) -> ParseResult<Statement> {
     let (input, return_token) = return_keyword(input)?;
-    let (input, result) = parse_expression(input)?;
+    let (input, result) = located(parse_expression)(input)?;
 
     Ok((
         input,
         Token {
             position: return_token.position,
-            value: Statement::Return(result.value),
+            value: Statement::Return(result),
         },
     ))
 }
@@ -4,32 +4,33 @@ use nom::Slice;
 use crate::parser::{
     parse_expression, parse_identifier,
     tokens::{assignment_token, let_keyword},
-    Expression, Identifier, ParseResult, Span, Token,
+    util::located,
+    Expression, Identifier, Located, ParseResult, Span, Token,
 };
 
 #[cfg(test)]
 use crate::{
     parser::{ArithmeticOperator, BinaryOperator, Literal},
-    tests::parse_test,
+    tests::{node, parse_test},
 };
 
 use super::Statement;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct VariableDeclaration {
-    pub name: Identifier,
-    pub value: Expression,
+    pub name: Located<Identifier>,
+    pub value: Located<Expression>,
 }
 
 pub fn parse_variable_declaration(input: Span) -> ParseResult<VariableDeclaration> {
     let (input, let_token) = let_keyword(input)?;
-    let (input, name) = parse_identifier(input)?;
+    let (input, name) = located(parse_identifier)(input)?;
     let (input, _) = assignment_token(input)?;
-    let (input, initial_value) = parse_expression(input)?;
+    let (input, initial_value) = located(parse_expression)(input)?;
 
     let value = VariableDeclaration {
-        name: name.value,
-        value: initial_value.value,
+        name,
+        value: initial_value,
     };
 
     println!("Variable declaration: {:?}", value);
@@ -62,12 +63,12 @@ fn test_parse_variable_declaration() {
             Token {
                 position: input.slice(0..0),
                 value: VariableDeclaration {
-                    name: Identifier::new("x"),
-                    value: Expression::BinaryExpression(
-                        Box::new(Expression::Literal(Literal::Integer(55))),
+                    name: node(Identifier::new("x")),
+                    value: node(Expression::BinaryExpression(
+                        Box::new(node(Expression::Literal(Literal::Integer(55)))),
                         BinaryOperator::ArithmeticOperator(ArithmeticOperator::Add),
-                        Box::new(Expression::Literal(Literal::Integer(42))),
-                    ),
+                        Box::new(node(Expression::Literal(Literal::Integer(42)))),
+                    )),
                 },
             },
         )
@@ -82,12 +83,12 @@ fn test_parse_variable_declaration_2() {
             Token {
                 position: input.slice(0..0),
                 value: VariableDeclaration {
-                    name: Identifier::new("result"),
-                    value: Expression::BinaryExpression(
-                        Box::new(Expression::Identifier(Identifier::new("x"))),
+                    name: node(Identifier::new("result")),
+                    value: node(Expression::BinaryExpression(
+                        Box::new(node(Expression::Identifier(Identifier::new("x")))),
                         BinaryOperator::ArithmeticOperator(ArithmeticOperator::Multiply),
-                        Box::new(Expression::Identifier(Identifier::new("x"))),
-                    ),
+                        Box::new(node(Expression::Identifier(Identifier::new("x")))),
+                    )),
                 },
             },
         )
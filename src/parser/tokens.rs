@@ -1,9 +1,47 @@
-use super::{util::ignore_whitespace, ParseResult, Span, Token};
-use nom::bytes::complete::tag;
+use super::{
+    identifier::identifier_name, util::ignore_whitespace, ParseError, ParseErrorKind, ParseResult,
+    Span, Token,
+};
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::one_of,
+    combinator::{not, verify},
+    sequence::terminated,
+};
+
+// Words that mean something to the parser, so can't be used as identifiers.
+pub const KEYWORDS: &[&str] = &[
+    "def", "let", "if", "else", "return", "while", "true", "false",
+];
+
+pub fn is_keyword(word: &str) -> bool {
+    KEYWORDS.contains(&word)
+}
+
+fn token<'a>(c: &'static str) -> impl FnMut(Span<'a>) -> ParseResult<'a, String> {
+    move |input| {
+        let (input, token) = ignore_whitespace(tag(c))(input).map_err(expected(c))?;
+        Ok((
+            input,
+            Token {
+                position: token.position,
+                value: token.value.to_string(),
+            },
+        ))
+    }
+}
 
-fn token<'a>(c: &'a str) -> impl FnMut(Span<'a>) -> ParseResult<'a, String> {
+// Like `token`, but doesn't match when the operator is immediately followed
+// by one of `continuations`, so that `<` doesn't match the start of `<<`.
+fn operator_token<'a>(
+    c: &'static str,
+    continuations: &'static str,
+) -> impl FnMut(Span<'a>) -> ParseResult<'a, String> {
     move |input| {
-        let (input, token) = ignore_whitespace(tag(c))(input)?;
+        let (input, token) =
+            ignore_whitespace(terminated(tag(c), not(one_of(continuations))))(input)
+                .map_err(expected(c))?;
         Ok((
             input,
             Token {
@@ -14,9 +52,14 @@ fn token<'a>(c: &'a str) -> impl FnMut(Span<'a>) -> ParseResult<'a, String> {
     }
 }
 
-fn keyword(k: &str) -> impl FnMut(Span) -> ParseResult<'_, Span> + '_ {
+// Matches a whole word, so that `def` doesn't match the start of `define`.
+fn keyword<'a>(k: &'static str) -> impl FnMut(Span<'a>) -> ParseResult<'a, Span<'a>> {
+    debug_assert!(is_keyword(k), "{} is missing from KEYWORDS", k);
+
     move |input| {
-        let (input, token) = ignore_whitespace(tag(k))(input)?;
+        let (input, token) =
+            ignore_whitespace(verify(identifier_name, |word: &Span| *word.fragment() == k))(input)
+                .map_err(expected(k))?;
         Ok((
             input,
             Token {
@@ -27,6 +70,18 @@ fn keyword(k: &str) -> impl FnMut(Span) -> ParseResult<'_, Span> + '_ {
     }
 }
 
+// Reports an error as "expected `c`", where it happened, which is after any
+// whitespace in front of the token.
+fn expected<'a>(c: &'static str) -> impl Fn(nom::Err<ParseError<'a>>) -> nom::Err<ParseError<'a>> {
+    move |error| match error {
+        nom::Err::Error(error) => nom::Err::Error(ParseError::new(
+            error.span,
+            ParseErrorKind::ExpectedToken(c),
+        )),
+        error => error,
+    }
+}
+
 pub fn comma_token(input: Span<'_>) -> ParseResult<'_, String> {
     token(",")(input)
 }
@@ -40,19 +95,27 @@ pub fn close_brace_token(input: Span<'_>) -> ParseResult<'_, String> {
 }
 
 pub fn add_token(input: Span<'_>) -> ParseResult<'_, String> {
-    token("+")(input)
+    operator_token("+", "=")(input)
 }
 
 pub fn subtract_token(input: Span<'_>) -> ParseResult<'_, String> {
-    token("-")(input)
+    operator_token("-", "=")(input)
 }
 
 pub fn multiply_token(input: Span<'_>) -> ParseResult<'_, String> {
-    token("*")(input)
+    operator_token("*", "*=")(input)
+}
+
+pub fn power_token(input: Span<'_>) -> ParseResult<'_, String> {
+    operator_token("**", "=")(input)
 }
 
 pub fn divide_token(input: Span<'_>) -> ParseResult<'_, String> {
-    token("/")(input)
+    operator_token("/", "=")(input)
+}
+
+pub fn remainder_token(input: Span<'_>) -> ParseResult<'_, String> {
+    operator_token("%", "=")(input)
 }
 
 pub fn equality_token(input: Span<'_>) -> ParseResult<'_, String> {
@@ -64,7 +127,7 @@ pub fn inequality_token(input: Span<'_>) -> ParseResult<'_, String> {
 }
 
 pub fn less_than_token(input: Span<'_>) -> ParseResult<'_, String> {
-    token("<")(input)
+    operator_token("<", "<")(input)
 }
 
 pub fn less_or_equal_token(input: Span<'_>) -> ParseResult<'_, String> {
@@ -72,17 +135,75 @@ pub fn less_or_equal_token(input: Span<'_>) -> ParseResult<'_, String> {
 }
 
 pub fn greater_than_token(input: Span<'_>) -> ParseResult<'_, String> {
-    token(">")(input)
+    operator_token(">", ">")(input)
 }
 
 pub fn greater_or_equal_token(input: Span<'_>) -> ParseResult<'_, String> {
     token(">=")(input)
 }
 
+pub fn and_token(input: Span<'_>) -> ParseResult<'_, String> {
+    token("&&")(input)
+}
+
+pub fn or_token(input: Span<'_>) -> ParseResult<'_, String> {
+    token("||")(input)
+}
+
+pub fn bitwise_and_token(input: Span<'_>) -> ParseResult<'_, String> {
+    operator_token("&", "&=")(input)
+}
+
+pub fn bitwise_or_token(input: Span<'_>) -> ParseResult<'_, String> {
+    operator_token("|", "|=")(input)
+}
+
+pub fn bitwise_xor_token(input: Span<'_>) -> ParseResult<'_, String> {
+    operator_token("^", "=")(input)
+}
+
+pub fn bitwise_not_token(input: Span<'_>) -> ParseResult<'_, String> {
+    token("~")(input)
+}
+
+pub fn shift_left_token(input: Span<'_>) -> ParseResult<'_, String> {
+    operator_token("<<", "=")(input)
+}
+
+pub fn shift_right_token(input: Span<'_>) -> ParseResult<'_, String> {
+    operator_token(">>", ">=")(input)
+}
+
+pub fn logical_shift_right_token(input: Span<'_>) -> ParseResult<'_, String> {
+    operator_token(">>>", "=")(input)
+}
+
+pub fn not_token(input: Span<'_>) -> ParseResult<'_, String> {
+    token("!")(input)
+}
+
 pub fn assignment_token(input: Span<'_>) -> ParseResult<'_, String> {
     token("=")(input)
 }
 
+// Any of the operators that update a variable in place, such as `+=`.
+pub fn compound_assignment_token(input: Span<'_>) -> ParseResult<'_, String> {
+    alt((
+        token("+="),
+        token("-="),
+        token("*="),
+        token("**="),
+        token("/="),
+        token("%="),
+        token("&="),
+        token("|="),
+        token("^="),
+        token("<<="),
+        token(">>>="),
+        token(">>="),
+    ))(input)
+}
+
 pub fn def_keyword(input: Span<'_>) -> ParseResult<'_, Span<'_>> {
     keyword("def")(input)
 }
@@ -106,3 +227,22 @@ pub fn return_keyword(input: Span<'_>) -> ParseResult<'_, Span<'_>> {
 pub fn while_keyword(input: Span<'_>) -> ParseResult<'_, Span<'_>> {
     keyword("while")(input)
 }
+
+#[test]
+fn test_keyword() {
+    use nom::Slice;
+
+    let input = Span::new_extra(" let x", None);
+    let (rest, token) = let_keyword(input).unwrap();
+    assert_eq!(*token.value.fragment(), "let");
+    assert_eq!(rest, input.slice(4..));
+
+    let input = Span::new_extra("letter", None);
+    assert_eq!(
+        let_keyword(input),
+        Err(nom::Err::Error(ParseError::new(
+            input,
+            ParseErrorKind::ExpectedToken("let")
+        )))
+    );
+}
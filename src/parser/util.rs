@@ -1,16 +1,16 @@
 use std::ops::{RangeFrom, RangeTo};
 
 use nom::{
-    character::complete::{char, multispace0},
-    error::ParseError,
+    character::complete::char,
+    error::ParseError as NomParseError,
     sequence::{preceded, terminated},
     AsChar, IResult, InputIter, Offset, Slice,
 };
 use nom_locate::position;
 
-use super::{ParseResult, Span, Token};
+use super::{comment::whitespace, Located, Location, ParseError, ParseResult, Span, Token};
 
-pub fn bracketed<I, O, E: ParseError<I>, F>(f: F) -> impl FnMut(I) -> IResult<I, O, E>
+pub fn bracketed<I, O, E: NomParseError<I>, F>(f: F) -> impl FnMut(I) -> IResult<I, O, E>
 where
     F: FnMut(I) -> IResult<I, O, E>,
     I: InputIter,
@@ -24,12 +24,23 @@ where
 }
 
 pub fn ignore_whitespace<'a, O: std::fmt::Debug + std::cmp::PartialEq>(
-    mut parser: impl FnMut(Span<'a>) -> IResult<Span<'a>, O, (Span<'a>, nom::error::ErrorKind)>,
+    mut parser: impl FnMut(Span<'a>) -> IResult<Span<'a>, O, ParseError<'a>>,
 ) -> impl FnMut(Span<'a>) -> ParseResult<'a, O> {
     move |input| {
-        let (input, _) = multispace0(input)?;
+        let (input, _) = whitespace(input)?;
         let (input, position) = position(input)?;
         let (input, value) = parser(input)?;
         Ok((input, Token { position, value }))
     }
 }
+
+// Keeps the location of whatever `parser` parsed along with its result.
+pub fn located<'a, O: std::fmt::Debug + std::cmp::PartialEq>(
+    mut parser: impl FnMut(Span<'a>) -> ParseResult<'a, O>,
+) -> impl FnMut(Span<'a>) -> IResult<Span<'a>, Located<O>, ParseError<'a>> {
+    move |input| {
+        let (input, token) = parser(input)?;
+        let location = Location::between(token.position, input);
+        Ok((input, Located::new(location, token.value)))
+    }
+}
@@ -0,0 +1,94 @@
+mod arithmetic;
+mod builtins;
+mod comparison;
+mod error;
+mod format;
+mod string;
+mod truthiness;
+
+use std::cell::{Cell, RefCell};
+
+use crate::{
+    parser::{Identifier, Location},
+    value::EncodedValue,
+};
+
+pub use self::{
+    arithmetic::{
+        risp_add, risp_bitwise_and, risp_bitwise_or, risp_bitwise_xor, risp_divide,
+        risp_logical_shift_right, risp_multiply, risp_power, risp_remainder, risp_shift_left,
+        risp_shift_right, risp_subtract,
+    },
+    builtins::{builtin, Builtin},
+    comparison::risp_compare,
+    error::RuntimeError,
+    truthiness::{risp_require_boolean, risp_truthy},
+};
+
+// Functions in this module are called directly from generated code, so they
+// use the C calling convention and exchange values in their encoded form.
+//
+// Generated code can't unwind, so a runtime error is reported by storing it
+// here and returning `EncodedValue::RUNTIME_ERROR`. Every caller checks for
+// that value and returns it in turn, until it reaches `Function::call`, which
+// takes the error back out. The first caller to see the error gives it the
+// location of the code that raised it.
+thread_local! {
+    static PENDING_ERROR: RefCell<Option<(RuntimeError, Option<Location>)>> =
+        const { RefCell::new(None) };
+}
+
+pub fn raise(error: RuntimeError) -> u64 {
+    PENDING_ERROR.with(|pending| *pending.borrow_mut() = Some((error, None)));
+    EncodedValue::RUNTIME_ERROR
+}
+
+pub fn take_error() -> Option<(RuntimeError, Option<Location>)> {
+    PENDING_ERROR.with(|pending| pending.borrow_mut().take())
+}
+
+// Called by generated code when a call it made raised an error, with the
+// location of the call. Returns the error value to pass on.
+pub extern "C" fn risp_locate_error(location: *const Location) -> u64 {
+    PENDING_ERROR.with(|pending| {
+        if let Some((_, ref mut error_location @ None)) = *pending.borrow_mut() {
+            *error_location = Some(unsafe { (*location).clone() });
+        }
+    });
+    EncodedValue::RUNTIME_ERROR
+}
+
+// The lowest address the stack pointer may reach in generated code. Every
+// function compares against it on entry, and raises an error rather than
+// running into the end of the stack.
+thread_local! {
+    static STACK_LIMIT: Cell<u64> = const { Cell::new(0) };
+}
+
+// Where generated code finds the limit, which is only valid on this thread.
+pub fn stack_limit_address() -> *const u64 {
+    STACK_LIMIT.with(|limit| limit.as_ptr() as *const u64)
+}
+
+// Runs `f`, letting any generated code it calls use at most `max_size` bytes
+// of stack below the current stack pointer.
+pub fn with_stack_limit<T>(max_size: usize, f: impl FnOnce() -> T) -> T {
+    let marker = 0u64;
+    let base = std::hint::black_box(&marker) as *const u64 as u64;
+
+    let outer_limit = STACK_LIMIT.with(|limit| limit.replace(base.saturating_sub(max_size as u64)));
+    let result = f();
+    STACK_LIMIT.with(|limit| limit.set(outer_limit));
+
+    result
+}
+
+// Called by generated code on entry to a function when the stack has reached
+// its limit, with the name of the function.
+pub extern "C" fn risp_stack_overflow(name: *const Identifier) -> u64 {
+    raise(RuntimeError::StackOverflow(unsafe { (*name).clone() }))
+}
+
+fn encode(value: impl Into<EncodedValue>) -> u64 {
+    unsafe { value.into().encoded_value() }
+}
@@ -0,0 +1,146 @@
+use num_bigint::{BigInt, BigUint, Sign};
+
+use crate::{
+    parser::ArithmeticOperator,
+    value::{EncodedValue, Value},
+};
+
+use super::{encode, raise, RuntimeError};
+
+// Generated code handles arithmetic on two inline integers itself, and only
+// calls these when an operand is something else or the result overflows.
+
+pub extern "C" fn risp_add(lhs: u64, rhs: u64) -> u64 {
+    arithmetic(ArithmeticOperator::Add, lhs, rhs)
+}
+
+pub extern "C" fn risp_subtract(lhs: u64, rhs: u64) -> u64 {
+    arithmetic(ArithmeticOperator::Subtract, lhs, rhs)
+}
+
+pub extern "C" fn risp_multiply(lhs: u64, rhs: u64) -> u64 {
+    arithmetic(ArithmeticOperator::Multiply, lhs, rhs)
+}
+
+pub extern "C" fn risp_divide(lhs: u64, rhs: u64) -> u64 {
+    arithmetic(ArithmeticOperator::Divide, lhs, rhs)
+}
+
+pub extern "C" fn risp_remainder(lhs: u64, rhs: u64) -> u64 {
+    arithmetic(ArithmeticOperator::Remainder, lhs, rhs)
+}
+
+pub extern "C" fn risp_power(lhs: u64, rhs: u64) -> u64 {
+    arithmetic(ArithmeticOperator::Power, lhs, rhs)
+}
+
+pub extern "C" fn risp_bitwise_and(lhs: u64, rhs: u64) -> u64 {
+    arithmetic(ArithmeticOperator::BitwiseAnd, lhs, rhs)
+}
+
+pub extern "C" fn risp_bitwise_or(lhs: u64, rhs: u64) -> u64 {
+    arithmetic(ArithmeticOperator::BitwiseOr, lhs, rhs)
+}
+
+pub extern "C" fn risp_bitwise_xor(lhs: u64, rhs: u64) -> u64 {
+    arithmetic(ArithmeticOperator::BitwiseXor, lhs, rhs)
+}
+
+pub extern "C" fn risp_shift_left(lhs: u64, rhs: u64) -> u64 {
+    arithmetic(ArithmeticOperator::ShiftLeft, lhs, rhs)
+}
+
+pub extern "C" fn risp_shift_right(lhs: u64, rhs: u64) -> u64 {
+    arithmetic(ArithmeticOperator::ShiftRight, lhs, rhs)
+}
+
+pub extern "C" fn risp_logical_shift_right(lhs: u64, rhs: u64) -> u64 {
+    arithmetic(ArithmeticOperator::LogicalShiftRight, lhs, rhs)
+}
+
+// Shifting left by more than this would build an unreasonably large integer.
+const MAX_SHIFT_LEFT: usize = 1 << 16;
+
+// Likewise for powers with more bits than this.
+const MAX_POWER_BITS: u64 = 1 << 16;
+
+fn arithmetic(operator: ArithmeticOperator, lhs: u64, rhs: u64) -> u64 {
+    let lhs = unsafe { EncodedValue::from_encoded_value(lhs) };
+    let rhs = unsafe { EncodedValue::from_encoded_value(rhs) };
+
+    if let (ArithmeticOperator::Add, Some(lhs_string), Some(rhs_string)) =
+        (operator, lhs.as_str(), rhs.as_str())
+    {
+        return encode(Value::String(format!("{lhs_string}{rhs_string}")));
+    }
+
+    let (Some(lhs_value), Some(rhs_value)) = (lhs.to_big_integer(), rhs.to_big_integer()) else {
+        return raise(RuntimeError::TypeMismatch(
+            operator,
+            lhs.value_type().expect("invalid operand"),
+            rhs.value_type().expect("invalid operand"),
+        ));
+    };
+
+    let result = match operator {
+        ArithmeticOperator::Add => lhs_value + rhs_value,
+        ArithmeticOperator::Subtract => lhs_value - rhs_value,
+        ArithmeticOperator::Multiply => lhs_value * rhs_value,
+        ArithmeticOperator::Divide => {
+            if rhs_value.sign() == Sign::NoSign {
+                return raise(RuntimeError::DivisionByZero);
+            }
+
+            lhs_value / rhs_value
+        }
+        // Like division, this truncates towards zero, so the remainder takes
+        // the sign of the dividend.
+        ArithmeticOperator::Remainder => {
+            if rhs_value.sign() == Sign::NoSign {
+                return raise(RuntimeError::DivisionByZero);
+            }
+
+            lhs_value % rhs_value
+        }
+        // Only whole number powers are supported, so negative exponents are
+        // an error. Powers of 0, 1 and -1 never grow, however large the
+        // exponent.
+        ArithmeticOperator::Power => match u32::try_from(&rhs_value) {
+            Ok(exponent)
+                if lhs_value.magnitude() <= &BigUint::from(1u8)
+                    || lhs_value.bits() * u64::from(exponent) <= MAX_POWER_BITS =>
+            {
+                lhs_value.pow(exponent)
+            }
+            _ => return raise(RuntimeError::InvalidExponent(rhs_value)),
+        },
+        // Bitwise operations treat integers as two's complement, with as many
+        // sign bits as they need.
+        ArithmeticOperator::BitwiseAnd => lhs_value & rhs_value,
+        ArithmeticOperator::BitwiseOr => lhs_value | rhs_value,
+        ArithmeticOperator::BitwiseXor => lhs_value ^ rhs_value,
+        ArithmeticOperator::ShiftLeft => match usize::try_from(&rhs_value) {
+            Ok(amount) if amount <= MAX_SHIFT_LEFT => lhs_value << amount,
+            _ => return raise(RuntimeError::InvalidShiftAmount(rhs_value)),
+        },
+        ArithmeticOperator::ShiftRight | ArithmeticOperator::LogicalShiftRight => {
+            if rhs_value.sign() == Sign::Minus {
+                return raise(RuntimeError::InvalidShiftAmount(rhs_value));
+            }
+            let amount = usize::try_from(&rhs_value).unwrap_or(usize::MAX);
+
+            if operator == ArithmeticOperator::ShiftRight {
+                lhs_value >> amount
+            } else {
+                let bits = u64::try_from(lhs_value & BigInt::from(u64::MAX))
+                    .expect("masked integer doesn't fit in 64 bits");
+                let shifted = u32::try_from(amount)
+                    .ok()
+                    .and_then(|amount| bits.checked_shr(amount));
+                BigInt::from(shifted.unwrap_or(0))
+            }
+        }
+    };
+
+    encode(result)
+}
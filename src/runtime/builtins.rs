@@ -0,0 +1,123 @@
+use crate::{heap, value::Value};
+
+use super::{
+    encode,
+    format::{risp_format, risp_str},
+    string::{
+        risp_char_at, risp_index_of, risp_len, risp_lower, risp_split, risp_substring, risp_trim,
+        risp_upper,
+    },
+};
+
+// Functions provided by the runtime, which can be called from risp code by
+// name unless a function with the same name has been defined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Builtin {
+    pub name: &'static str,
+    pub arity: usize,
+    // Variadic builtins take at least `arity` arguments, which are passed as
+    // a pointer to an array of them and its length.
+    pub variadic: bool,
+    function: *const (),
+}
+
+impl Builtin {
+    pub fn address(&self) -> u64 {
+        self.function as u64
+    }
+
+    pub fn accepts_argument_count(&self, count: usize) -> bool {
+        if self.variadic {
+            count >= self.arity
+        } else {
+            count == self.arity
+        }
+    }
+}
+
+impl std::fmt::Display for Builtin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+const BUILTINS: &[Builtin] = &[
+    Builtin {
+        name: "gc",
+        arity: 0,
+        variadic: false,
+        function: risp_gc as *const (),
+    },
+    Builtin {
+        name: "len",
+        arity: 1,
+        variadic: false,
+        function: risp_len as *const (),
+    },
+    Builtin {
+        name: "substring",
+        arity: 3,
+        variadic: false,
+        function: risp_substring as *const (),
+    },
+    Builtin {
+        name: "char_at",
+        arity: 2,
+        variadic: false,
+        function: risp_char_at as *const (),
+    },
+    Builtin {
+        name: "index_of",
+        arity: 2,
+        variadic: false,
+        function: risp_index_of as *const (),
+    },
+    Builtin {
+        name: "split",
+        arity: 3,
+        variadic: false,
+        function: risp_split as *const (),
+    },
+    Builtin {
+        name: "trim",
+        arity: 1,
+        variadic: false,
+        function: risp_trim as *const (),
+    },
+    Builtin {
+        name: "upper",
+        arity: 1,
+        variadic: false,
+        function: risp_upper as *const (),
+    },
+    Builtin {
+        name: "lower",
+        arity: 1,
+        variadic: false,
+        function: risp_lower as *const (),
+    },
+    Builtin {
+        name: "str",
+        arity: 1,
+        variadic: false,
+        function: risp_str as *const (),
+    },
+    Builtin {
+        name: "format",
+        arity: 1,
+        variadic: true,
+        function: risp_format as *const (),
+    },
+];
+
+pub fn builtin(name: &str) -> Option<Builtin> {
+    BUILTINS
+        .iter()
+        .find(|builtin| builtin.name == name)
+        .copied()
+}
+
+// Runs a garbage collection, returning the number of objects freed.
+pub extern "C" fn risp_gc() -> u64 {
+    encode(Value::Integer(heap::collect() as i64))
+}
@@ -0,0 +1,28 @@
+use crate::value::{EncodedValue, Value};
+
+use super::{encode, raise, RuntimeError};
+
+// Compares two values that generated code can't compare itself, returning
+// -1, 0 or 1 as an encoded integer depending on their ordering. Strings are
+// compared by their contents.
+pub extern "C" fn risp_compare(lhs: u64, rhs: u64) -> u64 {
+    let lhs = unsafe { EncodedValue::from_encoded_value(lhs) };
+    let rhs = unsafe { EncodedValue::from_encoded_value(rhs) };
+
+    let ordering =
+        if let (Some(lhs_value), Some(rhs_value)) = (lhs.to_big_integer(), rhs.to_big_integer()) {
+            lhs_value.cmp(&rhs_value)
+        } else if let (Some(lhs_string), Some(rhs_string)) = (lhs.as_str(), rhs.as_str()) {
+            lhs_string.cmp(rhs_string)
+        } else {
+            let lhs_type = lhs.value_type().expect("invalid operand");
+            let rhs_type = rhs.value_type().expect("invalid operand");
+            if lhs_type != rhs_type {
+                return raise(RuntimeError::IncomparableTypes(lhs_type, rhs_type));
+            }
+
+            unsafe { lhs.encoded_value().cmp(&rhs.encoded_value()) }
+        };
+
+    encode(Value::Integer(ordering as i64))
+}
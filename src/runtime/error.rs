@@ -0,0 +1,26 @@
+use num_bigint::BigInt;
+
+use crate::{
+    parser::{ArithmeticOperator, Identifier},
+    value::ValueType,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RuntimeError {
+    TypeMismatch(ArithmeticOperator, ValueType, ValueType),
+    IncomparableTypes(ValueType, ValueType),
+    DivisionByZero,
+    InvalidShiftAmount(BigInt),
+    InvalidExponent(BigInt),
+    NotABoolean(ValueType),
+    // The name of the builtin, the type it expected and the type it was given.
+    InvalidArgument(&'static str, ValueType, ValueType),
+    IndexOutOfRange(i64, usize),
+    InvalidRange(usize, usize),
+    // The index given to `split` and the number of pieces there were.
+    PieceOutOfRange(i64, usize),
+    EmptySeparator,
+    InvalidFormat(String),
+    // The function that was called when the stack reached its limit.
+    StackOverflow(Identifier),
+}
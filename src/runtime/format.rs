@@ -0,0 +1,280 @@
+use std::{iter::Peekable, str::Chars};
+
+use num_bigint::BigInt;
+
+use crate::value::{EncodedValue, Value};
+
+use super::{encode, raise, RuntimeError};
+
+// Converts any value to a string.
+pub extern "C" fn risp_str(value: u64) -> u64 {
+    encode(Value::String(decode(value).to_string()))
+}
+
+// Formats its arguments into the template given as the first one. Each `{}`
+// in the template is replaced by the next argument, and `{{` and `}}` stand
+// for literal braces. A placeholder can also specify how to format its
+// argument, as `{:[[fill]align][0][width][.precision][type]}`:
+//
+// - `align` is `<`, `^` or `>`, and `fill` is the character to pad with.
+// - `0` pads numbers with zeros after their sign.
+// - `precision` is the minimum number of digits for integers, and the maximum
+//   number of characters for anything else.
+// - `type` is `x`, `X`, `o` or `b` to format integers in another base.
+pub extern "C" fn risp_format(arguments: *const u64, count: u64) -> u64 {
+    let arguments = unsafe { std::slice::from_raw_parts(arguments, count as usize) };
+    let arguments = arguments
+        .iter()
+        .map(|argument| decode(*argument))
+        .collect::<Vec<_>>();
+
+    let Some((Value::String(template), arguments)) = arguments.split_first() else {
+        return raise(RuntimeError::InvalidFormat(
+            "the template must be a string".to_owned(),
+        ));
+    };
+
+    match format(template, arguments) {
+        Ok(output) => encode(Value::String(output)),
+        Err(error) => raise(error),
+    }
+}
+
+fn decode(value: u64) -> Value {
+    let value = unsafe { EncodedValue::from_encoded_value(value) };
+    Value::try_from(value).expect("invalid argument")
+}
+
+fn format(template: &str, arguments: &[Value]) -> Result<String, RuntimeError> {
+    let mut output = String::new();
+    let mut arguments = arguments.iter();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                output.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                output.push('}');
+            }
+            '{' => {
+                let spec = placeholder(&mut chars)?;
+                let Some(argument) = arguments.next() else {
+                    return Err(invalid_format("not enough arguments for the template"));
+                };
+                output.push_str(&format_argument(argument, &spec)?);
+            }
+            '}' => return Err(invalid_format("unmatched '}' in the template")),
+            c => output.push(c),
+        }
+    }
+
+    if arguments.next().is_some() {
+        return Err(invalid_format("too many arguments for the template"));
+    }
+
+    Ok(output)
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct FormatSpec {
+    fill: Option<char>,
+    align: Option<char>,
+    zero_pad: bool,
+    width: usize,
+    precision: Option<usize>,
+    base: Option<char>,
+}
+
+// Parses the rest of a placeholder, after its opening brace.
+fn placeholder(chars: &mut Peekable<Chars>) -> Result<FormatSpec, RuntimeError> {
+    let mut contents = String::new();
+    loop {
+        match chars.next() {
+            Some('}') => break,
+            Some(c) => contents.push(c),
+            None => return Err(invalid_format("unterminated placeholder in the template")),
+        }
+    }
+
+    match contents.strip_prefix(':') {
+        Some(spec) => parse_spec(spec),
+        None if contents.is_empty() => Ok(FormatSpec::default()),
+        None => Err(invalid_format(&format!(
+            "invalid placeholder '{{{contents}}}'"
+        ))),
+    }
+}
+
+fn parse_spec(spec: &str) -> Result<FormatSpec, RuntimeError> {
+    let mut result = FormatSpec::default();
+    let mut chars = spec.chars().peekable();
+
+    let is_align = |c: char| matches!(c, '<' | '^' | '>');
+    let mut lookahead = spec.chars();
+    match (lookahead.next(), lookahead.next()) {
+        (Some(fill), Some(align)) if is_align(align) => {
+            result.fill = Some(fill);
+            result.align = Some(align);
+            chars.next();
+            chars.next();
+        }
+        (Some(align), _) if is_align(align) => {
+            result.align = Some(align);
+            chars.next();
+        }
+        _ => {}
+    }
+
+    if chars.peek() == Some(&'0') {
+        result.zero_pad = true;
+        chars.next();
+    }
+
+    result.width = number(&mut chars).unwrap_or(0);
+
+    if chars.peek() == Some(&'.') {
+        chars.next();
+        let Some(precision) = number(&mut chars) else {
+            return Err(invalid_format(&format!(
+                "missing precision in '{{:{spec}}}'"
+            )));
+        };
+        result.precision = Some(precision);
+    }
+
+    if let Some(base) = chars.next_if(|c| matches!(c, 'x' | 'X' | 'o' | 'b')) {
+        result.base = Some(base);
+    }
+
+    if chars.next().is_some() {
+        return Err(invalid_format(&format!(
+            "invalid format spec '{{:{spec}}}'"
+        )));
+    }
+
+    Ok(result)
+}
+
+fn number(chars: &mut Peekable<Chars>) -> Option<usize> {
+    let mut digits = String::new();
+    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+        digits.push(digit);
+    }
+    digits.parse().ok()
+}
+
+fn format_argument(argument: &Value, spec: &FormatSpec) -> Result<String, RuntimeError> {
+    let integer = match argument {
+        Value::Integer(value) => Some(BigInt::from(*value)),
+        Value::BigInteger(value) => Some(value.clone()),
+        _ => None,
+    };
+
+    let Some(integer) = integer else {
+        if let Some(base) = spec.base {
+            return Err(invalid_format(&format!(
+                "cannot format {} with '{}'",
+                argument, base
+            )));
+        }
+
+        let mut text = argument.to_string();
+        if let Some(precision) = spec.precision {
+            text = text.chars().take(precision).collect();
+        }
+
+        return Ok(pad(&text, spec, '<'));
+    };
+
+    let sign = if integer.sign() == num_bigint::Sign::Minus {
+        "-"
+    } else {
+        ""
+    };
+    let magnitude = integer.magnitude();
+    let mut digits = match spec.base {
+        Some('x') => format!("{:x}", magnitude),
+        Some('X') => format!("{:X}", magnitude),
+        Some('o') => format!("{:o}", magnitude),
+        Some('b') => format!("{:b}", magnitude),
+        _ => magnitude.to_string(),
+    };
+
+    let mut minimum_digits = spec.precision.unwrap_or(0);
+    if spec.zero_pad && spec.align.is_none() {
+        minimum_digits = minimum_digits.max(spec.width.saturating_sub(sign.len()));
+    }
+    if digits.len() < minimum_digits {
+        digits = "0".repeat(minimum_digits - digits.len()) + &digits;
+    }
+
+    Ok(pad(&format!("{sign}{digits}"), spec, '>'))
+}
+
+fn pad(text: &str, spec: &FormatSpec, default_align: char) -> String {
+    let length = text.chars().count();
+    if length >= spec.width {
+        return text.to_owned();
+    }
+
+    let padding = spec.width - length;
+    let (before, after) = match spec.align.unwrap_or(default_align) {
+        '<' => (0, padding),
+        '^' => (padding / 2, padding - padding / 2),
+        _ => (padding, 0),
+    };
+
+    let fill = spec.fill.unwrap_or(' ').to_string();
+    format!("{}{}{}", fill.repeat(before), text, fill.repeat(after))
+}
+
+fn invalid_format(message: &str) -> RuntimeError {
+    RuntimeError::InvalidFormat(message.to_owned())
+}
+
+#[test]
+fn test_format() {
+    let format = |template: &str, arguments: &[Value]| format(template, arguments).unwrap();
+
+    assert_eq!(
+        format(
+            "{} of {}",
+            &[Value::Integer(1), Value::String("two".to_owned())]
+        ),
+        "1 of two"
+    );
+    assert_eq!(format("{{{}}}", &[Value::Boolean(true)]), "{true}");
+    assert_eq!(format("[{:5}]", &[Value::Integer(42)]), "[   42]");
+    assert_eq!(format("[{:<5}]", &[Value::Integer(42)]), "[42   ]");
+    assert_eq!(
+        format("[{:*^7}]", &[Value::String("ab".to_owned())]),
+        "[**ab***]"
+    );
+    assert_eq!(format("{:05}", &[Value::Integer(-42)]), "-0042");
+    assert_eq!(format("{:.3}", &[Value::Integer(7)]), "007");
+    assert_eq!(format("{:.2}", &[Value::String("risp".to_owned())]), "ri");
+    assert_eq!(
+        format("{:x} {:X}", &[Value::Integer(255), Value::Integer(255)]),
+        "ff FF"
+    );
+    assert_eq!(format("{:08b}", &[Value::Integer(5)]), "00000101");
+    assert_eq!(format("{:o}", &[Value::Integer(-8)]), "-10");
+    assert_eq!(
+        format("{:x}", &[Value::BigInteger(BigInt::from(u64::MAX) + 1)]),
+        "10000000000000000"
+    );
+}
+
+#[test]
+fn test_format_errors() {
+    assert!(format("{}", &[]).is_err());
+    assert!(format("{}", &[Value::Integer(1), Value::Integer(2)]).is_err());
+    assert!(format("{", &[Value::Integer(1)]).is_err());
+    assert!(format("}", &[]).is_err());
+    assert!(format("{:x}", &[Value::String("a".to_owned())]).is_err());
+    assert!(format("{:q}", &[Value::Integer(1)]).is_err());
+}
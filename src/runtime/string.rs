@@ -0,0 +1,173 @@
+use crate::value::{EncodedValue, Value, ValueType};
+
+use super::{encode, raise, RuntimeError};
+
+// Builtins that operate on strings. Strings are immutable, so each of these
+// returns a new string rather than changing its argument. Lengths and indices
+// count characters rather than bytes.
+
+pub extern "C" fn risp_len(string: u64) -> u64 {
+    finish(len(decode(string)))
+}
+
+pub extern "C" fn risp_substring(string: u64, start: u64, end: u64) -> u64 {
+    finish(substring(decode(string), decode(start), decode(end)))
+}
+
+pub extern "C" fn risp_char_at(string: u64, index: u64) -> u64 {
+    finish(char_at(decode(string), decode(index)))
+}
+
+pub extern "C" fn risp_index_of(string: u64, needle: u64) -> u64 {
+    finish(index_of(decode(string), decode(needle)))
+}
+
+pub extern "C" fn risp_split(string: u64, separator: u64, index: u64) -> u64 {
+    finish(split(decode(string), decode(separator), decode(index)))
+}
+
+pub extern "C" fn risp_trim(string: u64) -> u64 {
+    let string = decode(string);
+    finish(string_argument("trim", &string).map(|s| Value::String(s.trim().to_owned())))
+}
+
+pub extern "C" fn risp_upper(string: u64) -> u64 {
+    let string = decode(string);
+    finish(string_argument("upper", &string).map(|s| Value::String(s.to_uppercase())))
+}
+
+pub extern "C" fn risp_lower(string: u64) -> u64 {
+    let string = decode(string);
+    finish(string_argument("lower", &string).map(|s| Value::String(s.to_lowercase())))
+}
+
+fn len(string: EncodedValue) -> Result<Value, RuntimeError> {
+    let string = string_argument("len", &string)?;
+    Ok(Value::Integer(string.chars().count() as i64))
+}
+
+fn substring(
+    string: EncodedValue,
+    start: EncodedValue,
+    end: EncodedValue,
+) -> Result<Value, RuntimeError> {
+    let string = string_argument("substring", &string)?;
+    let length = string.chars().count();
+    let start = index_argument("substring", &start, length)?;
+    let end = index_argument("substring", &end, length)?;
+
+    if start > end {
+        return Err(RuntimeError::InvalidRange(start, end));
+    }
+
+    Ok(Value::String(
+        string.chars().skip(start).take(end - start).collect(),
+    ))
+}
+
+// The character at `index` as a string of its own, which is what indexing a
+// string with `s[i]` does.
+fn char_at(string: EncodedValue, index: EncodedValue) -> Result<Value, RuntimeError> {
+    let string = string_argument("char_at", &string)?;
+    let length = string.chars().count();
+    let index = index_argument("char_at", &index, length)?;
+
+    match string.chars().nth(index) {
+        Some(c) => Ok(Value::String(c.to_string())),
+        None => Err(RuntimeError::IndexOutOfRange(index as i64, length)),
+    }
+}
+
+fn index_of(string: EncodedValue, needle: EncodedValue) -> Result<Value, RuntimeError> {
+    let string = string_argument("index_of", &string)?;
+    let needle = string_argument("index_of", &needle)?;
+
+    let index = match string.find(needle) {
+        Some(byte_index) => string[..byte_index].chars().count() as i64,
+        None => -1,
+    };
+
+    Ok(Value::Integer(index))
+}
+
+// The piece at `index` of `string` split at each `separator`, as there's no
+// list type to return all of the pieces in. `split("a,b", ",", 1)` is "b".
+fn split(
+    string: EncodedValue,
+    separator: EncodedValue,
+    index: EncodedValue,
+) -> Result<Value, RuntimeError> {
+    let string = string_argument("split", &string)?;
+    let separator = string_argument("split", &separator)?;
+    if separator.is_empty() {
+        return Err(RuntimeError::EmptySeparator);
+    }
+
+    let pieces = string.split(separator).collect::<Vec<_>>();
+    let Some(index) = index.to_big_integer() else {
+        return Err(RuntimeError::InvalidArgument(
+            "split",
+            ValueType::Integer,
+            index.value_type().expect("invalid argument"),
+        ));
+    };
+
+    match usize::try_from(&index)
+        .ok()
+        .and_then(|index| pieces.get(index))
+    {
+        Some(piece) => Ok(Value::String(piece.to_string())),
+        None => Err(RuntimeError::PieceOutOfRange(
+            i64::try_from(&index).unwrap_or(i64::MAX),
+            pieces.len(),
+        )),
+    }
+}
+
+fn decode(value: u64) -> EncodedValue {
+    unsafe { EncodedValue::from_encoded_value(value) }
+}
+
+fn finish(result: Result<Value, RuntimeError>) -> u64 {
+    match result {
+        Ok(value) => encode(value),
+        Err(error) => raise(error),
+    }
+}
+
+fn string_argument<'a>(
+    function: &'static str,
+    value: &'a EncodedValue,
+) -> Result<&'a str, RuntimeError> {
+    value.as_str().ok_or_else(|| {
+        RuntimeError::InvalidArgument(
+            function,
+            ValueType::String,
+            value.value_type().expect("invalid argument"),
+        )
+    })
+}
+
+// An index into a string of `length` characters, which may point just past
+// the last character.
+fn index_argument(
+    function: &'static str,
+    value: &EncodedValue,
+    length: usize,
+) -> Result<usize, RuntimeError> {
+    let Some(index) = value.to_big_integer() else {
+        return Err(RuntimeError::InvalidArgument(
+            function,
+            ValueType::Integer,
+            value.value_type().expect("invalid argument"),
+        ));
+    };
+
+    match usize::try_from(&index) {
+        Ok(index) if index <= length => Ok(index),
+        _ => Err(RuntimeError::IndexOutOfRange(
+            i64::try_from(&index).unwrap_or(i64::MAX),
+            length,
+        )),
+    }
+}
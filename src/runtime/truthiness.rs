@@ -0,0 +1,29 @@
+use crate::value::{EncodedValue, Value, ValueType};
+
+use super::{encode, raise, RuntimeError};
+
+// Decides whether a value that isn't a boolean or an inline integer counts as
+// true, returning an encoded boolean. Empty strings are false, and every big
+// integer is true since none of them are zero.
+pub extern "C" fn risp_truthy(value: u64) -> u64 {
+    let value = unsafe { EncodedValue::from_encoded_value(value) };
+
+    let truthy = match value.value_type().expect("invalid operand") {
+        ValueType::Integer => value.to_big_integer() != Some(0.into()),
+        ValueType::String => !value.as_str().expect("invalid string").is_empty(),
+        ValueType::Boolean => value.is_true(),
+        ValueType::BigInteger => true,
+    };
+
+    encode(Value::Boolean(truthy))
+}
+
+// Called in strict mode when a condition isn't a boolean.
+pub extern "C" fn risp_require_boolean(value: u64) -> u64 {
+    let value = unsafe { EncodedValue::from_encoded_value(value) };
+
+    match value.value_type().expect("invalid operand") {
+        ValueType::Boolean => encode(Value::Boolean(value.is_true())),
+        value_type => raise(RuntimeError::NotABoolean(value_type)),
+    }
+}
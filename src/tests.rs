@@ -6,7 +6,7 @@ pub fn parse_test<T: std::fmt::Debug + PartialEq>(
 ) {
     use crate::parser::Span;
 
-    let input = Span::new(input);
+    let input = Span::new_extra(input, None);
     let actual = parser(input);
 
     match actual {
@@ -18,9 +18,25 @@ pub fn parse_test<T: std::fmt::Debug + PartialEq>(
     }
 }
 
+// Wraps a node to compare with parsed ones. Locations aren't compared, so
+// it doesn't matter that this one is made up.
+#[cfg(test)]
+pub fn node<T>(value: T) -> crate::parser::Located<T> {
+    use crate::parser::{Located, Location, Span};
+
+    Located::new(Location::new(Span::new_extra("", None), 0), value)
+}
+
 #[cfg(test)]
 mod test {
-    use crate::{evaluator::Evaluator, value::Value};
+    use crate::{
+        compiler::{CompileOptions, CompilerError},
+        evaluator::{EvaluationError, Evaluator},
+        heap,
+        parser::ArithmeticOperator,
+        runtime::RuntimeError,
+        value::{Value, ValueType},
+    };
 
     fn eval(code: &str) -> Value {
         Evaluator::default()
@@ -28,6 +44,13 @@ mod test {
             .expect("evaluation failed")
     }
 
+    fn eval_runtime_error(code: &str) -> RuntimeError {
+        match Evaluator::default().evaluate(code) {
+            Err(EvaluationError::RuntimeError(error, _)) => error,
+            result => panic!("expected a runtime error but got {:?}", result),
+        }
+    }
+
     #[test]
     fn test_arithmetic() {
         assert_eq!(eval("55 + 42"), Value::Integer(97));
@@ -36,12 +59,184 @@ mod test {
         assert_eq!(eval("2*3+3*4"), Value::Integer(18));
     }
 
+    #[test]
+    fn test_negative_numbers() {
+        assert_eq!(eval("1 - 2"), Value::Integer(-1));
+        assert_eq!(eval("-5 * 3"), Value::Integer(-15));
+        assert_eq!(eval("-4 * -4"), Value::Integer(16));
+        assert_eq!(eval("1 - -2"), Value::Integer(3));
+        assert_eq!(eval("-2 ** 2"), Value::Integer(-4));
+        assert_eq!(eval("def f(x) { -x } f(5) + -f(3)"), Value::Integer(-2));
+
+        // Negating the smallest integer overflows into a big integer.
+        assert_eq!(
+            eval("-(-9223372036854775807 - 1)"),
+            Value::BigInteger("9223372036854775808".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_big_integers() {
+        assert_eq!(
+            eval("1152921504606846975 + 1"),
+            Value::Integer(1152921504606846976)
+        );
+        assert_eq!(
+            eval("-1152921504606846975 - 2"),
+            Value::Integer(-1152921504606846977)
+        );
+        assert_eq!(
+            eval("1000000000 * 1000000000 * 1000000000"),
+            Value::BigInteger("1000000000000000000000000000".parse().unwrap())
+        );
+        assert_eq!(
+            eval("def square(x) { x * x } square(square(square(2000000000)))"),
+            Value::BigInteger(
+                "256000000000000000000000000000000000000000000000000000000000000000000000000"
+                    .parse()
+                    .unwrap()
+            )
+        );
+
+        // results that fit inline again go back to the fast path
+        assert_eq!(
+            eval("1000000000 * 1000000000 * 1000000000 / 1000000000 / 1000000000 - 1"),
+            Value::Integer(999999999)
+        );
+    }
+
+    #[test]
+    fn test_division() {
+        assert_eq!(eval("84 / 2"), Value::Integer(42));
+        assert_eq!(eval("7 / 2"), Value::Integer(3));
+        assert_eq!(eval("-7 / 2"), Value::Integer(-3));
+        assert_eq!(eval_runtime_error("1 / 0"), RuntimeError::DivisionByZero);
+    }
+
+    #[test]
+    fn test_type_errors() {
+        assert_eq!(
+            eval_runtime_error("\"a\" + 1"),
+            RuntimeError::TypeMismatch(
+                ArithmeticOperator::Add,
+                ValueType::String,
+                ValueType::Integer
+            )
+        );
+        assert_eq!(
+            eval_runtime_error("def f(x) { 2 * x } f(\"a\")"),
+            RuntimeError::TypeMismatch(
+                ArithmeticOperator::Multiply,
+                ValueType::Integer,
+                ValueType::String
+            )
+        );
+        assert_eq!(
+            eval_runtime_error("if \"a\" < 1 { 1 } else { 2 }"),
+            RuntimeError::IncomparableTypes(ValueType::String, ValueType::Integer)
+        );
+
+        let error = Evaluator::default().evaluate("\"a\" + 1").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "cannot add string and integer at line 1, column 1"
+        );
+    }
+
+    #[test]
+    fn test_big_integer_comparison() {
+        assert_eq!(
+            eval("if 1000000000 * 1000000000 * 1000000000 > 5 { 1 } else { 2 }"),
+            Value::Integer(1)
+        );
+        assert_eq!(
+            eval("if -1000000000 * 1000000000 * 1000000000 > 5 { 1 } else { 2 }"),
+            Value::Integer(2)
+        );
+        assert_eq!(
+            eval("if 1000000000 * 1000000000 * 1000000000 == 1000000000000000000 * 1000000000 { 1 } else { 2 }"),
+            Value::Integer(1)
+        );
+    }
+
     #[test]
     fn test_bracketed_expressions() {
         assert_eq!(eval("1+(2*3)"), Value::Integer(7));
         assert_eq!(eval("(2*3)+(3*4)"), Value::Integer(18));
     }
 
+    #[test]
+    fn test_long_expression() {
+        // More code than fits in a single page of memory.
+        let code = vec!["1"; 100].join(" + ");
+        assert_eq!(eval(&code), Value::Integer(100));
+    }
+
+    #[test]
+    fn test_register_pressure() {
+        assert_eq!(eval("(1+(2+(3+(4+5))))"), Value::Integer(15));
+        assert_eq!(
+            eval("((1+2)*(3+4)) + (((5+6)*(7+8)) + (((9+10)*(11+12)) + ((13+14)*(15+16))))"),
+            Value::Integer(1460)
+        );
+        assert_eq!(
+            eval("def f(a,b,c,d) { a+b+c+d } f(1+2, 3+4, 5+6, 7+8)"),
+            Value::Integer(36)
+        );
+        assert_eq!(
+            eval(
+                "def f(a, b) { \"a=${a} b=${b} s=${a + b} d=${a - b}\" }
+                f(7, 3)"
+            ),
+            Value::String("a=7 b=3 s=10 d=4".to_owned())
+        );
+
+        // Six arguments leave few registers to spare.
+        assert_eq!(
+            eval(
+                "def f(a, b, c, d, e, g) { if a == 0 { g } else { 1 + f(a-1, b, c, d, e, g+1) } }
+                f(10, 1, 2, 3, 4, 0)"
+            ),
+            Value::Integer(20)
+        );
+        assert_eq!(
+            eval(
+                "def f(a, b, c, d, e, g) { (a*b)+((c*d)+((e*g)+((a*g)+(b*c)))) }
+                f(1, 2, 3, 4, 5, 6)"
+            ),
+            Value::Integer(56)
+        );
+        assert_eq!(
+            eval(
+                "def g(a, b, c, d, e, h) { a+b+c+d+e+h }
+                def f(a, b, c, d, e, h) {
+                    g(a*1, b*2, c*3, d*4, e*5, g(a+1, b+1, c+1, d+1, e+1, h+1))
+                }
+                f(1, 1, 1, 1, 1, 1)"
+            ),
+            Value::Integer(27)
+        );
+        assert_eq!(
+            eval(
+                "def f(a, b, c, d, e, g) {
+                    if a < b {
+                        if c < d { (a*b)+((c*d)+(e*g)) } else { 1 }
+                    } else {
+                        if b < c { 2 } else { if e < g { (a*c)+((b*d)+(e*e)) } else { 3 } }
+                    }
+                }
+                f(1, 2, 3, 4, 5, 6) + f(2, 1, 3, 4, 5, 6) + f(3, 2, 1, 4, 5, 6) * 100"
+            ),
+            Value::Integer(3646)
+        );
+
+        // A branch's value is moved into place even if it's an argument.
+        assert_eq!(
+            eval("def f(a) { if a > 5 { a } else { 0 } } f(10)"),
+            Value::Integer(10)
+        );
+    }
+
     #[test]
     fn test_function_evaluation() {
         assert_eq!(
@@ -71,6 +266,28 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_string_returned_repeatedly() {
+        let mut evaluator = Evaluator::default();
+        evaluator
+            .evaluate("def greeting() { \"hello\" }")
+            .expect("evaluation failed");
+
+        for _ in 0..3 {
+            assert_eq!(
+                evaluator.evaluate("greeting()").expect("evaluation failed"),
+                Value::String("hello".to_string())
+            );
+        }
+
+        assert_eq!(
+            evaluator
+                .evaluate("def echo(x) { x } echo(echo(greeting()))")
+                .expect("evaluation failed"),
+            Value::String("hello".to_string())
+        );
+    }
+
     #[test]
     fn test_let() {
         assert_eq!(
@@ -79,6 +296,25 @@ mod test {
         )
     }
 
+    #[test]
+    fn test_let_between_inputs() {
+        // Variables don't outlive the input they're declared in.
+        let mut evaluator = Evaluator::default();
+        evaluator.evaluate("let x = 1\nlet s = \"hi\"").unwrap();
+        for code in ["x", "upper(s)"] {
+            assert!(matches!(
+                evaluator.evaluate(code),
+                Err(EvaluationError::CompilerError(CompilerError::CompileError(
+                    ..
+                )))
+            ));
+        }
+        assert_eq!(
+            evaluator.evaluate("let x = 2\nx").unwrap(),
+            Value::Integer(2)
+        );
+    }
+
     #[test]
     fn test_if() {
         assert_eq!(
@@ -198,6 +434,88 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_compound_assignment() {
+        assert_eq!(
+            eval(
+                "
+            def gcd(x, y) {
+                while x != y {
+                    if x > y {
+                        x -= y
+                    } else {
+                        y -= x
+                    }
+                }
+                x
+            } gcd(1071, 462)"
+            ),
+            Value::Integer(21)
+        );
+        assert_eq!(
+            eval(
+                "
+            def update(x) {
+                let y = 3
+                y *= x + 1
+                y += 10
+                y -= 1
+                y /= 2
+                y %= 7
+                y <<= 4
+                y |= 3
+                y &= 61
+                y ^= 8
+                y >>= 1
+                y
+            } update(4)"
+            ),
+            Value::Integer(12)
+        );
+        assert_eq!(
+            eval("def neg(x) { x >>>= 60\n x } neg(-16)"),
+            Value::Integer(15)
+        );
+        assert_eq!(
+            eval("def greet(name) { let s = \"hello \"\n s += name\n s } greet(\"risp\")"),
+            Value::String("hello risp".into())
+        );
+        assert_eq!(
+            eval_runtime_error("def f(x) { x %= 0\n x } f(1)"),
+            RuntimeError::DivisionByZero
+        );
+    }
+
+    #[test]
+    fn test_assignment_expressions() {
+        assert_eq!(
+            eval("let x = 1\nlet y = 2\nx = y = 5\nx + y"),
+            Value::Integer(10)
+        );
+        assert_eq!(eval("let x = 7\nlet y = x += 2\nx + y"), Value::Integer(18));
+        assert_eq!(eval("let a = 2\na **= a **= 2\na"), Value::Integer(16));
+        assert_eq!(eval("let x = 1\nx + (x = 5)"), Value::Integer(6));
+        assert_eq!(eval("let x = 1\n(x = 5) + x"), Value::Integer(10));
+        assert_eq!(eval("def f(a) { a + (a = 5) } f(1)"), Value::Integer(6));
+        assert_eq!(
+            eval("def f(a, b) { a - b } let x = 3\nf(x, x = 1) + x"),
+            Value::Integer(3)
+        );
+        assert_eq!(eval("let x = 1\nx < (x = 5)"), Value::Boolean(true));
+    }
+
+    #[test]
+    fn test_remainder() {
+        assert_eq!(eval("17 % 5"), Value::Integer(2));
+        assert_eq!(eval("-17 % 5"), Value::Integer(-2));
+        assert_eq!(eval("2 + 7 % 4 * 2"), Value::Integer(8));
+        assert_eq!(
+            eval("def f(x) { x * x * x % 1000000007 } f(2000000000)"),
+            Value::Integer(999997263)
+        );
+        assert_eq!(eval_runtime_error("1 % 0"), RuntimeError::DivisionByZero);
+    }
+
     #[test]
     fn test_while_loop() {
         assert_eq!(
@@ -245,4 +563,792 @@ mod test {
             Value::String("pass".into())
         )
     }
+
+    #[test]
+    fn test_truthiness() {
+        let truthiness = |value: &str| {
+            eval(&format!(
+                "def test(x) {{ if x {{ 1 }} else {{ 0 }} }} test({value})"
+            ))
+        };
+
+        assert_eq!(truthiness("false"), Value::Integer(0));
+        assert_eq!(truthiness("true"), Value::Integer(1));
+        assert_eq!(truthiness("0"), Value::Integer(0));
+        assert_eq!(truthiness("7"), Value::Integer(1));
+        assert_eq!(truthiness("\"\""), Value::Integer(0));
+        assert_eq!(truthiness("\"risp\""), Value::Integer(1));
+        assert_eq!(
+            truthiness("2000000000 * 2000000000 * 2000000000"),
+            Value::Integer(1)
+        );
+    }
+
+    #[test]
+    fn test_logical_operators() {
+        assert_eq!(eval("true && false"), Value::Boolean(false));
+        assert_eq!(eval("false || true"), Value::Boolean(true));
+        assert_eq!(eval("!false"), Value::Boolean(true));
+        assert_eq!(eval("!\"\""), Value::Boolean(true));
+        assert_eq!(eval("1 < 2 && 2 < 3"), Value::Boolean(true));
+        assert_eq!(eval("1 > 2 || 3 < 2"), Value::Boolean(false));
+
+        // The right hand side isn't evaluated if the left decides the result.
+        assert_eq!(eval("false && 1 / 0"), Value::Boolean(false));
+        assert_eq!(eval("true || 1 / 0"), Value::Boolean(true));
+
+        assert_eq!(
+            eval(
+                "
+            def between(x, low, high) {
+                if !(x < low || x > high) { 1 } else { 0 }
+            }
+            between(5, 1, 10) * 10 + between(11, 1, 10)"
+            ),
+            Value::Integer(10)
+        );
+    }
+
+    #[test]
+    fn test_strict_conditions() {
+        let options = CompileOptions {
+            strict_conditions: true,
+            ..CompileOptions::default()
+        };
+        let strict_eval = |code| Evaluator::with_options(options).evaluate(code);
+
+        assert_eq!(
+            strict_eval("def test(x) { if x { 1 } else { 0 } } test(true)").unwrap(),
+            Value::Integer(1)
+        );
+        assert_eq!(
+            strict_eval("def test(x) { if x { 1 } else { 0 } } test(false)").unwrap(),
+            Value::Integer(0)
+        );
+        assert!(matches!(
+            strict_eval("def test(x) { if x { 1 } else { 0 } } test(1)"),
+            Err(EvaluationError::RuntimeError(
+                RuntimeError::NotABoolean(ValueType::Integer),
+                _
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_garbage_collection() {
+        let big = "2000000000 * 2000000000 * 2000000000";
+        let mut evaluator = Evaluator::default();
+
+        // Each iteration leaves behind big integers that nothing refers to.
+        evaluator
+            .evaluate(&format!(
+                "def churn(n) {{
+                    while n > 0 {{
+                        let x = {big}
+                        n = n - 1
+                    }}
+                    gc()
+                }}"
+            ))
+            .expect("evaluation failed");
+
+        let freed = evaluator.evaluate("churn(10)").expect("evaluation failed");
+        assert!(matches!(freed, Value::Integer(freed) if freed > 0));
+        assert!(heap::stats().collections > 0);
+
+        // Values that are still in use survive a collection.
+        assert_eq!(
+            eval(&format!(
+                "def keep() {{
+                    let x = {big}
+                    gc()
+                    x
+                }}
+                keep()"
+            )),
+            eval(big)
+        );
+
+        // Enough garbage triggers a collection without calling `gc`.
+        let collections = heap::stats().collections;
+        evaluator
+            .evaluate(&format!(
+                "def churn_without_gc(n) {{
+                    while n > 0 {{
+                        let x = {big}
+                        n = n - 1
+                    }}
+                    0
+                }}
+                churn_without_gc(2000)"
+            ))
+            .expect("evaluation failed");
+        assert!(heap::stats().collections > collections);
+    }
+
+    #[test]
+    fn test_string_concatenation() {
+        assert_eq!(
+            eval("\"Hello\" + \" \" + \"world\""),
+            Value::String("Hello world".to_string())
+        );
+        assert_eq!(
+            eval("def greet(name) { \"Hello \" + name } greet(\"risp\")"),
+            Value::String("Hello risp".to_string())
+        );
+        assert_eq!(
+            eval_runtime_error("\"a\" + 1"),
+            RuntimeError::TypeMismatch(
+                ArithmeticOperator::Add,
+                ValueType::String,
+                ValueType::Integer
+            )
+        );
+    }
+
+    #[test]
+    fn test_string_comparison() {
+        assert_eq!(eval("\"a\" == \"a\""), Value::Boolean(true));
+        assert_eq!(eval("\"a\" + \"b\" == \"ab\""), Value::Boolean(true));
+        assert_eq!(eval("\"a\" != \"b\""), Value::Boolean(true));
+        assert_eq!(eval("\"apple\" < \"banana\""), Value::Boolean(true));
+        assert_eq!(eval("\"b\" <= \"a\""), Value::Boolean(false));
+    }
+
+    #[test]
+    fn test_string_builtins() {
+        assert_eq!(eval("len(\"hello\")"), Value::Integer(5));
+        assert_eq!(eval("len(\"\")"), Value::Integer(0));
+        assert_eq!(eval("len(\"héllo\\n\")"), Value::Integer(6));
+        assert_eq!(
+            eval("substring(\"hello world\", 6, 11)"),
+            Value::String("world".to_string())
+        );
+        assert_eq!(eval("index_of(\"hello\", \"llo\")"), Value::Integer(2));
+        assert_eq!(eval("index_of(\"hello\", \"z\")"), Value::Integer(-1));
+        assert_eq!(
+            eval("trim(\"  hello  \")"),
+            Value::String("hello".to_string())
+        );
+        assert_eq!(eval("upper(\"risp\")"), Value::String("RISP".to_string()));
+        assert_eq!(eval("lower(\"RISP\")"), Value::String("risp".to_string()));
+
+        assert_eq!(
+            eval_runtime_error("len(5)"),
+            RuntimeError::InvalidArgument("len", ValueType::String, ValueType::Integer)
+        );
+        assert_eq!(
+            eval_runtime_error("substring(\"hello\", 2, 10)"),
+            RuntimeError::IndexOutOfRange(10, 5)
+        );
+        assert_eq!(
+            eval_runtime_error("substring(\"hello\", 3, 2)"),
+            RuntimeError::InvalidRange(3, 2)
+        );
+        assert_eq!(
+            eval("split(\"a, b, c\", \", \", 1)"),
+            Value::String("b".to_string())
+        );
+        assert_eq!(
+            eval("split(\"a,,b\", \",\", 1) + split(\"abc\", \"-\", 0)"),
+            Value::String("abc".to_string())
+        );
+        assert_eq!(
+            eval_runtime_error("split(\"a,b\", \",\", 2)"),
+            RuntimeError::PieceOutOfRange(2, 2)
+        );
+        assert_eq!(
+            eval_runtime_error("split(\"a,b\", \",\", -1)"),
+            RuntimeError::PieceOutOfRange(-1, 2)
+        );
+        assert_eq!(
+            eval_runtime_error("split(\"a,b\", \"\", 0)"),
+            RuntimeError::EmptySeparator
+        );
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        assert_eq!(
+            eval("def describe(x, y) { \"x is ${x} and y is ${y * 2}\" } describe(1, 21)"),
+            Value::String("x is 1 and y is 42".to_string())
+        );
+        assert_eq!(
+            eval("def twice(s) { s + s } \"${twice(\"ab\")}${1 < 2}\""),
+            Value::String("ababtrue".to_string())
+        );
+        assert_eq!(eval("\"${1 + 2}\""), Value::String("3".to_string()));
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(
+            eval("format(\"{} of {}\", 1, \"two\")"),
+            Value::String("1 of two".to_string())
+        );
+        assert_eq!(
+            eval("def hex(x) { format(\"{:08x}\", x) } hex(48879)"),
+            Value::String("0000beef".to_string())
+        );
+        assert_eq!(
+            eval("format(\"{:b} {:^7} {:.2}\", 5, true, \"risp\")"),
+            Value::String("101  true   ri".to_string())
+        );
+        assert!(matches!(
+            eval_runtime_error("format(\"{} {}\", 1)"),
+            RuntimeError::InvalidFormat(_)
+        ));
+    }
+
+    #[test]
+    fn test_integer_literals() {
+        assert_eq!(eval("0xFF + 0b1 + 0o10 + 1_000"), Value::Integer(1264));
+
+        let error = Evaluator::default()
+            .evaluate("1 + 99999999999999999999")
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("integer literal is out of range at line 1, column 5\n"));
+    }
+
+    #[test]
+    fn test_bitwise_operators() {
+        assert_eq!(eval("12 & 10"), Value::Integer(8));
+        assert_eq!(eval("12 | 3"), Value::Integer(15));
+        assert_eq!(eval("12 ^ 10"), Value::Integer(6));
+        assert_eq!(eval("~5"), Value::Integer(-6));
+        assert_eq!(eval("~-1"), Value::Integer(0));
+        assert_eq!(eval("1 << 4"), Value::Integer(16));
+        assert_eq!(eval("-16 >> 2"), Value::Integer(-4));
+        assert_eq!(eval("-16 >>> 60"), Value::Integer(15));
+        assert_eq!(eval("1 | 2 == 3"), Value::Boolean(true));
+        assert_eq!(eval("1 + 1 << 2"), Value::Integer(8));
+        assert_eq!(eval("1 << 2 < 4"), Value::Boolean(false));
+
+        // Results keep their tag, so they can be used like any other integer.
+        assert_eq!(
+            eval("def flags(a, b) { let all = a | b\n all & ~b } flags(6, 2) + 1"),
+            Value::Integer(5)
+        );
+
+        // Shifts past the inline range promote to big integers.
+        assert_eq!(eval("(1 << 64) >> 64"), Value::Integer(1));
+        assert_eq!(
+            eval("1 << 70"),
+            Value::BigInteger(num_bigint::BigInt::from(1) << 70)
+        );
+
+        assert_eq!(
+            eval_runtime_error("def shift(x, amount) { x << amount } shift(1, -1)"),
+            RuntimeError::InvalidShiftAmount((-1).into())
+        );
+        assert_eq!(
+            eval_runtime_error("\"a\" & 1"),
+            RuntimeError::TypeMismatch(
+                ArithmeticOperator::BitwiseAnd,
+                ValueType::String,
+                ValueType::Integer
+            )
+        );
+    }
+
+    #[test]
+    fn test_comments() {
+        assert_eq!(
+            eval(
+                "
+            # Greatest common divisor, by repeated subtraction.
+            def gcd(x, y) {
+                while x != y { // until they meet
+                    if x > y {
+                        x -= y /* the larger one /* always */ shrinks */
+                    } else {
+                        y -= x
+                    }
+                }
+                x
+            }
+            gcd(1071, /* and */ 462) // 21
+            "
+            ),
+            Value::Integer(21)
+        );
+        assert_eq!(
+            eval("\"# not // a /* comment */\""),
+            Value::String("# not // a /* comment */".into())
+        );
+        assert_eq!(eval("6 /* times */ * 7"), Value::Integer(42));
+        assert_eq!(eval("84 / 2 // halved"), Value::Integer(42));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |code| Evaluator::default().evaluate(code).unwrap_err().to_string();
+
+        assert_eq!(
+            error("def f(x) {\n  x + 1"),
+            "expected `}` at line 2, column 8\n  |\n2 |   x + 1\n  |        ^"
+        );
+        assert_eq!(
+            error("def f(x { x }"),
+            "expected `)` at line 1, column 8\n  |\n1 | def f(x { x }\n  |        ^"
+        );
+        assert!(error("let = 5").starts_with("expected an identifier at line 1, column 5\n"));
+        assert!(error("x = \"abc").starts_with("unterminated string literal at line 1, column 5\n"));
+        assert!(error("1 /* 2").starts_with("unterminated block comment at line 1, column 3\n"));
+        assert!(error("1 +").starts_with("expected an expression at line 1, column 4\n"));
+        assert!(
+            error("def f(a) { a + }").starts_with("expected an expression at line 1, column 16\n")
+        );
+        assert!(error("x = 1 *\n\n").starts_with("expected an expression at line 3, column 1\n"));
+        assert!(error("!").starts_with("expected an expression at line 1, column 2\n"));
+        assert!(error("\"${}\"").starts_with("invalid string interpolation at line 1, column 2\n"));
+        assert!(error("x = \"a ${1 +}\"")
+            .starts_with("invalid string interpolation at line 1, column 8\n"));
+    }
+
+    #[test]
+    fn test_unparsed_input() {
+        let error = |code| Evaluator::default().evaluate(code).unwrap_err().to_string();
+
+        assert_eq!(
+            error("def f(x) { x }\nf(1) } f(2)"),
+            "unexpected input at line 2, column 6\n  |\n2 | f(1) } f(2)\n  |      ^"
+        );
+        assert!(error("1 @ 2").starts_with("expected `;` or a new line at line 1, column 3\n"));
+        assert!(error("if 1 { 2 } else").starts_with("unexpected input at line 1, column 12\n"));
+
+        // Nothing before the error is evaluated.
+        let mut evaluator = Evaluator::default();
+        assert!(evaluator.evaluate("def f(x) { x }\n)").is_err());
+        assert!(matches!(
+            evaluator.evaluate("f(1)"),
+            Err(EvaluationError::CompilerError(_))
+        ));
+    }
+
+    #[test]
+    fn test_error_locations() {
+        let location = |code| match Evaluator::default().evaluate(code) {
+            Err(EvaluationError::RuntimeError(_, Some(location))) => {
+                (location.line, location.column, location.length)
+            }
+            Err(EvaluationError::CompilerError(
+                CompilerError::CompileError(_, Some(location))
+                | CompilerError::CodegenError(_, Some(location)),
+            )) => (location.line, location.column, location.length),
+            result => panic!("expected an error with a location but got {:?}", result),
+        };
+
+        // Runtime errors point at the code that raised them, even inside a
+        // function.
+        assert_eq!(location("1 + len(2 * \"a\")"), (1, 9, 7));
+        assert_eq!(location("def f(x) {\n  x % 2\n}\nf(\"a\")"), (2, 3, 5));
+        assert_eq!(location("if 1 < \"a\" { 1 } else { 2 }"), (1, 4, 7));
+        assert_eq!(location("~\"a\""), (1, 1, 4));
+        assert_eq!(location("def f(x) { x }\nf(1) + len(f(2))"), (2, 8, 9));
+
+        // Compile errors point at the code that couldn't be compiled.
+        assert_eq!(location("def f(x) { x }\n1 + g(2)"), (2, 5, 4));
+        assert_eq!(location("def f(x) { x }\nf(1, 2)"), (2, 1, 7));
+        assert_eq!(location("def f(x) {\n  y = x\n  y\n}"), (2, 3, 1));
+        assert_eq!(location("def f(a, b, c, d, e, f, g) { g + 1 }"), (1, 30, 5));
+
+        let error = Evaluator::default().evaluate("1 +\n  g(2)").unwrap_err();
+        assert_eq!(error.to_string(), "g is not defined at line 2, column 3");
+    }
+
+    #[test]
+    fn test_semantic_errors() {
+        let error = |code| match Evaluator::default().evaluate(code) {
+            Err(error @ EvaluationError::CompilerError(CompilerError::CompileError(..))) => {
+                error.to_string()
+            }
+            result => panic!("expected a compile error but got {:?}", result),
+        };
+
+        assert_eq!(error("x + 1"), "x is not defined at line 1, column 1");
+        assert_eq!(
+            error("def f(x) { x }\nf + 1"),
+            "function 'f' cannot be used as a value at line 2, column 1"
+        );
+        assert_eq!(
+            error("1 + len"),
+            "function 'len' cannot be used as a value at line 1, column 5"
+        );
+        assert_eq!(
+            error("def f(x) { x }\nf(1, 2)"),
+            "function 'f' expects 1 parameter but 2 were given at line 2, column 1"
+        );
+        assert_eq!(
+            error("let x = 1\nx(2)"),
+            "'x' is not a function at line 2, column 1"
+        );
+        assert_eq!(
+            error("def g(a, b) { a }\ng(1)"),
+            "function 'g' expects 2 parameters but 1 was given at line 2, column 1"
+        );
+        assert_eq!(
+            error("def f(x, y, x) { x }"),
+            "argument 'x' is declared more than once at line 1, column 13"
+        );
+        assert_eq!(
+            error("def f(x) { x }\nf = 2"),
+            "cannot assign to function 'f' at line 2, column 1"
+        );
+        assert_eq!(
+            error("def f(x) { x }\ndef g(y) {\n  f = y\n}"),
+            "cannot assign to function 'f' at line 3, column 3"
+        );
+        assert_eq!(
+            error("if 1 < 2 { 1 } else { y }"),
+            "y is not defined at line 1, column 23"
+        );
+
+        // Variables declared in a branch can be used after it, as they can
+        // when compiled.
+        assert_eq!(eval("if 1 < 2 { let x = 1 }\nx + 1"), Value::Integer(2));
+
+        // Statements that used to be left to the compiler are fine at the
+        // top level too.
+        assert_eq!(eval("let x = 1\nx += 2\nx"), Value::Integer(3));
+        assert_eq!(
+            eval("let x = 0\nwhile x < 3 { x += 1 }\nx"),
+            Value::Integer(3)
+        );
+        assert_eq!(eval("return 5"), Value::Integer(5));
+    }
+
+    #[test]
+    fn test_analyze_program() {
+        use crate::{compiler, parser};
+
+        let errors = |source| {
+            let (block, _) = parser::parse_with_recovery(source, None);
+            compiler::analyze_program(&block)
+                .into_iter()
+                .map(|error| EvaluationError::CompilerError(error).to_string())
+                .collect::<Vec<_>>()
+        };
+
+        // Every error is found, not just the first, and statements that
+        // failed to parse are skipped.
+        assert_eq!(
+            errors("def f(a) { a }\nq = 4\nf(1, 2)\ng(3)\nlet = 1"),
+            vec![
+                "q is not defined at line 2, column 1",
+                "function 'f' expects 1 parameter but 2 were given at line 3, column 1",
+                "g is not defined at line 4, column 1",
+            ]
+        );
+
+        // Function bodies can call the functions defined before them and
+        // themselves, and the rest of the program can call any of them.
+        assert_eq!(
+            errors("def f(a) { g(a) }\ndef g(a, a) { f(a) + g(a, 1) + x }\ng(1, 2)"),
+            vec![
+                "g is not defined at line 1, column 12",
+                "argument 'a' is declared more than once at line 2, column 10",
+                "x is not defined at line 2, column 32",
+            ]
+        );
+        assert!(errors("def f(a) { a }\nlet x = f(1)\nx + len(\"a\")").is_empty());
+    }
+
+    #[test]
+    fn test_empty_block() {
+        use crate::{
+            compiler::{self, CompileError, StackFrame},
+            parser::{Block, Condition, Expression, Literal, Statement},
+            tests::node,
+        };
+
+        let block = Block(vec![node(Statement::Condition(Condition {
+            branches: vec![(
+                Some(node(Expression::Literal(Literal::Boolean(true)))),
+                Block(vec![]),
+            )],
+        }))]);
+
+        let result = compiler::compile(
+            &mut StackFrame::default(),
+            &block,
+            CompileOptions::default(),
+        );
+        assert!(matches!(
+            result,
+            Err(CompilerError::CompileError(
+                CompileError::EmptyBlock,
+                Some(_)
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_warnings() {
+        let mut evaluator = Evaluator::default();
+        assert_eq!(
+            evaluator.evaluate("def f(x, y) { x }\nf(1, 2)").unwrap(),
+            Value::Integer(1)
+        );
+
+        let warnings = evaluator
+            .take_warnings()
+            .into_iter()
+            .map(|warning| warning.value.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(warnings, vec!["argument 'y' is never used"]);
+        assert!(evaluator.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn test_identifiers_starting_with_keywords() {
+        assert_eq!(
+            eval("def define(letter) { letter }\nlet iffy = define(2)\niffy"),
+            Value::Integer(2)
+        );
+        assert_eq!(
+            eval("let returned = 1\nlet whiled = 2\nreturned = whiled + 1\nreturned"),
+            Value::Integer(3)
+        );
+        assert_eq!(eval("let trueish = 4\nreturn trueish"), Value::Integer(4));
+
+        assert!(matches!(
+            Evaluator::default().evaluate("let return = 1"),
+            Err(EvaluationError::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_index() {
+        assert_eq!(eval("\"héllo\"[1]"), Value::String("é".to_string()));
+        assert_eq!(
+            eval("def f(s) { s + \"!\" }\nf(\"ab\")[len(\"ab\")]"),
+            Value::String("!".to_string())
+        );
+        assert_eq!(
+            eval("char_at(\"abc\", 2)[0]"),
+            Value::String("c".to_string())
+        );
+        assert_eq!(
+            eval_runtime_error("\"abc\"[3]"),
+            RuntimeError::IndexOutOfRange(3, 3)
+        );
+        assert_eq!(
+            eval_runtime_error("5[0]"),
+            RuntimeError::InvalidArgument("char_at", ValueType::String, ValueType::Integer)
+        );
+    }
+
+    #[test]
+    fn test_bracket_spacing() {
+        assert_eq!(eval("1 + ( 2 * 3 )"), Value::Integer(7));
+        assert_eq!(eval("!( 1 < 2 ) || ~( 0 ) == -1"), Value::Boolean(true));
+        // A bracket on the next line starts a new statement rather than
+        // calling what came before.
+        assert_eq!(
+            eval("def f(x) { x }\nlet g = 1\ng\n(f(2))"),
+            Value::Integer(2)
+        );
+    }
+
+    #[test]
+    fn test_power() {
+        assert_eq!(eval("2 ** 10"), Value::Integer(1024));
+        assert_eq!(eval("2 ** 3 ** 2"), Value::Integer(512));
+        assert_eq!(eval("(2 ** 3) ** 2"), Value::Integer(64));
+        assert_eq!(eval("2 * 3 ** 2"), Value::Integer(18));
+        assert_eq!(eval("~2 ** 2"), Value::Integer(-5));
+        assert_eq!(eval("let x = 3\nx **= 2\nx"), Value::Integer(9));
+
+        // Constant exponents are unrolled, and others use a loop.
+        for exponent in ["0", "1", "5", "62", "100"] {
+            let constant = eval(&format!("def f(b) {{ b ** {exponent} }}\nf(2)"));
+            let variable = eval(&format!("def f(b, e) {{ b ** e }}\nf(2, {exponent})"));
+            assert_eq!(constant, variable);
+        }
+        assert_eq!(
+            eval("def f(b, e) { b ** e }\nf(2, 62)"),
+            Value::Integer(1 << 62)
+        );
+        assert_eq!(
+            eval("def f(b, e) { b ** e }\nf(2, 100)"),
+            Value::BigInteger(num_bigint::BigInt::from(1) << 100)
+        );
+        assert_eq!(
+            eval("def f(b, e) { b ** e }\nf(-3, 3) + f(-1, 63)"),
+            Value::Integer(-28)
+        );
+        assert_eq!(
+            eval("(1000000000 * 1000000000 * 1000000000) ** 2 / 10 ** 53"),
+            Value::Integer(10)
+        );
+
+        // The registers the loop borrows may be holding arguments.
+        assert_eq!(
+            eval("def f(a, b, c, d) { a ** b + d ** c }\nf(2, 5, 2, 7)"),
+            Value::Integer(81)
+        );
+
+        assert_eq!(
+            eval_runtime_error("def f(b, e) { b ** e }\nf(2, -1)"),
+            RuntimeError::InvalidExponent((-1).into())
+        );
+        assert_eq!(
+            eval_runtime_error("2 ** 100000"),
+            RuntimeError::InvalidExponent(100000.into())
+        );
+        assert_eq!(eval("1 ** 100000"), Value::Integer(1));
+        assert_eq!(
+            eval_runtime_error("\"a\" ** 2"),
+            RuntimeError::TypeMismatch(
+                ArithmeticOperator::Power,
+                ValueType::String,
+                ValueType::Integer
+            )
+        );
+    }
+
+    #[test]
+    fn test_statement_separators() {
+        let error = |code| Evaluator::default().evaluate(code).unwrap_err().to_string();
+
+        assert_eq!(eval("let x = 1; let y = 2; x + y"), Value::Integer(3));
+        assert_eq!(eval("let x = 1;\n;; x;"), Value::Integer(1));
+        assert_eq!(
+            eval("def f(x) { if x { 1 } else { 2 } } f(false)"),
+            Value::Integer(2)
+        );
+        assert_eq!(
+            error("let x = 0\nlet y = 0\nx = 1 y = 2"),
+            "expected `;` or a new line at line 3, column 7\n  |\n3 | x = 1 y = 2\n  |       ^"
+        );
+
+        // A line ending in an operator or an open bracket carries on to the
+        // next.
+        assert_eq!(
+            eval("def add(a, b) {\n  a +\n    b\n}\nadd(\n  1,\n  2\n) * (\n  3\n)"),
+            Value::Integer(9)
+        );
+        // An operator at the start of a line doesn't continue the one
+        // before it.
+        assert!(error("let x = 1\n+ 2").starts_with("unexpected input at line 2, column 1\n"));
+    }
+
+    #[test]
+    fn test_recursion() {
+        assert_eq!(
+            eval("def fact(n) { if n == 0 { return 1 } n * fact(n - 1) }\nfact(20)"),
+            Value::Integer(2432902008176640000)
+        );
+        assert_eq!(
+            eval("def gcd(a, b) { if b == 0 { return a } gcd(b, a % b) }\ngcd(1071, 462)"),
+            Value::Integer(21)
+        );
+    }
+
+    #[test]
+    fn test_tail_calls() {
+        // Deep enough to overflow the stack if every call took a frame.
+        assert_eq!(
+            eval(
+                "def count(n, total) {
+                    if n == 0 { return total }
+                    return count(n - 1, total + 1)
+                }
+                count(1000000, 0)"
+            ),
+            Value::Integer(1000000)
+        );
+        assert_eq!(
+            eval(
+                "def down(n) {
+                    let next = n - 1
+                    if n == 0 { return \"done\" }
+                    down(next)
+                }
+                down(1000000)"
+            ),
+            Value::String("done".to_string())
+        );
+
+        // The ends of the branches of a condition that ends a function are in
+        // tail position too.
+        assert_eq!(
+            eval("def f(n) { if n == 0 { 0 } else { f(n - 1) } } f(1000000)"),
+            Value::Integer(0)
+        );
+        assert_eq!(
+            eval(
+                "def f(n) {
+                    if n > 10 {
+                        if n % 2 == 0 { f(n - 1) } else { f(n - 3) }
+                    } else {
+                        n
+                    }
+                }
+                f(1000000)"
+            ),
+            Value::Integer(8)
+        );
+
+        // Calls to other functions, which take over the caller's frame.
+        assert_eq!(
+            eval(
+                "def add(a, b) { a + b }
+                def f(x) { let y = x * 10; add(y, x) }
+                f(4)"
+            ),
+            Value::Integer(44)
+        );
+        assert_eq!(
+            eval_runtime_error("def g(x) { x + \"a\" }\ndef f(x) { return g(x) }\nf(1)"),
+            RuntimeError::TypeMismatch(
+                ArithmeticOperator::Add,
+                ValueType::Integer,
+                ValueType::String
+            )
+        );
+    }
+
+    #[test]
+    fn test_stack_overflow() {
+        let mut evaluator = Evaluator::default();
+        let error = evaluator
+            .evaluate("def f(n) { 1 + f(n + 1) }\nf(0)")
+            .unwrap_err();
+        assert!(matches!(
+            &error,
+            EvaluationError::RuntimeError(RuntimeError::StackOverflow(name), _) if name.0 == "f"
+        ));
+        assert_eq!(
+            error.to_string(),
+            "maximum recursion depth exceeded in 'f' at line 1, column 16"
+        );
+
+        // The evaluator can carry on afterwards.
+        assert_eq!(evaluator.evaluate("1 + 2").unwrap(), Value::Integer(3));
+
+        let depth = "def depth(n) { if n == 0 { return 0 } 1 + depth(n - 1) }\n\
+                     def count(n) { if n == 0 { return 0 } count(n - 1) }\n";
+        assert_eq!(eval(&format!("{depth}depth(1000)")), Value::Integer(1000));
+
+        let mut evaluator = Evaluator::default();
+        evaluator.set_max_stack_size(64 * 1024);
+        assert!(matches!(
+            evaluator.evaluate(&format!("{depth}depth(1000)")),
+            Err(EvaluationError::RuntimeError(
+                RuntimeError::StackOverflow(_),
+                _
+            ))
+        ));
+        // Tail calls don't use any more stack however deep they go.
+        assert_eq!(
+            evaluator
+                .evaluate(&format!("{depth}count(100000)"))
+                .unwrap(),
+            Value::Integer(0)
+        );
+    }
 }
@@ -1,9 +1,8 @@
-use std::convert::{Into, TryFrom, TryInto};
+use std::convert::{Into, TryFrom};
 
-#[derive(Clone, Copy, Debug)]
-pub enum ValueEncodeError {
-    Overflow,
-}
+use num_bigint::BigInt;
+
+use crate::heap::{self, HeapObject};
 
 #[derive(Clone, Copy, Debug)]
 pub enum ValueDecodeError {
@@ -11,12 +10,13 @@ pub enum ValueDecodeError {
     InvalidBoolean(u64),
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[repr(i64)]
 pub enum ValueType {
     Integer,
     String,
     Boolean,
+    BigInteger,
 }
 
 impl TryFrom<u64> for ValueType {
@@ -27,6 +27,7 @@ impl TryFrom<u64> for ValueType {
             0 => Ok(ValueType::Integer),
             1 => Ok(ValueType::String),
             2 => Ok(ValueType::Boolean),
+            3 => Ok(ValueType::BigInteger),
             _ => Err(ValueDecodeError::UnknownType(type_number)),
         }
     }
@@ -38,6 +39,17 @@ impl From<ValueType> for u64 {
             ValueType::Integer => 0,
             ValueType::String => 1,
             ValueType::Boolean => 2,
+            ValueType::BigInteger => 3,
+        }
+    }
+}
+
+impl std::fmt::Display for ValueType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValueType::Integer | ValueType::BigInteger => write!(f, "integer"),
+            ValueType::String => write!(f, "string"),
+            ValueType::Boolean => write!(f, "boolean"),
         }
     }
 }
@@ -47,6 +59,29 @@ pub enum Value {
     Integer(i64),
     String(String),
     Boolean(bool),
+    // Only used for integers that don't fit in an i64, so that every integer
+    // has exactly one representation.
+    BigInteger(BigInt),
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Integer(value) => write!(f, "{}", value),
+            Value::String(value) => write!(f, "{}", value),
+            Value::Boolean(value) => write!(f, "{}", value),
+            Value::BigInteger(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl From<BigInt> for Value {
+    fn from(value: BigInt) -> Self {
+        match i64::try_from(&value) {
+            Ok(value) => Value::Integer(value),
+            Err(_) => Value::BigInteger(value),
+        }
+    }
 }
 
 impl From<Value> for ValueType {
@@ -55,71 +90,188 @@ impl From<Value> for ValueType {
             Value::Integer(_) => ValueType::Integer,
             Value::String(_) => ValueType::String,
             Value::Boolean(_) => ValueType::Boolean,
+            Value::BigInteger(_) => ValueType::BigInteger,
         }
     }
 }
 
-// Note: this should not derive Copy or Clone, because decoding a String will drop it.
+// Values are encoded into a single machine word with the type stored in the
+// low `TAG_BITS` bits. Integers use a tag of zero and keep their value in the
+// remaining high bits, which means two encoded integers can be added or
+// subtracted directly and the CPU's overflow flag tells us when the result no
+// longer fits. Heap values store a pointer, which is always aligned to at
+// least 8 bytes, so the tag fits in the bits the alignment leaves free. The
+// objects themselves are owned by the heap, so encoded values can be copied
+// freely.
 #[derive(Debug)]
 #[repr(transparent)]
 pub struct EncodedValue(u64);
 
 impl EncodedValue {
-    const VALUE_BITS: u64 = 53;
-    const VALUE_MASK: u64 = (1 << Self::VALUE_BITS) - 1;
-    const TYPE_MASK: u64 = !Self::VALUE_MASK;
+    pub const TAG_BITS: u64 = 3;
+    pub const TAG_MASK: u64 = (1 << Self::TAG_BITS) - 1;
+
+    pub const INTEGER_MIN: i64 = i64::MIN >> Self::TAG_BITS;
+    pub const INTEGER_MAX: i64 = i64::MAX >> Self::TAG_BITS;
+
+    // Returned by generated code in place of a value when a runtime error was
+    // raised. The error itself is held by the runtime until it is taken.
+    pub const RUNTIME_ERROR: u64 = Self::TAG_MASK;
+
+    pub const FALSE: u64 = 2;
+    pub const TRUE: u64 = (1 << Self::TAG_BITS) | 2;
 
     // Returns the encoded value.
     //
-    // This is unsafe and consumes the value, because it requires whoever
-    // calls it to ensure that the value ends up being decoded again so it
-    // gets properly dropped. If not then memory will leak.
-    //
-    // It also must only be decoded exactly once, or else the memory will be
-    // dropped more than once which will crash the program.
+    // This is unsafe because the result may point to a heap object, and
+    // nothing stops that object from going away once this value has.
     pub unsafe fn encoded_value(self) -> u64 {
         self.0
     }
+
+    // Wraps a value that was encoded by generated code.
+    //
+    // This is unsafe because the value must have come from `encoded_value`,
+    // and any heap object it points to must still be alive.
+    pub unsafe fn from_encoded_value(value: u64) -> Self {
+        EncodedValue(value)
+    }
+
+    pub fn is_runtime_error(&self) -> bool {
+        self.0 == Self::RUNTIME_ERROR
+    }
+
+    pub fn value_type(&self) -> Result<ValueType, ValueDecodeError> {
+        ValueType::try_from(self.0 & Self::TAG_MASK)
+    }
+
+    // Returns the integer held by this value, whether it's stored inline or
+    // on the heap, without consuming it.
+    pub fn to_big_integer(&self) -> Option<BigInt> {
+        match self.value_type() {
+            Ok(ValueType::Integer) => Some(BigInt::from(self.inline_integer())),
+            Ok(ValueType::BigInteger) => match self.heap_object() {
+                HeapObject::BigInteger(value) => Some(value.clone()),
+                object => panic!("big integer points to {object:?}"),
+            },
+            _ => None,
+        }
+    }
+
+    // Borrows the string held by this value without consuming it.
+    pub fn as_str(&self) -> Option<&str> {
+        match self.value_type() {
+            Ok(ValueType::String) => match self.heap_object() {
+                HeapObject::String(string) => Some(string),
+                object => panic!("string points to {object:?}"),
+            },
+            _ => None,
+        }
+    }
+
+    pub fn is_true(&self) -> bool {
+        self.0 == Self::TRUE
+    }
+
+    // Encodes a constant that's baked into generated code. Heap values are
+    // allocated as constants, so they're shared and never freed.
+    pub fn constant(value: &Value) -> Self {
+        match value {
+            Value::String(s) => Self::encode(
+                heap::allocate_constant(HeapObject::String(s.clone())),
+                ValueType::String,
+            ),
+            Value::BigInteger(value) => Self::encode(
+                heap::allocate_constant(HeapObject::BigInteger(value.clone())),
+                ValueType::BigInteger,
+            ),
+            value => value.into(),
+        }
+    }
+
+    // The address of the heap object this value points to, if it's a heap
+    // value.
+    pub fn heap_address(&self) -> Option<u64> {
+        match self.value_type() {
+            Ok(ValueType::String | ValueType::BigInteger) => Some(self.payload()),
+            _ => None,
+        }
+    }
+
+    fn heap_object(&self) -> &HeapObject {
+        unsafe { heap::object(self.payload()) }
+    }
+
+    fn inline_integer(&self) -> i64 {
+        (self.0 as i64) >> Self::TAG_BITS
+    }
+
+    fn payload(&self) -> u64 {
+        self.0 & !Self::TAG_MASK
+    }
+
+    fn encode(payload: u64, value_type: ValueType) -> Self {
+        debug_assert!(payload & Self::TAG_MASK == 0);
+        EncodedValue(payload | Into::<u64>::into(value_type))
+    }
+
+    fn encode_inline_integer(value: i64) -> Option<Self> {
+        if (Self::INTEGER_MIN..=Self::INTEGER_MAX).contains(&value) {
+            Some(Self::encode(
+                (value << Self::TAG_BITS) as u64,
+                ValueType::Integer,
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+// Integers that fit are stored inline, and anything bigger is moved to the
+// heap.
+impl From<BigInt> for EncodedValue {
+    fn from(value: BigInt) -> Self {
+        if let Some(encoded) = i64::try_from(&value)
+            .ok()
+            .and_then(Self::encode_inline_integer)
+        {
+            return encoded;
+        }
+
+        Self::encode(
+            heap::allocate(HeapObject::BigInteger(value)),
+            ValueType::BigInteger,
+        )
+    }
 }
 
 impl From<String> for EncodedValue {
     fn from(s: String) -> Self {
         let value = Value::String(s);
-        value.try_into().expect("failed to encode string value")
+        value.into()
     }
 }
 
-impl TryFrom<Value> for EncodedValue {
-    type Error = ValueEncodeError;
-
-    fn try_from(value: Value) -> Result<Self, Self::Error> {
-        EncodedValue::try_from(&value)
+impl From<Value> for EncodedValue {
+    fn from(value: Value) -> Self {
+        EncodedValue::from(&value)
     }
 }
 
-impl TryFrom<&Value> for EncodedValue {
-    type Error = ValueEncodeError;
-
-    fn try_from(value: &Value) -> Result<Self, Self::Error> {
-        let (value, value_type) = match value {
-            Value::Integer(value) => (*value as u64, ValueType::Integer),
-            Value::String(s) => {
-                let boxed_str = Box::new(s.clone());
-                let str_ref = Box::<String>::leak(boxed_str);
-                let ptr = str_ref as *mut String;
-                (ptr as u64, ValueType::String)
+impl From<&Value> for EncodedValue {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Integer(value) => {
+                Self::encode_inline_integer(*value).unwrap_or_else(|| BigInt::from(*value).into())
             }
-            Value::Boolean(true) => (1, ValueType::Boolean),
-            Value::Boolean(false) => (0, ValueType::Boolean),
-        };
-
-        if value & Self::TYPE_MASK != 0 {
-            return Err(ValueEncodeError::Overflow);
+            Value::String(s) => Self::encode(
+                heap::allocate(HeapObject::String(s.clone())),
+                ValueType::String,
+            ),
+            Value::Boolean(true) => EncodedValue(Self::TRUE),
+            Value::Boolean(false) => EncodedValue(Self::FALSE),
+            Value::BigInteger(value) => value.clone().into(),
         }
-
-        let type_code = Into::<u64>::into(value_type) << Self::VALUE_BITS;
-        let encoded = value | type_code;
-        Ok(EncodedValue(encoded))
     }
 }
 
@@ -128,17 +280,20 @@ impl TryFrom<EncodedValue> for Value {
 
     fn try_from(encoded: EncodedValue) -> Result<Self, Self::Error> {
         println!("Decoding value: {encoded:?}");
-        let type_number = encoded.0 >> EncodedValue::VALUE_BITS;
-        let value = (encoded.0 & EncodedValue::VALUE_MASK) as i64;
-        match ValueType::try_from(type_number)? {
-            ValueType::Integer => Ok(Value::Integer(value)),
-            ValueType::String => {
-                let ptr = value as u64 as *mut String;
-                let boxed_str = unsafe { Box::from_raw(ptr) };
-                let string = *boxed_str;
-                Ok(Value::String(string))
-            }
-            ValueType::Boolean => match value {
+        let payload = encoded.payload();
+        match encoded.value_type()? {
+            ValueType::Integer => Ok(Value::Integer(encoded.inline_integer())),
+            ValueType::BigInteger => Ok(encoded
+                .to_big_integer()
+                .expect("big integer could not be decoded")
+                .into()),
+            ValueType::String => Ok(Value::String(
+                encoded
+                    .as_str()
+                    .expect("string could not be decoded")
+                    .to_owned(),
+            )),
+            ValueType::Boolean => match payload >> EncodedValue::TAG_BITS {
                 0 => Ok(Value::Boolean(false)),
                 1 => Ok(Value::Boolean(true)),
                 _ => Err(ValueDecodeError::InvalidBoolean(encoded.0)),
@@ -146,3 +301,21 @@ impl TryFrom<EncodedValue> for Value {
         }
     }
 }
+
+#[test]
+fn test_integer_round_trip() {
+    let values = [
+        Value::Integer(0),
+        Value::Integer(-1),
+        Value::Integer(EncodedValue::INTEGER_MAX),
+        Value::Integer(EncodedValue::INTEGER_MIN),
+        Value::Integer(i64::MAX),
+        Value::Integer(i64::MIN),
+        Value::BigInteger(BigInt::from(i64::MAX) * 1000),
+    ];
+
+    for value in values {
+        let encoded = EncodedValue::from(&value);
+        assert_eq!(Value::try_from(encoded).unwrap(), value);
+    }
+}